@@ -0,0 +1,189 @@
+/// Timer periods in CPU cycles (NTSC).
+const RATE_TABLE: [u16; 16] = [428, 380, 340, 320, 286, 254, 226, 214, 190, 160, 142, 128, 106, 84, 72, 54];
+
+const MASK_IRQ_ENABLED: u8 = 0b1000_0000;
+const MASK_LOOP: u8 = 0b0100_0000;
+const MASK_RATE: u8 = 0b0000_1111;
+const MASK_OUTPUT_LEVEL: u8 = 0b0111_1111;
+
+/// Delta modulation channel playing 1-bit delta-encoded samples from CPU memory.
+///
+/// The channel cannot access memory itself. When the sample buffer is empty it requests
+/// a DMA transfer, which the CPU services by stalling and reading the byte for it.
+///
+/// Useful links:
+/// [Nesdev wiki - APU DMC]
+///
+/// [Nesdev wiki - APU DMC]: https://www.nesdev.org/wiki/APU_DMC
+pub struct Dmc {
+    irq_enabled: bool,
+    looping: bool,
+    timer_period: u16,
+    timer: u16,
+    output_level: u8,
+    sample_address: u16,
+    sample_length: u16,
+    current_address: u16,
+    bytes_remaining: u16,
+    sample_buffer: Option<u8>,
+    shift_register: u8,
+    bits_remaining: u8,
+    silence: bool,
+    pub interrupt: bool,
+}
+
+impl Default for Dmc {
+    fn default() -> Self {
+        Dmc {
+            irq_enabled: false,
+            looping: false,
+            timer_period: RATE_TABLE[0],
+            timer: 0,
+            output_level: 0,
+            sample_address: 0xC000,
+            sample_length: 1,
+            current_address: 0xC000,
+            bytes_remaining: 0,
+            sample_buffer: None,
+            shift_register: 0,
+            bits_remaining: 8,
+            silence: true,
+            interrupt: false,
+        }
+    }
+}
+
+impl Dmc {
+    /// Writes to one of the channel registers. `register` is the address offset 0-3.
+    pub fn write_register(&mut self, register: u16, value: u8) {
+        match register {
+            0 => {
+                self.irq_enabled = value & MASK_IRQ_ENABLED != 0;
+                self.looping = value & MASK_LOOP != 0;
+                self.timer_period = RATE_TABLE[(value & MASK_RATE) as usize];
+                if !self.irq_enabled {
+                    self.interrupt = false;
+                }
+            },
+            1 => self.output_level = value & MASK_OUTPUT_LEVEL,
+            2 => self.sample_address = 0xC000 | ((value as u16) << 6),
+            3 => self.sample_length = ((value as u16) << 4) | 1,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Handles the DMC bit of a status register write.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.interrupt = false;
+        if !enabled {
+            self.bytes_remaining = 0;
+        } else if self.bytes_remaining == 0 {
+            self.restart();
+        }
+    }
+
+    /// The Reset button clears the upper 6 bits of the output level.
+    pub fn reset(&mut self) {
+        self.output_level &= 1;
+    }
+
+    fn restart(&mut self) {
+        self.current_address = self.sample_address;
+        self.bytes_remaining = self.sample_length;
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.bytes_remaining > 0
+    }
+
+    /// Returns the address of the next sample byte if the memory reader needs one.
+    pub fn dma_request(&self) -> Option<u16> {
+        if self.sample_buffer.is_none() && self.bytes_remaining > 0 {
+            Some(self.current_address)
+        } else {
+            None
+        }
+    }
+
+    /// Fills the sample buffer with a byte fetched by the CPU for the pending DMA request.
+    pub fn complete_dma(&mut self, value: u8) {
+        self.sample_buffer = Some(value);
+        self.current_address = self.current_address.checked_add(1).unwrap_or(0x8000);
+        self.bytes_remaining -= 1;
+        if self.bytes_remaining == 0 {
+            if self.looping {
+                self.restart();
+            } else if self.irq_enabled {
+                self.interrupt = true;
+            }
+        }
+    }
+
+    /// Clocked every CPU cycle.
+    pub fn clock_timer(&mut self) {
+        if self.timer > 0 {
+            self.timer -= 1;
+            return;
+        }
+        self.timer = self.timer_period - 1;
+
+        if !self.silence {
+            if self.shift_register & 1 != 0 {
+                if self.output_level <= 125 {
+                    self.output_level += 2;
+                }
+            } else if self.output_level >= 2 {
+                self.output_level -= 2;
+            }
+        }
+        self.shift_register >>= 1;
+
+        self.bits_remaining -= 1;
+        if self.bits_remaining == 0 {
+            self.bits_remaining = 8;
+            match self.sample_buffer.take() {
+                Some(sample) => {
+                    self.silence = false;
+                    self.shift_register = sample;
+                },
+                None => self.silence = true,
+            }
+        }
+    }
+
+    pub fn output(&self) -> u8 {
+        self.output_level
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dmc_dma_and_irq() -> Result<(), std::io::Error> {
+        let mut dmc = Dmc::default();
+        dmc.write_register(0, 0x80); // IRQ enabled, no loop
+        dmc.write_register(2, 0xFF); // Address 0xFFC0
+        dmc.write_register(3, 0x04); // Length 65 bytes
+        assert_eq!(dmc.dma_request(), None);
+
+        dmc.set_enabled(true);
+        for i in 0..65u16 {
+            assert_eq!(dmc.dma_request(), Some(0xFFC0u16.wrapping_add(i).max(0x8000)));
+            dmc.complete_dma(0xFF);
+            assert_eq!(dmc.dma_request(), None);
+            // The output unit empties the sample buffer after playing the previous 8 bits
+            for _ in 0..8 * RATE_TABLE[0] {
+                dmc.clock_timer();
+            }
+        }
+        assert!(!dmc.is_active());
+        assert!(dmc.interrupt);
+
+        // Any write to the status register acknowledges the interrupt
+        dmc.set_enabled(false);
+        assert!(!dmc.interrupt);
+        Ok(())
+    }
+}
@@ -0,0 +1,64 @@
+const MASK_LOOP: u8 = 0b0010_0000;
+const MASK_CONSTANT_VOLUME: u8 = 0b0001_0000;
+const MASK_VOLUME: u8 = 0b0000_1111;
+
+/// Volume envelope shared by the pulse and noise channels.
+///
+/// Produces either a constant volume or a decaying saw envelope.
+///
+/// Useful links:
+/// [Nesdev wiki - APU Envelope]
+///
+/// [Nesdev wiki - APU Envelope]: https://www.nesdev.org/wiki/APU_Envelope
+#[derive(Default)]
+pub struct Envelope {
+    start: bool,
+    looping: bool,
+    constant_volume: bool,
+    volume: u8,
+    divider: u8,
+    decay_level: u8,
+}
+
+impl Envelope {
+    /// Updates the envelope from the low 6 bits of a channel's first register.
+    pub fn write_control(&mut self, value: u8) {
+        self.looping = value & MASK_LOOP != 0;
+        self.constant_volume = value & MASK_CONSTANT_VOLUME != 0;
+        self.volume = value & MASK_VOLUME;
+    }
+
+    /// Restarts the envelope on the next quarter frame.
+    pub fn restart(&mut self) {
+        self.start = true;
+    }
+
+    /// Clocked by the frame counter every quarter frame.
+    pub fn clock(&mut self) {
+        if self.start {
+            self.start = false;
+            self.decay_level = 15;
+            self.divider = self.volume;
+            return;
+        }
+
+        if self.divider == 0 {
+            self.divider = self.volume;
+            if self.decay_level > 0 {
+                self.decay_level -= 1;
+            } else if self.looping {
+                self.decay_level = 15;
+            }
+        } else {
+            self.divider -= 1;
+        }
+    }
+
+    pub fn output(&self) -> u8 {
+        if self.constant_volume {
+            self.volume
+        } else {
+            self.decay_level
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use std::f32::consts::PI;
+
+/// First-order filter approximating the analog filters on the NES audio output.
+///
+/// Useful links:
+/// [Nesdev wiki - APU Mixer]
+///
+/// [Nesdev wiki - APU Mixer]: https://www.nesdev.org/wiki/APU_Mixer
+pub enum Filter {
+    HighPass { alpha: f32, previous_input: f32, previous_output: f32 },
+    LowPass { alpha: f32, previous_output: f32 },
+}
+
+impl Filter {
+    pub fn high_pass(sample_rate: f32, cutoff: f32) -> Filter {
+        let rc = 1.0 / (2.0 * PI * cutoff);
+        let dt = 1.0 / sample_rate;
+        Filter::HighPass { alpha: rc / (rc + dt), previous_input: 0.0, previous_output: 0.0 }
+    }
+
+    pub fn low_pass(sample_rate: f32, cutoff: f32) -> Filter {
+        let rc = 1.0 / (2.0 * PI * cutoff);
+        let dt = 1.0 / sample_rate;
+        Filter::LowPass { alpha: dt / (rc + dt), previous_output: 0.0 }
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        match self {
+            Filter::HighPass { alpha, previous_input, previous_output } => {
+                *previous_output = *alpha * (*previous_output + input - *previous_input);
+                *previous_input = input;
+                *previous_output
+            },
+            Filter::LowPass { alpha, previous_output } => {
+                *previous_output += *alpha * (input - *previous_output);
+                *previous_output
+            },
+        }
+    }
+}
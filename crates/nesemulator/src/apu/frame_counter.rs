@@ -0,0 +1,128 @@
+const MASK_FIVE_STEP_MODE: u8 = 0b1000_0000;
+const MASK_IRQ_INHIBIT: u8 = 0b0100_0000;
+
+/// Signal sent by the frame counter to the channels on a given CPU cycle.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FrameClock {
+    None,
+    /// Clocks envelopes and the triangle's linear counter.
+    QuarterFrame,
+    /// Clocks everything clocked on a quarter frame, plus length counters and sweep units.
+    HalfFrame,
+}
+
+/// Frame counter (also called frame sequencer) that clocks the channels' low frequency units
+/// and raises the frame IRQ in 4-step mode.
+///
+/// Step timings are counted in CPU cycles (NTSC).
+///
+/// Useful links:
+/// [Nesdev wiki - APU Frame Counter]
+///
+/// [Nesdev wiki - APU Frame Counter]: https://www.nesdev.org/wiki/APU_Frame_Counter
+#[derive(Default)]
+pub struct FrameCounter {
+    five_step_mode: bool,
+    irq_inhibit: bool,
+    pub interrupt: bool,
+    cycle: u32,
+    /// CPU cycles until a written mode takes effect and the sequence restarts.
+    reset_delay: Option<u8>,
+}
+
+impl FrameCounter {
+    /// Handles a write to $4017. The write takes effect 3 or 4 CPU cycles later
+    /// depending on whether it happened on an odd CPU cycle.
+    pub fn write(&mut self, value: u8, odd_cycle: bool) {
+        self.five_step_mode = value & MASK_FIVE_STEP_MODE != 0;
+        self.irq_inhibit = value & MASK_IRQ_INHIBIT != 0;
+        if self.irq_inhibit {
+            self.interrupt = false;
+        }
+        self.reset_delay = Some(if odd_cycle { 4 } else { 3 });
+    }
+
+    /// Restarts the sequence in the current mode as if the last value was written to $4017 again.
+    pub fn reset(&mut self) {
+        self.interrupt = false;
+        self.reset_delay = Some(3);
+    }
+
+    /// Clocked every CPU cycle.
+    pub fn step(&mut self) -> FrameClock {
+        if let Some(delay) = self.reset_delay.as_mut() {
+            *delay -= 1;
+            if *delay == 0 {
+                self.reset_delay = None;
+                self.cycle = 0;
+                // Entering 5-step mode clocks the units immediately
+                return if self.five_step_mode { FrameClock::HalfFrame } else { FrameClock::None };
+            }
+        }
+
+        self.cycle += 1;
+        match (self.cycle, self.five_step_mode) {
+            (7457, _) | (22371, _) => FrameClock::QuarterFrame,
+            (14913, _) => FrameClock::HalfFrame,
+            (29828, false) => {
+                self.raise_interrupt();
+                FrameClock::None
+            },
+            (29829, false) => {
+                self.raise_interrupt();
+                FrameClock::HalfFrame
+            },
+            (29830, false) => {
+                self.raise_interrupt();
+                self.cycle = 0;
+                FrameClock::None
+            },
+            (37281, true) => FrameClock::HalfFrame,
+            (37282, true) => {
+                self.cycle = 0;
+                FrameClock::None
+            },
+            _ => FrameClock::None,
+        }
+    }
+
+    fn raise_interrupt(&mut self) {
+        if !self.irq_inhibit {
+            self.interrupt = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_counter_four_step_sequence() -> Result<(), std::io::Error> {
+        let mut frame_counter = FrameCounter::default();
+        let clocks: Vec<(u32, FrameClock)> = (1..=29830)
+            .map(|cycle| (cycle, frame_counter.step()))
+            .filter(|(_, clock)| *clock != FrameClock::None)
+            .collect();
+        assert_eq!(clocks, vec![
+            (7457, FrameClock::QuarterFrame),
+            (14913, FrameClock::HalfFrame),
+            (22371, FrameClock::QuarterFrame),
+            (29829, FrameClock::HalfFrame),
+        ]);
+        assert!(frame_counter.interrupt);
+
+        // Inhibiting clears the flag and switching to 5-step mode clocks immediately after the delay
+        frame_counter.write(0xC0, false);
+        assert!(!frame_counter.interrupt);
+        assert_eq!(frame_counter.step(), FrameClock::None);
+        assert_eq!(frame_counter.step(), FrameClock::None);
+        assert_eq!(frame_counter.step(), FrameClock::HalfFrame);
+        let interrupt_raised = (0..37282).any(|_| {
+            frame_counter.step();
+            frame_counter.interrupt
+        });
+        assert!(!interrupt_raised);
+        Ok(())
+    }
+}
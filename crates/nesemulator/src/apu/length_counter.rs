@@ -0,0 +1,80 @@
+const LENGTH_TABLE: [u8; 32] = [
+    10, 254, 20,  2, 40,  4, 80,  6, 160,  8, 60, 10, 14, 12, 26, 14,
+    12,  16, 24, 18, 48, 20, 96, 22, 192, 24, 72, 26, 16, 28, 32, 30,
+];
+
+/// Length counter that silences a channel after a given time.
+///
+/// Useful links:
+/// [Nesdev wiki - APU Length Counter]
+///
+/// [Nesdev wiki - APU Length Counter]: https://www.nesdev.org/wiki/APU_Length_Counter
+#[derive(Default)]
+pub struct LengthCounter {
+    enabled: bool,
+    halt: bool,
+    counter: u8,
+}
+
+impl LengthCounter {
+    /// Enables or disables the counter through the status register. Disabling clears the counter.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.counter = 0;
+        }
+    }
+
+    pub fn set_halt(&mut self, halt: bool) {
+        self.halt = halt;
+    }
+
+    /// Loads the counter using the 5-bit index from the upper bits of a channel's last register.
+    pub fn load(&mut self, value: u8) {
+        if self.enabled {
+            self.counter = LENGTH_TABLE[(value >> 3) as usize];
+        }
+    }
+
+    /// Clocked by the frame counter every half frame.
+    pub fn clock(&mut self) {
+        if !self.halt && self.counter > 0 {
+            self.counter -= 1;
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.counter > 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_length_counter_load_and_clock() -> Result<(), std::io::Error> {
+        let mut length_counter = LengthCounter::default();
+
+        // Loading is ignored while disabled
+        length_counter.load(0b0000_1000);
+        assert!(!length_counter.is_active());
+
+        length_counter.set_enabled(true);
+        length_counter.load(0b0001_1000); // Index 3: length 2
+        length_counter.clock();
+        assert!(length_counter.is_active());
+        length_counter.clock();
+        assert!(!length_counter.is_active());
+
+        // Halt stops the counter and disabling clears it
+        length_counter.load(0b0001_1000);
+        length_counter.set_halt(true);
+        length_counter.clock();
+        length_counter.clock();
+        assert!(length_counter.is_active());
+        length_counter.set_enabled(false);
+        assert!(!length_counter.is_active());
+        Ok(())
+    }
+}
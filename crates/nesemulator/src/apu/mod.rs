@@ -0,0 +1,297 @@
+mod dmc;
+mod envelope;
+mod filter;
+mod frame_counter;
+mod length_counter;
+mod noise;
+mod pulse;
+mod triangle;
+
+use dmc::Dmc;
+use filter::Filter;
+use frame_counter::{FrameClock, FrameCounter};
+use noise::Noise;
+use pulse::Pulse;
+use triangle::Triangle;
+
+/// CPU clock rate (NTSC) in Hz. The APU is clocked at the same rate.
+pub const CPU_CLOCK_RATE: u32 = 1_789_773;
+pub const DEFAULT_SAMPLE_RATE: u32 = 44_100;
+
+/// The audio processing unit of the 2A03.
+///
+/// Contains two pulse channels, a triangle channel, a noise channel and a delta modulation channel (DMC).
+/// The channels are mixed with the non-linear mixer, filtered and resampled to the host sample rate.
+///
+/// Useful links:
+/// [Nesdev wiki - APU]
+/// [Nesdev wiki - APU Mixer]
+///
+/// [Nesdev wiki - APU]: https://www.nesdev.org/wiki/APU
+/// [Nesdev wiki - APU Mixer]: https://www.nesdev.org/wiki/APU_Mixer
+pub struct Apu {
+    pulse1: Pulse,
+    pulse2: Pulse,
+    triangle: Triangle,
+    noise: Noise,
+    dmc: Dmc,
+    frame_counter: FrameCounter,
+    cycle: u64,
+    sample_rate: u32,
+    /// Advances by the sample rate each CPU cycle. A sample is output when it reaches the CPU clock rate.
+    sample_clock: u32,
+    sample_sum: f32,
+    sample_count: u32,
+    filters: [Filter; 3],
+    samples: Vec<f32>,
+}
+
+impl Default for Apu {
+    fn default() -> Self {
+        Apu::new(DEFAULT_SAMPLE_RATE)
+    }
+}
+
+impl Apu {
+    /// Creates an APU with given output sample rate, clamped to range 1..=CPU_CLOCK_RATE.
+    pub fn new(sample_rate: u32) -> Apu {
+        let sample_rate = sample_rate.clamp(1, CPU_CLOCK_RATE);
+        Apu {
+            pulse1: Pulse::new(true),
+            pulse2: Pulse::new(false),
+            triangle: Triangle::default(),
+            noise: Noise::default(),
+            dmc: Dmc::default(),
+            frame_counter: FrameCounter::default(),
+            cycle: 0,
+            sample_rate,
+            sample_clock: 0,
+            sample_sum: 0.0,
+            sample_count: 0,
+            filters: Apu::create_filters(sample_rate),
+            samples: Vec::new(),
+        }
+    }
+
+    /// The NES has two high-pass filters (90 Hz and 440 Hz) and a low-pass filter (14 kHz) on its output.
+    fn create_filters(sample_rate: u32) -> [Filter; 3] {
+        let sample_rate = sample_rate as f32;
+        [
+            Filter::high_pass(sample_rate, 90.0),
+            Filter::high_pass(sample_rate, 440.0),
+            Filter::low_pass(sample_rate, 14_000.0),
+        ]
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Changes the output sample rate. Samples that have not been taken yet are discarded.
+    ///
+    /// The rate is clamped to range 1..=CPU_CLOCK_RATE, since at most one sample is output per CPU cycle.
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        let sample_rate = sample_rate.clamp(1, CPU_CLOCK_RATE);
+        self.sample_rate = sample_rate;
+        self.sample_clock = 0;
+        self.sample_sum = 0.0;
+        self.sample_count = 0;
+        self.filters = Apu::create_filters(sample_rate);
+        self.samples.clear();
+    }
+
+    /// Returns the samples output since the last call.
+    ///
+    /// Samples are mono `f32` values roughly in range [-1.0, 1.0] at the configured sample rate.
+    pub fn take_samples(&mut self) -> Vec<f32> {
+        std::mem::take(&mut self.samples)
+    }
+
+    /// Handles a write to one of the APU registers $4000-$4013, $4015 or $4017.
+    pub fn write_register(&mut self, address: u16, value: u8) {
+        match address {
+            0x4000..=0x4003 => self.pulse1.write_register(address - 0x4000, value),
+            0x4004..=0x4007 => self.pulse2.write_register(address - 0x4004, value),
+            0x4008..=0x400B => self.triangle.write_register(address - 0x4008, value),
+            0x400C..=0x400F => self.noise.write_register(address - 0x400C, value),
+            0x4010..=0x4013 => self.dmc.write_register(address - 0x4010, value),
+            0x4015 => self.write_status(value),
+            0x4017 => self.frame_counter.write(value, self.cycle & 1 == 1),
+            _ => panic!("APU: unknown register {:#06X}", address),
+        }
+    }
+
+    fn write_status(&mut self, value: u8) {
+        self.pulse1.length_counter.set_enabled(value & 0b0000_0001 != 0);
+        self.pulse2.length_counter.set_enabled(value & 0b0000_0010 != 0);
+        self.triangle.length_counter.set_enabled(value & 0b0000_0100 != 0);
+        self.noise.length_counter.set_enabled(value & 0b0000_1000 != 0);
+        self.dmc.set_enabled(value & 0b0001_0000 != 0);
+    }
+
+    /// Handles the Reset button.
+    ///
+    /// The channels are silenced as if $4015 was written with 0 and the frame counter restarts in its current mode.
+    /// The other channel registers keep their values.
+    ///
+    /// Useful links:
+    /// [Nesdev wiki - CPU power up state]
+    ///
+    /// [Nesdev wiki - CPU power up state]: https://www.nesdev.org/wiki/CPU_power_up_state#After_reset
+    pub fn reset(&mut self) {
+        self.write_status(0);
+        self.frame_counter.reset();
+        self.triangle.reset();
+        self.dmc.reset();
+    }
+
+    /// Reads $4015. Reading acknowledges the frame interrupt but not the DMC interrupt.
+    pub fn read_status(&mut self) -> u8 {
+        let status = (self.pulse1.length_counter.is_active() as u8)
+            | (self.pulse2.length_counter.is_active() as u8) << 1
+            | (self.triangle.length_counter.is_active() as u8) << 2
+            | (self.noise.length_counter.is_active() as u8) << 3
+            | (self.dmc.is_active() as u8) << 4
+            | (self.frame_counter.interrupt as u8) << 6
+            | (self.dmc.interrupt as u8) << 7;
+        self.frame_counter.interrupt = false;
+        status
+    }
+
+    pub fn frame_interrupt(&self) -> bool {
+        self.frame_counter.interrupt
+    }
+
+    pub fn dmc_interrupt(&self) -> bool {
+        self.dmc.interrupt
+    }
+
+    /// Returns the address the DMC wants to read a sample byte from, if any.
+    pub fn dmc_dma_request(&self) -> Option<u16> {
+        self.dmc.dma_request()
+    }
+
+    /// Hands the byte read for the DMC by the CPU over to the DMC.
+    pub fn complete_dmc_dma(&mut self, value: u8) {
+        self.dmc.complete_dma(value);
+    }
+
+    /// Clocks the APU once. Should be called every CPU cycle.
+    pub fn step(&mut self) {
+        match self.frame_counter.step() {
+            FrameClock::None => (),
+            FrameClock::QuarterFrame => self.clock_quarter_frame(),
+            FrameClock::HalfFrame => {
+                self.clock_quarter_frame();
+                self.clock_half_frame();
+            },
+        }
+
+        self.triangle.clock_timer();
+        self.noise.clock_timer();
+        self.dmc.clock_timer();
+        if self.cycle & 1 == 1 {
+            self.pulse1.clock_timer();
+            self.pulse2.clock_timer();
+        }
+        self.cycle += 1;
+
+        self.output_sample();
+    }
+
+    fn clock_quarter_frame(&mut self) {
+        self.pulse1.envelope.clock();
+        self.pulse2.envelope.clock();
+        self.noise.envelope.clock();
+        self.triangle.clock_linear_counter();
+    }
+
+    fn clock_half_frame(&mut self) {
+        self.pulse1.length_counter.clock();
+        self.pulse2.length_counter.clock();
+        self.triangle.length_counter.clock();
+        self.noise.length_counter.clock();
+        self.pulse1.clock_sweep();
+        self.pulse2.clock_sweep();
+    }
+
+    /// Non-linear mixer. Output is in range [0.0, 1.0].
+    fn mix(&self) -> f32 {
+        let pulse = (self.pulse1.output() + self.pulse2.output()) as f32;
+        let triangle = self.triangle.output() as f32;
+        let noise = self.noise.output() as f32;
+        let dmc = self.dmc.output() as f32;
+
+        let pulse_out = if pulse == 0.0 {
+            0.0
+        } else {
+            95.88 / (8128.0 / pulse + 100.0)
+        };
+        let tnd_sum = triangle / 8227.0 + noise / 12241.0 + dmc / 22638.0;
+        let tnd_out = if tnd_sum == 0.0 {
+            0.0
+        } else {
+            159.79 / (1.0 / tnd_sum + 100.0)
+        };
+        pulse_out + tnd_out
+    }
+
+    /// Averages the mixer output over the CPU cycles of each output sample.
+    fn output_sample(&mut self) {
+        self.sample_sum += self.mix();
+        self.sample_count += 1;
+        self.sample_clock += self.sample_rate;
+        if self.sample_clock < CPU_CLOCK_RATE {
+            return;
+        }
+        self.sample_clock -= CPU_CLOCK_RATE;
+
+        let sample = self.filters.iter_mut()
+            .fold(self.sample_sum / self.sample_count as f32, |sample, filter| filter.process(sample));
+        self.samples.push(sample);
+        self.sample_sum = 0.0;
+        self.sample_count = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apu_status_register() -> Result<(), std::io::Error> {
+        let mut apu = Apu::default();
+        apu.write_register(0x4015, 0x0F);
+        apu.write_register(0x4003, 0x08);
+        apu.write_register(0x400B, 0x08);
+        assert_eq!(apu.read_status(), 0b0000_0101);
+
+        // Disabling a channel clears its length counter
+        apu.write_register(0x4015, 0x01);
+        assert_eq!(apu.read_status(), 0b0000_0001);
+
+        // Frame interrupt is set at the end of the 4-step sequence and cleared by reading
+        for _ in 0..29830 {
+            apu.step();
+        }
+        assert_eq!(apu.read_status() & 0x40, 0x40);
+        assert_eq!(apu.read_status() & 0x40, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_apu_sample_rate() -> Result<(), std::io::Error> {
+        let mut apu = Apu::new(48_000);
+        for _ in 0..CPU_CLOCK_RATE {
+            apu.step();
+        }
+        assert_eq!(apu.take_samples().len(), 48_000);
+        assert!(apu.take_samples().is_empty());
+
+        apu.set_sample_rate(0);
+        assert_eq!(apu.sample_rate(), 1);
+        apu.set_sample_rate(u32::MAX);
+        assert_eq!(apu.sample_rate(), CPU_CLOCK_RATE);
+        Ok(())
+    }
+}
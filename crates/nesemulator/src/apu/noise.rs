@@ -0,0 +1,106 @@
+use super::envelope::Envelope;
+use super::length_counter::LengthCounter;
+
+/// Timer periods in CPU cycles (NTSC).
+const PERIOD_TABLE: [u16; 16] = [4, 8, 16, 32, 64, 96, 128, 160, 202, 254, 380, 508, 762, 1016, 2034, 4068];
+
+const MASK_LENGTH_COUNTER_HALT: u8 = 0b0010_0000;
+const MASK_MODE: u8 = 0b1000_0000;
+const MASK_PERIOD: u8 = 0b0000_1111;
+
+/// Noise channel producing pseudo-random output from a 15-bit linear feedback shift register.
+///
+/// Useful links:
+/// [Nesdev wiki - APU Noise]
+///
+/// [Nesdev wiki - APU Noise]: https://www.nesdev.org/wiki/APU_Noise
+pub struct Noise {
+    pub envelope: Envelope,
+    pub length_counter: LengthCounter,
+    /// Short mode uses bit 6 instead of bit 1 as the feedback tap, giving a 93-step sequence.
+    mode: bool,
+    timer_period: u16,
+    timer: u16,
+    shift_register: u16,
+}
+
+impl Default for Noise {
+    fn default() -> Self {
+        Noise {
+            envelope: Envelope::default(),
+            length_counter: LengthCounter::default(),
+            mode: false,
+            timer_period: PERIOD_TABLE[0],
+            timer: 0,
+            shift_register: 1,
+        }
+    }
+}
+
+impl Noise {
+    /// Writes to one of the channel registers. `register` is the address offset 0-3.
+    pub fn write_register(&mut self, register: u16, value: u8) {
+        match register {
+            0 => {
+                self.length_counter.set_halt(value & MASK_LENGTH_COUNTER_HALT != 0);
+                self.envelope.write_control(value);
+            },
+            1 => (),
+            2 => {
+                self.mode = value & MASK_MODE != 0;
+                self.timer_period = PERIOD_TABLE[(value & MASK_PERIOD) as usize];
+            },
+            3 => {
+                self.length_counter.load(value);
+                self.envelope.restart();
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    /// Clocked every CPU cycle.
+    pub fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.timer_period - 1;
+            self.clock_shift_register();
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    fn clock_shift_register(&mut self) {
+        let tap = if self.mode { 6 } else { 1 };
+        let feedback = (self.shift_register ^ (self.shift_register >> tap)) & 1;
+        self.shift_register = (self.shift_register >> 1) | (feedback << 14);
+    }
+
+    pub fn output(&self) -> u8 {
+        if self.shift_register & 1 != 0 || !self.length_counter.is_active() {
+            return 0;
+        }
+        self.envelope.output()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sequence_length(noise: &mut Noise) -> usize {
+        let start = noise.shift_register;
+        (1..).find(|_| {
+            noise.clock_shift_register();
+            noise.shift_register == start
+        }).unwrap()
+    }
+
+    #[test]
+    fn test_noise_shift_register_modes() -> Result<(), std::io::Error> {
+        let mut noise = Noise::default();
+        assert_eq!(sequence_length(&mut noise), 32767);
+
+        noise.write_register(2, 0x80);
+        assert_eq!(sequence_length(&mut noise), 93);
+        Ok(())
+    }
+}
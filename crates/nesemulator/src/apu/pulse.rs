@@ -0,0 +1,174 @@
+use super::envelope::Envelope;
+use super::length_counter::LengthCounter;
+
+const DUTY_SEQUENCES: [[u8; 8]; 4] = [
+    [0, 1, 0, 0, 0, 0, 0, 0],
+    [0, 1, 1, 0, 0, 0, 0, 0],
+    [0, 1, 1, 1, 1, 0, 0, 0],
+    [1, 0, 0, 1, 1, 1, 1, 1],
+];
+
+const MASK_DUTY: u8 = 0b1100_0000;
+const MASK_LENGTH_COUNTER_HALT: u8 = 0b0010_0000;
+const MASK_SWEEP_ENABLED: u8 = 0b1000_0000;
+const MASK_SWEEP_PERIOD: u8 = 0b0111_0000;
+const MASK_SWEEP_NEGATE: u8 = 0b0000_1000;
+const MASK_SWEEP_SHIFT: u8 = 0b0000_0111;
+const MASK_TIMER_HIGH: u8 = 0b0000_0111;
+
+/// Pulse (square wave) channel.
+///
+/// The two pulse channels differ only in how the sweep unit negates the period:
+/// pulse 1 uses ones' complement and pulse 2 uses two's complement.
+///
+/// Useful links:
+/// [Nesdev wiki - APU Pulse]
+/// [Nesdev wiki - APU Sweep]
+///
+/// [Nesdev wiki - APU Pulse]: https://www.nesdev.org/wiki/APU_Pulse
+/// [Nesdev wiki - APU Sweep]: https://www.nesdev.org/wiki/APU_Sweep
+pub struct Pulse {
+    ones_complement: bool,
+    pub envelope: Envelope,
+    pub length_counter: LengthCounter,
+    duty: u8,
+    sequence_step: u8,
+    timer_period: u16,
+    timer: u16,
+    sweep_enabled: bool,
+    sweep_period: u8,
+    sweep_negate: bool,
+    sweep_shift: u8,
+    sweep_divider: u8,
+    sweep_reload: bool,
+}
+
+impl Pulse {
+    /// Creates pulse channel 1 when `ones_complement` is true and pulse channel 2 otherwise.
+    pub fn new(ones_complement: bool) -> Pulse {
+        Pulse {
+            ones_complement,
+            envelope: Envelope::default(),
+            length_counter: LengthCounter::default(),
+            duty: 0,
+            sequence_step: 0,
+            timer_period: 0,
+            timer: 0,
+            sweep_enabled: false,
+            sweep_period: 0,
+            sweep_negate: false,
+            sweep_shift: 0,
+            sweep_divider: 0,
+            sweep_reload: false,
+        }
+    }
+
+    /// Writes to one of the four channel registers. `register` is the address offset 0-3.
+    pub fn write_register(&mut self, register: u16, value: u8) {
+        match register {
+            0 => {
+                self.duty = (value & MASK_DUTY) >> 6;
+                self.length_counter.set_halt(value & MASK_LENGTH_COUNTER_HALT != 0);
+                self.envelope.write_control(value);
+            },
+            1 => {
+                self.sweep_enabled = value & MASK_SWEEP_ENABLED != 0;
+                self.sweep_period = (value & MASK_SWEEP_PERIOD) >> 4;
+                self.sweep_negate = value & MASK_SWEEP_NEGATE != 0;
+                self.sweep_shift = value & MASK_SWEEP_SHIFT;
+                self.sweep_reload = true;
+            },
+            2 => self.timer_period = (self.timer_period & 0x0700) | value as u16,
+            3 => {
+                self.timer_period = (self.timer_period & 0x00FF) | (((value & MASK_TIMER_HIGH) as u16) << 8);
+                self.length_counter.load(value);
+                self.sequence_step = 0;
+                self.envelope.restart();
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    /// Clocked every APU cycle (every other CPU cycle).
+    pub fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.timer_period;
+            self.sequence_step = (self.sequence_step + 1) & 0x07;
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    /// Clocked by the frame counter every half frame.
+    pub fn clock_sweep(&mut self) {
+        if self.sweep_divider == 0 && self.sweep_enabled && self.sweep_shift > 0 && !self.is_muted_by_sweep() {
+            self.timer_period = self.sweep_target_period();
+        }
+        if self.sweep_divider == 0 || self.sweep_reload {
+            self.sweep_divider = self.sweep_period;
+            self.sweep_reload = false;
+        } else {
+            self.sweep_divider -= 1;
+        }
+    }
+
+    fn sweep_target_period(&self) -> u16 {
+        let change = self.timer_period >> self.sweep_shift;
+        if !self.sweep_negate {
+            self.timer_period + change
+        } else if self.ones_complement {
+            self.timer_period.saturating_sub(change + 1)
+        } else {
+            self.timer_period.saturating_sub(change)
+        }
+    }
+
+    /// The sweep unit mutes the channel even when it is disabled.
+    fn is_muted_by_sweep(&self) -> bool {
+        self.timer_period < 8 || self.sweep_target_period() > 0x07FF
+    }
+
+    pub fn output(&self) -> u8 {
+        if !self.length_counter.is_active()
+            || DUTY_SEQUENCES[self.duty as usize][self.sequence_step as usize] == 0
+            || self.is_muted_by_sweep()
+        {
+            return 0;
+        }
+        self.envelope.output()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pulse_sweep_negate_and_mute() -> Result<(), std::io::Error> {
+        let mut pulse1 = Pulse::new(true);
+        let mut pulse2 = Pulse::new(false);
+        for pulse in [&mut pulse1, &mut pulse2] {
+            pulse.length_counter.set_enabled(true);
+            pulse.write_register(0, 0b1011_1111); // Duty 2, constant volume 15
+            pulse.write_register(1, 0b1000_1001); // Enabled, period 0, negate, shift 1
+            pulse.write_register(2, 0x00);
+            pulse.write_register(3, 0x01); // Period 0x100
+        }
+        assert_eq!(pulse1.sweep_target_period(), 0x7F);
+        assert_eq!(pulse2.sweep_target_period(), 0x80);
+
+        pulse1.clock_sweep();
+        assert_eq!(pulse1.timer_period, 0x7F);
+        pulse1.clock_sweep();
+        assert_eq!(pulse1.timer_period, 0x3F);
+
+        // A period below 8 mutes the channel
+        pulse2.write_register(2, 0x07);
+        pulse2.write_register(3, 0x00);
+        pulse2.sequence_step = 1;
+        assert_eq!(pulse2.output(), 0);
+        pulse2.write_register(2, 0x08);
+        assert_eq!(pulse2.output(), 15);
+        Ok(())
+    }
+}
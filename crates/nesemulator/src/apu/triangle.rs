@@ -0,0 +1,83 @@
+use super::length_counter::LengthCounter;
+
+const SEQUENCE: [u8; 32] = [
+    15, 14, 13, 12, 11, 10,  9,  8,  7,  6,  5,  4,  3,  2,  1,  0,
+     0,  1,  2,  3,  4,  5,  6,  7,  8,  9, 10, 11, 12, 13, 14, 15,
+];
+
+const MASK_CONTROL: u8 = 0b1000_0000;
+const MASK_LINEAR_COUNTER_RELOAD: u8 = 0b0111_1111;
+const MASK_TIMER_HIGH: u8 = 0b0000_0111;
+
+/// Triangle channel.
+///
+/// Useful links:
+/// [Nesdev wiki - APU Triangle]
+///
+/// [Nesdev wiki - APU Triangle]: https://www.nesdev.org/wiki/APU_Triangle
+#[derive(Default)]
+pub struct Triangle {
+    pub length_counter: LengthCounter,
+    control: bool,
+    linear_counter_reload_value: u8,
+    linear_counter: u8,
+    linear_counter_reload: bool,
+    sequence_step: u8,
+    timer_period: u16,
+    timer: u16,
+}
+
+impl Triangle {
+    /// Writes to one of the channel registers. `register` is the address offset 0-3.
+    pub fn write_register(&mut self, register: u16, value: u8) {
+        match register {
+            0 => {
+                self.control = value & MASK_CONTROL != 0;
+                self.length_counter.set_halt(self.control);
+                self.linear_counter_reload_value = value & MASK_LINEAR_COUNTER_RELOAD;
+            },
+            1 => (),
+            2 => self.timer_period = (self.timer_period & 0x0700) | value as u16,
+            3 => {
+                self.timer_period = (self.timer_period & 0x00FF) | (((value & MASK_TIMER_HIGH) as u16) << 8);
+                self.length_counter.load(value);
+                self.linear_counter_reload = true;
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    /// Resets the sequencer to the start of the waveform. Done by the Reset button.
+    pub fn reset(&mut self) {
+        self.sequence_step = 0;
+    }
+
+    /// Clocked every CPU cycle.
+    pub fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.timer_period;
+            if self.length_counter.is_active() && self.linear_counter > 0 {
+                self.sequence_step = (self.sequence_step + 1) & 0x1F;
+            }
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    /// Clocked by the frame counter every quarter frame.
+    pub fn clock_linear_counter(&mut self) {
+        if self.linear_counter_reload {
+            self.linear_counter = self.linear_counter_reload_value;
+        } else if self.linear_counter > 0 {
+            self.linear_counter -= 1;
+        }
+        if !self.control {
+            self.linear_counter_reload = false;
+        }
+    }
+
+    /// The sequencer keeps its last value when halted, so the output never drops to zero abruptly.
+    pub fn output(&self) -> u8 {
+        SEQUENCE[self.sequence_step as usize]
+    }
+}
@@ -0,0 +1,130 @@
+use crate::error::{Error, Result};
+
+use flate2::read::GzDecoder;
+use std::io::{Cursor, Read};
+use std::path::Path;
+
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+const GZIP_MAGIC: &[u8] = b"\x1F\x8B";
+const SEVEN_ZIP_MAGIC: &[u8] = b"7z\xBC\xAF\x27\x1C";
+
+/// File extensions of ROM files that are picked from archives.
+const ROM_EXTENSIONS: &[&str] = &["nes", "unf", "unif", "fds", "nsf", "nsfe"];
+
+/// Returns the ROM file contained in a zip, gzip or 7z archive.
+/// Data that is not an archive is returned as is.
+///
+/// Archives are recognized by their contents, not by the file extension.
+/// From zip and 7z archives the entry with the given name is picked,
+/// or if no name is given, the first entry with a ROM file extension such as `.nes` or `.fds`.
+/// A gzip file contains only one file, so the name is ignored.
+pub fn extract_rom(data: Vec<u8>, entry_name: Option<&str>) -> Result<Vec<u8>> {
+    if data.starts_with(ZIP_MAGIC) {
+        extract_from_zip(data, entry_name)
+    } else if data.starts_with(GZIP_MAGIC) {
+        let mut rom = Vec::new();
+        GzDecoder::new(&data[..]).read_to_end(&mut rom).map_err(archive_error)?;
+        Ok(rom)
+    } else if data.starts_with(SEVEN_ZIP_MAGIC) {
+        extract_from_7z(data, entry_name)
+    } else {
+        Ok(data)
+    }
+}
+
+fn extract_from_zip(data: Vec<u8>, entry_name: Option<&str>) -> Result<Vec<u8>> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(data)).map_err(archive_error)?;
+    // Entries are searched in archive order, file_names() is unordered
+    let mut index = None;
+    for i in 0..archive.len() {
+        if is_wanted_entry(archive.by_index_raw(i).map_err(archive_error)?.name(), entry_name) {
+            index = Some(i);
+            break;
+        }
+    }
+    let mut file = archive.by_index(index.ok_or(Error::RomNotFoundInArchive)?).map_err(archive_error)?;
+    let mut rom = Vec::new();
+    file.read_to_end(&mut rom).map_err(archive_error)?;
+    Ok(rom)
+}
+
+fn extract_from_7z(data: Vec<u8>, entry_name: Option<&str>) -> Result<Vec<u8>> {
+    let len = data.len() as u64;
+    let mut archive = sevenz_rust::SevenZReader::new(Cursor::new(data), len, sevenz_rust::Password::empty())
+        .map_err(archive_error)?;
+    let mut rom = None;
+    archive.for_each_entries(|entry, reader| {
+        if entry.is_directory() || !is_wanted_entry(entry.name(), entry_name) {
+            // Entries in a solid block must be read through to reach the next entry
+            std::io::copy(reader, &mut std::io::sink())?;
+            return Ok(true);
+        }
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        rom = Some(data);
+        Ok(false)
+    }).map_err(archive_error)?;
+    rom.ok_or(Error::RomNotFoundInArchive)
+}
+
+/// Returns true if an archive entry is the wanted ROM file.
+///
+/// A wanted name matches either the full path of the entry or its file name.
+fn is_wanted_entry(name: &str, entry_name: Option<&str>) -> bool {
+    let path = Path::new(name);
+    match entry_name {
+        Some(entry_name) => name == entry_name || path.file_name().is_some_and(|file_name| file_name == entry_name),
+        None => path.extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| ROM_EXTENSIONS.iter().any(|rom| extension.eq_ignore_ascii_case(rom))),
+    }
+}
+
+fn archive_error<E: std::fmt::Display>(error: E) -> Error {
+    Error::InvalidArchive(error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::result::Result;
+
+    const ROM: &[u8] = b"NES\x1a rom";
+
+    #[test]
+    fn test_extract_rom_from_archives() -> Result<(), std::io::Error> {
+        assert_eq!(extract_rom(ROM.to_vec(), None).unwrap(), ROM);
+
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options = zip::write::FileOptions::default();
+        zip.start_file("readme.txt", options)?;
+        zip.write_all(b"readme")?;
+        zip.start_file("roms/first.NES", options)?;
+        zip.write_all(ROM)?;
+        zip.start_file("second.nes", options)?;
+        zip.write_all(b"second")?;
+        let zip = zip.finish()?.into_inner();
+        assert_eq!(extract_rom(zip.clone(), None).unwrap(), ROM);
+        assert_eq!(extract_rom(zip.clone(), Some("second.nes")).unwrap(), b"second");
+        assert_eq!(extract_rom(zip.clone(), Some("first.NES")).unwrap(), ROM);
+        assert!(matches!(extract_rom(zip, Some("missing.nes")), Err(Error::RomNotFoundInArchive)));
+
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gzip.write_all(ROM)?;
+        assert_eq!(extract_rom(gzip.finish()?, None).unwrap(), ROM);
+
+        let mut seven_zip = sevenz_rust::SevenZWriter::new(Cursor::new(Vec::new())).unwrap();
+        for (name, data) in [("readme.txt", &b"readme"[..]), ("game.nes", ROM)] {
+            let mut entry = sevenz_rust::SevenZArchiveEntry::new();
+            entry.name = name.to_owned();
+            entry.has_stream = true;
+            seven_zip.push_archive_entry(entry, Some(data)).unwrap();
+        }
+        let seven_zip = seven_zip.finish()?.into_inner();
+        assert_eq!(extract_rom(seven_zip, None).unwrap(), ROM);
+
+        assert!(matches!(extract_rom(GZIP_MAGIC.to_vec(), None), Err(Error::InvalidArchive(_))));
+        Ok(())
+    }
+}
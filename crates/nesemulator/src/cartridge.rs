@@ -1,180 +1,417 @@
 use std::fs;
-use std::iter::FromIterator;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use crate::archive;
 use crate::cpu::ram::Ram;
+use crate::error::{Error, Result};
+use crate::mapper::{self, fds, Mapper, Memory, PRG_BANK_SIZE_8K};
+use crate::nsf::{self, NsfFile};
+use crate::rom_database::{RomDatabase, RomDatabaseEntry};
+use crate::rom_header::{RomHeader, HEADER_SIZE, TRAINER_SIZE};
+use crate::unif;
 use log::info;
 
-const HEADER_SIZE: usize = 16;
-const PRG_ROM_PAGE_SIZE: usize = 0x4000; // 16384
-const CHR_ROM_PAGE_SIZE: usize = 0x2000; //  8192
+const NAMETABLE_SIZE: usize = 0x0400;
+const TRAINER_ADDRESS: usize = 0x7000;
+const FDS_BIOS_FILE_NAME: &str = "disksys.rom";
 
+/// How the four nametables at PPU addresses 0x2000..=0x2FFF map to the 1 KiB pages of VRAM.
+///
+/// The console has 2 KiB of VRAM, enough for two nametables.
+/// Four-screen boards add 2 KiB of their own VRAM for the other two nametables.
+///
+/// Useful links:
+/// [Nesdev wiki - Mirroring]
+///
+/// [Nesdev wiki - Mirroring]: https://www.nesdev.org/wiki/Mirroring#Nametable_Mirroring
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum NametableMirroring {
     Vertical,
-    Horizontal
+    Horizontal,
+    SingleScreenA,
+    SingleScreenB,
+    FourScreen,
 }
 
+impl NametableMirroring {
+    /// Returns the VRAM pages of nametables 0..3. Pages 0 and 1 are in the console, pages 2 and 3 on the cartridge.
+    fn pages(self) -> [usize; 4] {
+        use NametableMirroring::*;
+        match self {
+            Vertical      => [0, 1, 0, 1],
+            Horizontal    => [0, 0, 1, 1],
+            SingleScreenA => [0, 0, 0, 0],
+            SingleScreenB => [1, 1, 1, 1],
+            FourScreen    => [0, 1, 2, 3],
+        }
+    }
+}
 
-pub struct Cartridge {
-    mem: Vec<u8>,
+
+/// Options for loading a ROM.
+#[derive(Clone, Debug)]
+pub struct LoadOptions {
+    /// Correct the header with the ROM database if the game is found in it. Enabled by default.
+    pub use_rom_database: bool,
+    /// ROM database used instead of the embedded one, e.g. entries converted from the NES 2.0 header database.
+    pub rom_database: Option<Arc<RomDatabase>>,
+    /// Name of the file to load from a zip or 7z archive. By default the first ROM file is loaded.
+    pub archive_entry: Option<String>,
+    /// Famicom Disk System BIOS for loading disk images.
+    /// By default `disksys.rom` is loaded from the directory of the disk image.
+    pub fds_bios: Option<Vec<u8>>,
+    /// Whether UxROM, CNROM, AxROM and GxROM boards have bus conflicts. By default this comes from the header.
+    pub bus_conflicts: Option<bool>,
+}
+
+impl Default for LoadOptions {
+    fn default() -> Self {
+        LoadOptions {
+            use_rom_database: true,
+            rom_database: None,
+            archive_entry: None,
+            fds_bios: None,
+            bus_conflicts: None,
+        }
+    }
+}
+
+/// Parts of a ROM file.
+struct RomImage<'a> {
+    header: RomHeader,
     prg_rom: Vec<u8>,
     chr_rom: Vec<u8>,
-    chr_ram: Vec<u8>,
-    ines_format: bool,
-    nes20_format: bool,
-    prg_rom_pages: usize,
-    chr_rom_pages: usize,
-    mapper_number: u8,
+    trainer: Option<&'a [u8]>,
+}
+
+impl RomImage<'_> {
+    /// Splits an iNES or NES 2.0 file into its header, PRG ROM, CHR ROM and trainer.
+    fn split_ines(data: &[u8]) -> Result<RomImage<'_>> {
+        let header = RomHeader::parse(data).ok_or(Error::InvalidRom)?;
+        info!("rom header: {:?}", header);
+        if header.prg_rom_size == 0 {
+            return Err(Error::MissingPrgRom);
+        }
+
+        // The optional trainer sits between the header and PRG ROM
+        let trainer_start = HEADER_SIZE;
+        let prg_start = if header.trainer { trainer_start + TRAINER_SIZE } else { trainer_start };
+        let prg_end = prg_start.saturating_add(header.prg_rom_size);
+        let chr_start = prg_end;
+        let chr_end = chr_start.saturating_add(header.chr_rom_size);
+        if chr_end > data.len() {
+            return Err(Error::TruncatedRom { expected: chr_end, actual: data.len() });
+        }
+        let prg_rom = data[prg_start..prg_end].to_vec();
+        let chr_rom = data[chr_start..chr_end].to_vec();
+        let trainer = if header.trainer { Some(&data[trainer_start..prg_start]) } else { None };
+        Ok(RomImage { header, prg_rom, chr_rom, trainer })
+    }
+}
+
+pub struct Cartridge {
+    header: RomHeader,
+    /// ROM database entry that corrected the header.
+    database_entry: Option<RomDatabaseEntry>,
+    mapper: Box<dyn Mapper>,
+    /// Save file of battery-backed PRG RAM. Set if the cartridge has a battery.
+    save_path: Option<PathBuf>,
+    /// Contents of PRG RAM when it was last loaded or saved.
+    saved_prg_ram: Vec<u8>,
+    /// Nametables 2 and 3 of four-screen boards.
+    four_screen_vram: Vec<u8>,
+    /// The music file when playing an NSF file.
+    nsf: Option<NsfFile>,
 }
 
 impl Cartridge {
     pub fn new() -> Cartridge {
         Cartridge {
-            mem: Vec::default(),
-            prg_rom: Vec::default(),
-            chr_rom: Vec::default(),
-            chr_ram: vec![0; 0x2000],
-            prg_rom_pages: 0,
-            chr_rom_pages: 0,
-            ines_format: false,
-            nes20_format: false,
-            mapper_number: 0,
+            header: RomHeader::default(),
+            database_entry: None,
+            mapper: Box::new(mapper::Nrom::new(Memory::default(), NametableMirroring::Horizontal)),
+            save_path: None,
+            saved_prg_ram: Vec::new(),
+            four_screen_vram: Vec::new(),
+            nsf: None,
+        }
+    }
+
+    /// Creates a cartridge around an already constructed mapper.
+    pub fn new_with_mapper(mapper: Box<dyn Mapper>) -> Cartridge {
+        Cartridge {
+            mapper,
+            ..Cartridge::new()
         }
     }
 
-    pub fn new_from_file(path: String) -> Cartridge {
-        let mut rom = Cartridge::new();
+    /// Loads an iNES or UNIF ROM file, Famicom Disk System image or NSF music file, which may be packed in a zip, gzip or 7z archive.
+    ///
+    /// Battery-backed RAM is saved next to the file, e.g. `game.zip` is saved to `game.sav`.
+    pub fn new_from_file(path: String, options: &LoadOptions) -> Result<Cartridge> {
+        let data = archive::extract_rom(fs::read(&path)?, options.archive_entry.as_deref())?;
+        let path = PathBuf::from(path);
+        if fds::is_disk_image(&data) {
+            let bios = match options.fds_bios.clone() {
+                Some(bios) => bios,
+                None => read_fds_bios(&path)?,
+            };
+            return Cartridge::new_from_disk_image(&data, bios);
+        }
+        if nsf::is_nsf(&data) {
+            return Cartridge::new_from_nsf(NsfFile::parse(&data)?);
+        }
+        Cartridge::new_from_rom(&data, Some(path.with_extension("sav")), options)
+    }
 
-        rom.mem = fs::read(path).expect("Failed to open file");
+    /// Loads a ROM, Famicom Disk System image or NSF music file from memory. The data may also be a zip, gzip or 7z archive containing it.
+    ///
+    /// Battery-backed RAM is not saved to a file. Disk images need the BIOS in the options.
+    pub fn new_from_bytes(data: &[u8], options: &LoadOptions) -> Result<Cartridge> {
+        let data = archive::extract_rom(data.to_vec(), options.archive_entry.as_deref())?;
+        if fds::is_disk_image(&data) {
+            let bios = options.fds_bios.clone().ok_or(Error::FdsBiosNotFound)?;
+            return Cartridge::new_from_disk_image(&data, bios);
+        }
+        if nsf::is_nsf(&data) {
+            return Cartridge::new_from_nsf(NsfFile::parse(&data)?);
+        }
+        Cartridge::new_from_rom(&data, None, options)
+    }
 
-        if !rom.is_ines_format() {
-            panic!("Invalid ROM: Missing identification string \"NES<EOF>\".");
+    /// Creates the Famicom Disk System RAM adapter with the disk image inserted.
+    fn new_from_disk_image(data: &[u8], bios: Vec<u8>) -> Result<Cartridge> {
+        if bios.len() != fds::BIOS_SIZE {
+            return Err(Error::InvalidFdsBios { size: bios.len() });
         }
-        rom.ines_format = true;
-        rom.nes20_format = rom.is_nes20_format();
+        let disk_sides = fds::disk_sides_from_image(data).ok_or(Error::InvalidDiskImage)?;
+        info!("disk image with {} sides", disk_sides.len());
+
+        let header = RomHeader::fds();
+        let mut memory = Memory::new(bios, Vec::new());
+        memory.prg_ram = vec![0; header.total_prg_ram_size()];
+        memory.chr_ram = vec![0; header.total_chr_ram_size()];
+        Ok(Cartridge {
+            header,
+            ..Cartridge::new_with_mapper(Box::new(mapper::Fds::new(memory, disk_sides)))
+        })
+    }
+
+    /// Creates the NSF player hardware with the program data of the music file.
+    fn new_from_nsf(file: NsfFile) -> Result<Cartridge> {
+        info!("nsf: {} - {}, {} songs", file.title, file.artist, file.song_count);
+        let memory = Memory::new(file.prg_rom(), Vec::new());
+        Ok(Cartridge {
+            header: RomHeader::nsf(memory.prg_rom.len()),
+            nsf: Some(file),
+            ..Cartridge::new_with_mapper(Box::new(mapper::Nsf::new(memory)))
+        })
+    }
+
+    fn new_from_rom(data: &[u8], save_path: Option<PathBuf>, options: &LoadOptions) -> Result<Cartridge> {
+        let RomImage { mut header, prg_rom, chr_rom, trainer } = if unif::is_unif(data) {
+            let rom = unif::parse(data)?;
+            info!("unif board {}: {:?}", rom.board_name, rom.header);
+            RomImage { header: rom.header, prg_rom: rom.prg_rom, chr_rom: rom.chr_rom, trainer: None }
+        } else {
+            RomImage::split_ines(data)?
+        };
 
-        rom.update_chr_rom_size();
-        rom.update_prg_rom_size();
-        rom.update_mapper_number();
+        let database_entry = if options.use_rom_database {
+            let database = match options.rom_database.as_deref() {
+                Some(database) => database,
+                None => RomDatabase::embedded(),
+            };
+            database.find(&prg_rom, &chr_rom).cloned()
+        } else {
+            None
+        };
+        if let Some(entry) = database_entry.as_ref() {
+            entry.apply(&mut header);
+            info!("corrected rom header: {:?}", header);
+        }
 
-        if rom.mapper_number != 0 {
-            panic!(
-                "Unsupported ROM: Mapper number {} not supported.",
-                rom.mapper_number
-            );
+        let mut memory = Memory::new(prg_rom, chr_rom);
+        memory.prg_ram = vec![0; header.total_prg_ram_size()];
+        if header.total_chr_ram_size() > 0 {
+            memory.chr_ram = vec![0; header.total_chr_ram_size()];
+        }
+        if let Some(trainer) = trainer {
+            // Copiers loaded the trainer to 0x7000..=0x71FF, so there has to be PRG RAM for it
+            let trainer_offset = TRAINER_ADDRESS - 0x6000;
+            if memory.prg_ram.len() < trainer_offset + TRAINER_SIZE {
+                memory.prg_ram.resize(PRG_BANK_SIZE_8K, 0);
+            }
+            memory.prg_ram[trainer_offset..trainer_offset + TRAINER_SIZE].copy_from_slice(trainer);
         }
 
+        let save_path = save_path.filter(|_| header.battery);
 
-        let prg_start = HEADER_SIZE;
-        let prg_end = prg_start + PRG_ROM_PAGE_SIZE * rom.prg_rom_pages;
-        let chr_start = prg_end;
-        let chr_end = chr_start + CHR_ROM_PAGE_SIZE * rom.chr_rom_pages;
-        rom.prg_rom = Vec::from_iter(rom.mem[prg_start..prg_end].iter().cloned());
-        rom.chr_rom = Vec::from_iter(rom.mem[chr_start..chr_end].iter().cloned());
+        let four_screen_vram = if header.mirroring == NametableMirroring::FourScreen {
+            vec![0; 2 * NAMETABLE_SIZE]
+        } else {
+            Vec::new()
+        };
 
-        rom
+        Ok(Cartridge {
+            database_entry,
+            four_screen_vram,
+            saved_prg_ram: memory.prg_ram.clone(),
+            mapper: mapper::new_mapper(&header, memory, options.bus_conflicts)?,
+            header,
+            save_path,
+            nsf: None,
+        })
     }
 
-    fn update_prg_rom_size(&mut self) {
-        self.prg_rom_pages = self.mem[4] as usize;
-        info!("prg rom pages: {}", self.prg_rom_pages);
+    pub fn header(&self) -> &RomHeader {
+        &self.header
     }
 
-    fn update_chr_rom_size(&mut self) {
-        self.chr_rom_pages = self.mem[5] as usize;
-        info!("chr rom pages: {}", self.chr_rom_pages);
+    /// Returns the ROM database entry that corrected the header, if the game was found in the database.
+    pub fn database_entry(&self) -> Option<&RomDatabaseEntry> {
+        self.database_entry.as_ref()
     }
 
-    fn update_mapper_number(&self) -> u8 {
-        (self.mem[6] & 0x0F) | (self.mem[7] & 0xF0)
+    /// Returns the music file if an NSF file is loaded.
+    pub fn nsf(&self) -> Option<&NsfFile> {
+        self.nsf.as_ref()
     }
 
-    fn is_ines_format(&self) -> bool {
-        self.mem[0..4].eq(b"NES\x1a")
+    pub fn has_battery(&self) -> bool {
+        self.header.battery
     }
 
-    fn is_nes20_format(&self) -> bool {
-        let nes20_bit_check = (self.mem[7] & 0x0C) == 0x08;
-        let nes20_size_check = true; // TODO: do size checks with self.mem[9].
-        self.ines_format && nes20_bit_check && nes20_size_check
+    /// Loads battery-backed PRG RAM from the save file.
+    /// Does nothing if the cartridge has no battery or the save file does not exist yet.
+    pub fn load_battery_ram(&mut self) -> io::Result<()> {
+        let path = match self.save_path.as_ref() {
+            Some(path) if path.exists() => path,
+            _ => return Ok(()),
+        };
+        let data = fs::read(path)?;
+        info!("loaded save file {}", path.display());
+        let prg_ram = &mut self.mapper.memory_mut().prg_ram;
+        let len = data.len().min(prg_ram.len());
+        prg_ram[..len].copy_from_slice(&data[..len]);
+        self.saved_prg_ram = prg_ram.clone();
+        Ok(())
     }
 
-    fn fetch_mirroring(&self) -> NametableMirroring {
-        if self.mem[6] & 0x01 == 0x01 {
-            NametableMirroring::Vertical
-        }
-        else {
-            NametableMirroring::Horizontal
+    /// Writes battery-backed PRG RAM to the save file if it has changed since it was last loaded or saved.
+    pub fn save_battery_ram(&mut self) -> io::Result<()> {
+        let path = match self.save_path.as_ref() {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let prg_ram = &self.mapper.memory().prg_ram;
+        if *prg_ram == self.saved_prg_ram {
+            return Ok(());
         }
+        fs::write(path, prg_ram)?;
+        info!("wrote save file {}", path.display());
+        self.saved_prg_ram = prg_ram.clone();
+        Ok(())
+    }
+
+    /// Returns None if the cartridge does not drive the data bus, leaving the open bus value.
+    pub fn read_using_cpu_bus_address(&self, address: usize) -> Option<u8> {
+        self.mapper.read_prg(address as u16)
     }
 
+    pub fn write_using_cpu_bus_address(&mut self, address: usize, value: u8) {
+        self.mapper.write_prg(address as u16, value);
+    }
 
-    pub fn read_using_cpu_bus_address(&self, address: usize) -> u8 {
-        match address {
-            0x6000..=0x7FFF => unimplemented!(),
-            0x8000..=0xBFFF => self.prg_rom[address - 0x8000usize],
-            0xC000..=0xFFFF => self.prg_rom[(address - 0x8000usize) % (self.prg_rom_pages * PRG_ROM_PAGE_SIZE)],
-            _ => panic!("Trying to read from invalid ROM address."),
-        }
+    pub fn read_from_pattern_table(&self, address: u16) -> u8 {
+        self.mapper.read_chr(address)
     }
 
-    
+    pub fn write_to_pattern_table(&mut self, address: u16, value: u8) {
+        self.mapper.write_chr(address, value);
+    }
 
-    pub fn read_using_ppu_bus_address(&self, _address: usize) -> u8 {
-        unimplemented!();
+    pub fn notify_ppu_address(&mut self, address: u16) {
+        self.mapper.notify_ppu_address(address);
     }
 
-    pub fn write(&self, _address: usize, _value: u8) {
-        unimplemented!(); // Temporarily supporting only read-only memory.
+    /// Reads from the expansion area 0x4020..=0x5FFF. Returns None if nothing responds (open bus).
+    pub fn read_expansion(&mut self, address: u16) -> Option<u8> {
+        self.mapper.read_expansion(address)
     }
 
-    pub fn read_from_pattern_table(&self, address: u16) -> u8 {
-        if self.chr_rom.is_empty() {
-            return self.chr_ram[address as usize];
+    pub fn write_expansion(&mut self, address: u16, value: u8) {
+        self.mapper.write_expansion(address, value);
+    }
+
+    pub fn disk_side_count(&self) -> usize {
+        self.mapper.disk_side_count()
+    }
+
+    pub fn disk_side(&self) -> Option<usize> {
+        self.mapper.disk_side()
+    }
+
+    pub fn set_disk_side(&mut self, side: Option<usize>) {
+        self.mapper.set_disk_side(side);
+    }
+
+    pub fn clock_cpu(&mut self) {
+        self.mapper.clock_cpu();
+    }
+
+    pub fn reset(&mut self) {
+        self.mapper.reset();
+    }
+
+    /// Returns the mapper to its power-up state and clears the RAM chips.
+    /// Battery-backed PRG RAM keeps its contents.
+    pub fn power_cycle(&mut self) {
+        self.mapper.power_cycle();
+        let memory = self.mapper.memory_mut();
+        if !self.header.battery {
+            memory.prg_ram.fill(0);
         }
-        self.chr_rom[address as usize]
+        memory.chr_ram.fill(0);
+        self.four_screen_vram.fill(0);
     }
 
-    pub fn write_to_pattern_table(&mut self, address: u16, value: u8) {
-        if self.chr_rom.is_empty() {
-            self.chr_ram[address as usize] = value;
+    pub fn irq(&self) -> bool {
+        self.mapper.irq()
+    }
+
+    /// Returns the current nametable mirroring.
+    ///
+    /// Four-screen boards have their VRAM wired to the nametables regardless of what the mapper selects.
+    pub fn mirroring(&self) -> NametableMirroring {
+        if self.header.mirroring == NametableMirroring::FourScreen {
+            NametableMirroring::FourScreen
+        } else {
+            self.mapper.mirroring()
         }
     }
 
+    /// Returns the VRAM page and the offset in it that a nametable address maps to.
+    /// Addresses 0x3000..=0x3EFF mirror 0x2000..=0x2EFF.
+    fn nametable_page(&self, address: u16) -> (usize, usize) {
+        let address = address as usize & 0x0FFF;
+        let nametable = address / NAMETABLE_SIZE;
+        (self.mirroring().pages()[nametable], address % NAMETABLE_SIZE)
+    }
+
     pub fn read_from_nametable(&self, address: u16, vram: &Ram) -> u8 {
-        use NametableMirroring::*;
-        let mirroring = self.fetch_mirroring();
-        let offset = match (mirroring, address) {
-            // vram 0x0000..=0x03FF
-            (Vertical,   0x2000..=0x23FF) => 0x2000,
-            (Vertical,   0x2800..=0x2BFF) => 0x2800,
-            (Horizontal, 0x2000..=0x23FF) => 0x2000,
-            (Horizontal, 0x2400..=0x27FF) => 0x2400,
-            // vram 0x0400..=0x07FF
-            (Vertical,   0x2400..=0x27FF) => 0x2000,
-            (Vertical,   0x2C00..=0x2FFF) => 0x2800,
-            (Horizontal, 0x2800..=0x2BFF) => 0x2400,
-            (Horizontal, 0x2C00..=0x2FFF) => 0x2800,
-            _ => panic!()
-        };
-        vram.read(address as usize - offset)
+        match self.nametable_page(address) {
+            (page @ 0..=1, offset) => vram.read(page * NAMETABLE_SIZE + offset),
+            (page, offset) => self.four_screen_vram[(page - 2) * NAMETABLE_SIZE + offset],
+        }
     }
 
-    pub fn write_to_nametable(&self, address: u16, vram: &mut Ram, value: u8) {
-        use NametableMirroring::*;
-        let mirroring = self.fetch_mirroring();
-        let offset = match (mirroring, address) {
-            // vram 0x0000..=0x03FF
-            (Vertical,   0x2000..=0x23FF) => 0x2000,
-            (Vertical,   0x2800..=0x2BFF) => 0x2800,
-            (Horizontal, 0x2000..=0x23FF) => 0x2000,
-            (Horizontal, 0x2400..=0x27FF) => 0x2400,
-            // vram 0x0400..=0x07FF
-            (Vertical,   0x2400..=0x27FF) => 0x2000,
-            (Vertical,   0x2C00..=0x2FFF) => 0x2800,
-            (Horizontal, 0x2800..=0x2BFF) => 0x2400,
-            (Horizontal, 0x2C00..=0x2FFF) => 0x2800,
-            _ => panic!()
-        };
-        vram.write(address as usize - offset, value);
+    pub fn write_to_nametable(&mut self, address: u16, vram: &mut Ram, value: u8) {
+        match self.nametable_page(address) {
+            (page @ 0..=1, offset) => vram.write(page * NAMETABLE_SIZE + offset, value),
+            (page, offset) => self.four_screen_vram[(page - 2) * NAMETABLE_SIZE + offset] = value,
+        }
     }
 }
 
@@ -183,3 +420,229 @@ impl Default for Cartridge {
         Self::new()
     }
 }
+
+/// Reads the Famicom Disk System BIOS from the directory of a disk image.
+fn read_fds_bios(disk_image_path: &Path) -> Result<Vec<u8>> {
+    let bios_path = disk_image_path.with_file_name(FDS_BIOS_FILE_NAME);
+    match fs::read(&bios_path) {
+        Ok(bios) => Ok(bios),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Err(Error::FdsBiosNotFound),
+        Err(error) => Err(error.into()),
+    }
+}
+
+/// Builds an iNES image of an NROM board with 16 KiB PRG ROM and 8 KiB CHR ROM for tests.
+///
+/// PRG ROM is mapped at 0xC000. It starts with `prg`, ends with the NMI, reset and IRQ vectors
+/// and is otherwise filled with NOPs, like CHR ROM.
+#[cfg(test)]
+pub(crate) fn nrom_test_rom(prg: &[u8], vectors: [u16; 3]) -> Vec<u8> {
+    let mut rom = b"NES\x1a\x01\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00".to_vec();
+    rom.resize(HEADER_SIZE + 0x4000 + 0x2000, 0xEA); // NOP
+    rom[HEADER_SIZE..HEADER_SIZE + prg.len()].copy_from_slice(prg);
+    for (i, vector) in vectors.iter().enumerate() {
+        let offset = HEADER_SIZE + 0x3FFA + i * 2;
+        rom[offset..offset + 2].copy_from_slice(&vector.to_le_bytes());
+    }
+    rom
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::result::Result;
+
+    #[test]
+    fn test_battery_ram_save_and_load() -> Result<(), std::io::Error> {
+        let directory = std::env::temp_dir().join(format!("nesemulator-test-{}", std::process::id()));
+        fs::create_dir_all(&directory)?;
+        let rom_path = directory.join("battery.nes");
+        let save_path = directory.join("battery.sav");
+
+        let mut rom = nrom_test_rom(&[], [0xC000; 3]);
+        rom[6] = 0x02; // Battery
+        fs::write(&rom_path, &rom)?;
+
+        let mut cartridge = Cartridge::new_from_file(rom_path.to_string_lossy().into_owned(), &LoadOptions::default()).unwrap();
+        assert!(cartridge.has_battery());
+        cartridge.save_battery_ram()?;
+        assert!(!save_path.exists(), "Unchanged RAM should not be saved");
+
+        cartridge.write_using_cpu_bus_address(0x6000, 0x42);
+        cartridge.write_using_cpu_bus_address(0x7FFF, 0x24);
+        cartridge.save_battery_ram()?;
+        assert_eq!(fs::read(&save_path)?.len(), 0x2000);
+
+        let mut cartridge = Cartridge::new_from_file(rom_path.to_string_lossy().into_owned(), &LoadOptions::default()).unwrap();
+        assert_eq!(cartridge.read_using_cpu_bus_address(0x6000), Some(0x00));
+        cartridge.load_battery_ram()?;
+        assert_eq!(cartridge.read_using_cpu_bus_address(0x6000), Some(0x42));
+        assert_eq!(cartridge.read_using_cpu_bus_address(0x7FFF), Some(0x24));
+
+        fs::remove_dir_all(&directory)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_rom_loading_errors() -> Result<(), std::io::Error> {
+        let directory = std::env::temp_dir().join(format!("nesemulator-test-errors-{}", std::process::id()));
+        fs::create_dir_all(&directory)?;
+        let rom_path = directory.join("error.nes");
+        let load = || Cartridge::new_from_file(rom_path.to_string_lossy().into_owned(), &LoadOptions::default());
+
+        assert!(matches!(load(), Err(Error::Io(_))));
+
+        fs::write(&rom_path, b"This is not a ROM")?;
+        assert!(matches!(load(), Err(Error::InvalidRom)));
+
+        let mut rom = b"NES\x1a\x02\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00".to_vec();
+        rom.resize(HEADER_SIZE + 0x4000, 0);
+        fs::write(&rom_path, &rom)?;
+        assert!(matches!(load(), Err(Error::TruncatedRom { expected: 0xA010, actual: 0x4010 })));
+
+        let mut rom = b"NES\x1a\x00\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00".to_vec();
+        rom.resize(HEADER_SIZE + 0x2000, 0);
+        fs::write(&rom_path, &rom)?;
+        assert!(matches!(load(), Err(Error::MissingPrgRom)));
+
+        let mut rom = b"NES\x1a\x00\x01\x00\x08\x00\x00\x00\x00\x00\x00\x00\x00".to_vec();
+        rom.resize(HEADER_SIZE + 0x2000, 0);
+        fs::write(&rom_path, &rom)?;
+        assert!(matches!(load(), Err(Error::MissingPrgRom)));
+
+        let mut rom = nrom_test_rom(&[], [0xC000; 3]);
+        rom[6..8].copy_from_slice(&[0xF0, 0xF0]); // Mapper 255
+        fs::write(&rom_path, &rom)?;
+        assert!(matches!(load(), Err(Error::UnsupportedMapper(255))));
+
+        fs::remove_dir_all(&directory)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_fds_loading() -> Result<(), std::io::Error> {
+        let directory = std::env::temp_dir().join(format!("nesemulator-test-fds-{}", std::process::id()));
+        fs::create_dir_all(&directory)?;
+        let disk_path = directory.join("game.fds");
+        let load = || Cartridge::new_from_file(disk_path.to_string_lossy().into_owned(), &LoadOptions::default());
+
+        let mut image = b"\x01*NINTENDO-HVC*".to_vec();
+        image.resize(2 * fds::DISK_SIDE_SIZE, 0);
+        fs::write(&disk_path, &image)?;
+        assert!(matches!(load(), Err(Error::FdsBiosNotFound)));
+        assert!(matches!(Cartridge::new_from_bytes(&image, &LoadOptions::default()), Err(Error::FdsBiosNotFound)));
+
+        fs::write(directory.join(FDS_BIOS_FILE_NAME), [0; 0x1000])?;
+        assert!(matches!(load(), Err(Error::InvalidFdsBios { size: 0x1000 })));
+
+        let mut bios = vec![0; fds::BIOS_SIZE];
+        bios[0x1FFC] = 0x24;
+        fs::write(directory.join(FDS_BIOS_FILE_NAME), &bios)?;
+        let mut cartridge = load().unwrap();
+        assert_eq!(cartridge.header().format, crate::rom_header::RomFormat::Fds);
+        assert_eq!(cartridge.read_using_cpu_bus_address(0xFFFC), Some(0x24));
+        cartridge.write_using_cpu_bus_address(0xDFFF, 0x42);
+        assert_eq!(cartridge.read_using_cpu_bus_address(0xDFFF), Some(0x42));
+
+        assert_eq!(cartridge.disk_side_count(), 2);
+        assert_eq!(cartridge.disk_side(), Some(0));
+        cartridge.set_disk_side(Some(2));
+        assert_eq!(cartridge.disk_side(), Some(0));
+        cartridge.set_disk_side(Some(1));
+        assert_eq!(cartridge.disk_side(), Some(1));
+        cartridge.set_disk_side(None);
+        assert_eq!(cartridge.disk_side(), None);
+
+        let options = LoadOptions {
+            fds_bios: Some(bios),
+            ..LoadOptions::default()
+        };
+        assert!(Cartridge::new_from_bytes(&image, &options).is_ok());
+        assert!(matches!(Cartridge::new_from_bytes(&image[..100], &options), Err(Error::InvalidDiskImage)));
+
+        fs::remove_dir_all(&directory)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_unif_loading() -> Result<(), std::io::Error> {
+        let mut rom = b"UNIF\x07\x00\x00\x00".to_vec();
+        rom.resize(32, 0);
+        for (id, contents) in [(b"MAPR", &b"NES-NROM-256\0"[..]), (b"PRG0", &[0x42; 0x8000]), (b"CHR0", &[0x24; 0x2000])] {
+            rom.extend_from_slice(id);
+            rom.extend_from_slice(&(contents.len() as u32).to_le_bytes());
+            rom.extend_from_slice(contents);
+        }
+        let cartridge = Cartridge::new_from_bytes(&rom, &LoadOptions::default()).unwrap();
+        assert_eq!(cartridge.header().format, crate::rom_header::RomFormat::Unif);
+        assert_eq!(cartridge.read_using_cpu_bus_address(0x8000), Some(0x42));
+        assert_eq!(cartridge.read_from_pattern_table(0x0000), 0x24);
+        Ok(())
+    }
+
+    #[test]
+    fn test_bus_conflicts_option() -> Result<(), std::io::Error> {
+        // UxROM with two PRG ROM banks, each filled with its own number
+        let mut rom = b"NES\x1a\x02\x00\x20\x00\x00\x00\x00\x00\x00\x00\x00\x00".to_vec();
+        rom.extend(vec![0x00; 0x4000]);
+        rom.extend(vec![0x01; 0x4000]);
+
+        // The ROM byte 0x00 at the written address is ANDed with the bank number
+        let mut cartridge = Cartridge::new_from_bytes(&rom, &LoadOptions::default()).unwrap();
+        cartridge.write_using_cpu_bus_address(0x8000, 0x01);
+        assert_eq!(cartridge.read_using_cpu_bus_address(0x8000), Some(0x00));
+
+        let options = LoadOptions {
+            bus_conflicts: Some(false),
+            ..LoadOptions::default()
+        };
+        let mut cartridge = Cartridge::new_from_bytes(&rom, &options).unwrap();
+        cartridge.write_using_cpu_bus_address(0x8000, 0x01);
+        assert_eq!(cartridge.read_using_cpu_bus_address(0x8000), Some(0x01));
+        Ok(())
+    }
+
+    #[test]
+    fn test_nametable_mirroring() -> Result<(), std::io::Error> {
+        let mut vram = Ram::new(0x0800);
+        let mut rom = nrom_test_rom(&[], [0xC000; 3]);
+        rom[6] = 0x08; // Four-screen mirroring
+        let mut cartridge = Cartridge::new_from_bytes(&rom, &LoadOptions::default()).unwrap();
+        assert_eq!(cartridge.mirroring(), NametableMirroring::FourScreen);
+
+        // Four separate nametables, two of them in cartridge VRAM
+        for nametable in 0..4 {
+            cartridge.write_to_nametable(0x2000 + nametable * 0x0400, &mut vram, nametable as u8 + 1);
+        }
+        for nametable in 0..4 {
+            assert_eq!(cartridge.read_from_nametable(0x2000 + nametable * 0x0400, &vram), nametable as u8 + 1);
+            assert_eq!(cartridge.read_from_nametable(0x3000 + nametable * 0x0400, &vram), nametable as u8 + 1);
+        }
+        assert_eq!(vram.read(0x0000), 1);
+        assert_eq!(vram.read(0x0400), 2);
+
+        let mut cartridge = Cartridge::new_with_mapper(Box::new(
+            mapper::Nrom::new(Memory::default(), NametableMirroring::SingleScreenB)
+        ));
+        cartridge.write_to_nametable(0x2C05, &mut vram, 0x42);
+        assert_eq!(cartridge.read_from_nametable(0x2005, &vram), 0x42);
+        assert_eq!(vram.read(0x0405), 0x42);
+        Ok(())
+    }
+
+    #[test]
+    fn test_trainer() -> Result<(), std::io::Error> {
+        let mut rom = nrom_test_rom(&[], [0xC000; 3]);
+        rom[6] = 0x04; // Trainer
+        rom.splice(HEADER_SIZE..HEADER_SIZE, (0..TRAINER_SIZE).map(|i| i as u8));
+        let cartridge = Cartridge::new_from_bytes(&rom, &LoadOptions::default()).unwrap();
+        assert_eq!(cartridge.read_using_cpu_bus_address(0x6FFF), Some(0x00));
+        assert_eq!(cartridge.read_using_cpu_bus_address(0x7000), Some(0x00));
+        assert_eq!(cartridge.read_using_cpu_bus_address(0x7001), Some(0x01));
+        assert_eq!(cartridge.read_using_cpu_bus_address(0x71FF), Some(0xFF));
+        assert_eq!(cartridge.read_using_cpu_bus_address(0x7200), Some(0x00));
+        assert_eq!(cartridge.read_using_cpu_bus_address(0x8000), Some(0xEA));
+        assert_eq!(cartridge.read_from_pattern_table(0x0000), 0xEA);
+        Ok(())
+    }
+}
@@ -1,7 +1,9 @@
 use super::Cpu;
+use super::instruction::Access;
+
 #[allow(non_camel_case_types)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum AddressMode {
-    Invalid,
     Abs, AbsX, AbsY, // Absolute  (indexed)
     Ind, IndX, IndY, // Indirect  (indexed)
     Zpg, ZpgX, ZpgY, // Zero page (indexed)
@@ -11,100 +13,141 @@ pub enum AddressMode {
     Imm  // Immediate
 }
 
+/// Per-cycle effective address calculation.
+///
+/// Each function runs cycle `t` of the instruction (cycle 1 being the opcode fetch) and does exactly one bus access.
+/// They return true when the effective address is in `self.address` and the data access can begin on the next cycle.
+///
+/// Indexed modes first read from the address before the page crossing has been fixed.
+/// For read instructions that read is the actual data access if no page was crossed,
+/// otherwise it is a dummy read and the access is repeated with the fixed address.
+///
+/// Useful links:
+/// [6502_cpu.txt]
+///
+/// [6502_cpu.txt]: https://www.nesdev.org/6502_cpu.txt
 impl Cpu {
-    pub fn abs  (&mut self) -> u16 {
-        let address = self.read_16(self.program_counter);
-        self.program_counter += 2;
-        address
-    }
-
-    pub fn abs_x(&mut self) -> u16 {
-        let address_low = self.read_8(self.program_counter) as u16;
-        self.program_counter += 1;
-        let address_high = self.read_8(self.program_counter) as u16;
-        self.program_counter += 1;
-        let address_low_x = address_low.wrapping_add(self.x_index as u16);
-        self.page_crossed = address_low_x > 0xFF;
-        address_low_x.wrapping_add(address_high << 8)
-    }
-
-    pub fn abs_y(&mut self) -> u16 {
-        let address_low = self.read_8(self.program_counter) as u16;
-        self.program_counter += 1;
-        let address_high = self.read_8(self.program_counter) as u16;
-        self.program_counter += 1;
-        let address_low_y = address_low.wrapping_add(self.y_index as u16);
-        self.page_crossed = address_low_y > 0xFF;
-        address_low_y.wrapping_add(address_high << 8)
-    }
-
-    pub fn ind(&mut self) -> u16 {
-        let address = self.read_16(self.program_counter);
-        self.program_counter += 2;
-
-        let byte_low = self.read_8(address) as u16;
-        let byte_high = self.read_8((address & 0xFF00) | (address.wrapping_add(1) & 0x00FF)) as u16;
-        byte_low | (byte_high << 8)
+    pub fn address_mode_cycle(&mut self, address_mode: AddressMode, access: Access, t: u8) -> bool {
+        use AddressMode::*;
+        match address_mode {
+            Abs  => self.abs(t),
+            AbsX => self.abs_indexed(t, self.x_index, access),
+            AbsY => self.abs_indexed(t, self.y_index, access),
+            IndX => self.ind_x(t),
+            IndY => self.ind_y(t, access),
+            Zpg  => self.zpg(),
+            ZpgX => self.zpg_indexed(t, self.x_index),
+            ZpgY => self.zpg_indexed(t, self.y_index),
+            Imm | Imp | Acc | Rel | Ind => unreachable!("Address mode {:?} is handled by the instruction", address_mode),
+        }
     }
 
-    pub fn ind_x(&mut self) -> u16 {
-        let a = self.read_8(self.program_counter).wrapping_add(self.x_index);
-        self.program_counter += 1;
-        let b = a.wrapping_add(1);
-        (self.read_8(a as u16) as u16).wrapping_add((self.read_8(b as u16) as u16) << 8)
+    pub fn fetch_operand(&mut self) -> u8 {
+        let value = self.read_8(self.program_counter);
+        self.program_counter = self.program_counter.wrapping_add(1);
+        value
     }
 
-    pub fn ind_y(&mut self) -> u16 {
-        let a = self.read_8(self.program_counter);
-        self.program_counter += 1;
-        let b = a.wrapping_add(1);
-        let address = (self.read_8(a as u16) as u16).wrapping_add((self.read_8(b as u16) as u16) << 8);
-        let address_y = address.wrapping_add(self.y_index as u16);
-        self.page_crossed = self.crossing_page(address, address_y);
-        address_y
+    fn abs(&mut self, t: u8) -> bool {
+        match t {
+            2 => {
+                self.address = self.fetch_operand() as u16;
+                false
+            },
+            _ => {
+                self.address |= (self.fetch_operand() as u16) << 8;
+                true
+            },
+        }
     }
 
-    pub fn zpg(&mut self) -> u16 {
-        let value = self.read_8(self.program_counter);
-        self.program_counter += 1;
-        value as u16
+    fn abs_indexed(&mut self, t: u8, index: u8, access: Access) -> bool {
+        match t {
+            2 => {
+                self.address = self.fetch_operand() as u16;
+                false
+            },
+            3 => {
+                self.base_address = self.address | (self.fetch_operand() as u16) << 8;
+                self.index_base_address(index);
+                access == Access::Read && !self.page_crossed
+            },
+            _ => self.fix_page_crossing(),
+        }
     }
 
-    pub fn zpg_x(&mut self) -> u16 {
-        let value = self.read_8(self.program_counter).wrapping_add(self.x_index);
-        self.program_counter += 1;
-        value as u16
+    fn ind_x(&mut self, t: u8) -> bool {
+        match t {
+            2 => {
+                self.pointer = self.fetch_operand();
+                false
+            },
+            3 => {
+                self.read_8(self.pointer as u16); // Dummy read
+                self.pointer = self.pointer.wrapping_add(self.x_index);
+                false
+            },
+            4 => {
+                self.address = self.read_8(self.pointer as u16) as u16;
+                false
+            },
+            _ => {
+                self.address |= (self.read_8(self.pointer.wrapping_add(1) as u16) as u16) << 8;
+                true
+            },
+        }
     }
 
-    pub fn zpg_y(&mut self) -> u16 {
-        let value = self.read_8(self.program_counter).wrapping_add(self.y_index);
-        self.program_counter += 1;
-        value as u16
+    fn ind_y(&mut self, t: u8, access: Access) -> bool {
+        match t {
+            2 => {
+                self.pointer = self.fetch_operand();
+                false
+            },
+            3 => {
+                self.address = self.read_8(self.pointer as u16) as u16;
+                false
+            },
+            4 => {
+                self.base_address = self.address | (self.read_8(self.pointer.wrapping_add(1) as u16) as u16) << 8;
+                self.index_base_address(self.y_index);
+                access == Access::Read && !self.page_crossed
+            },
+            _ => self.fix_page_crossing(),
+        }
     }
 
-    pub fn imp(&self) -> u16 {
-        0
+    fn zpg(&mut self) -> bool {
+        self.address = self.fetch_operand() as u16;
+        true
     }
 
-    pub fn rel(&mut self) -> u16 {
-        let offset = self.read_8(self.program_counter) as u16;
-        self.program_counter += 1;
-
-        if offset < 0x80u16 {
-            offset.wrapping_add(self.program_counter)
-        }
-        else {
-            offset.wrapping_add(self.program_counter).wrapping_add(0xFF00u16)
+    fn zpg_indexed(&mut self, t: u8, index: u8) -> bool {
+        match t {
+            2 => {
+                self.address = self.fetch_operand() as u16;
+                false
+            },
+            _ => {
+                self.read_8(self.address); // Dummy read
+                self.address = (self.address as u8).wrapping_add(index) as u16;
+                true
+            },
         }
     }
 
-    pub fn acc  (&self) -> u16 {
-        0
+    /// Adds the index to the low byte of the base address without carrying to the high byte.
+    fn index_base_address(&mut self, index: u8) {
+        let address = self.base_address.wrapping_add(index as u16);
+        self.page_crossed = self.crossing_page(self.base_address, address);
+        self.address = (self.base_address & 0xFF00) | (address & 0x00FF);
     }
 
-    pub fn imm  (&mut self) -> u16 {
-        let pc = self.program_counter;
-        self.program_counter += 1;
-        pc
+    fn fix_page_crossing(&mut self) -> bool {
+        self.read_8(self.address); // Dummy read from the address before fixing the high byte
+        if self.page_crossed {
+            self.address = self.address.wrapping_add(0x0100);
+        }
+        true
     }
-}
\ No newline at end of file
+}
@@ -1,7 +1,9 @@
 use crate::cpu::ram::Ram;
 use crate::cartridge::Cartridge;
 use crate::ppu::Ppu;
+use crate::apu::Apu;
 use crate::controller::Controller;
+use crate::cpu::irq::{IrqLine, IrqSource};
 
 use std::cell::RefCell;
 use std::rc::Rc;
@@ -10,9 +12,15 @@ pub struct Bus {
     ram: Ram,
     cartridge: Rc<RefCell<Cartridge>>,
     pub ppu: Option<Ppu>,
+    pub apu: Option<Apu>,
     pub oamdma_occurred: bool,
     pub oamdma_high_byte: u16,
     pub controller: Option<Controller>,
+    pub irq: IrqLine,
+    /// Last value on the data bus. Reads from addresses that nothing drives return it ([open bus]).
+    ///
+    /// [open bus]: https://www.nesdev.org/wiki/Open_bus_behavior
+    open_bus: u8,
 }
 
 impl Bus {
@@ -21,81 +29,120 @@ impl Bus {
         if size != 0x0800 {
             panic!("Creating a new Bus: CPU RAM does not have correct size (0x0800)");
         }
-        Bus { ram, cartridge, ppu: None, controller: None, oamdma_occurred: false, oamdma_high_byte: 0 }
+        Bus { ram, cartridge, ppu: None, apu: None, controller: None, oamdma_occurred: false, oamdma_high_byte: 0, irq: IrqLine::new(), open_bus: 0 }
     }
 
     pub fn set_ppu(&mut self, ppu: Ppu) {
         self.ppu = Some(ppu);
     }
 
+    pub fn set_apu(&mut self, apu: Apu) {
+        self.apu = Some(apu);
+    }
+
     pub fn set_controller(&mut self, controller: Controller) {
         self.controller = Some(controller);
     }
 
+    /// Clocks the cartridge once. Should be called every CPU cycle.
+    pub fn clock_cartridge(&mut self) {
+        let mut cartridge = self.cartridge.borrow_mut();
+        cartridge.clock_cpu();
+        self.irq.set(IrqSource::Mapper, cartridge.irq());
+    }
+
+    /// Clocks the APU once. Should be called every CPU cycle.
+    pub fn clock_apu(&mut self) {
+        if let Some(apu) = self.apu.as_mut() {
+            apu.step();
+        }
+        self.update_apu_irq();
+    }
+
+    fn update_apu_irq(&mut self) {
+        if let Some(apu) = self.apu.as_ref() {
+            self.irq.set(IrqSource::FrameCounter, apu.frame_interrupt());
+            self.irq.set(IrqSource::Dmc, apu.dmc_interrupt());
+        }
+    }
+
+    /// Returns the address of the sample byte the DMC is waiting for, if any.
+    pub fn dmc_dma_request(&self) -> Option<u16> {
+        self.apu.as_ref().and_then(|apu| apu.dmc_dma_request())
+    }
+
+    pub fn complete_dmc_dma(&mut self, value: u8) {
+        if let Some(apu) = self.apu.as_mut() {
+            apu.complete_dmc_dma(value);
+        }
+        self.update_apu_irq();
+    }
+
     pub fn read(&mut self, address: u16) -> u8 {
-        match address {
+        let value = match address {
             0x0000..=0x07FF => self.ram.read(address as usize), // CPU RAM
             0x0800..=0x1FFF => self.ram.read((address % 0x0800) as usize), // CPU RAM (mirror)
             0x2000..=0x2007 => self.read_ppu_register(address), // PPU registers
             0x2008..=0x3FFF => self.read_ppu_register((address - 0x2008u16) % 0x0008u16 + 0x2000u16), // PPU registers (mirror)
+            0x4015 => {
+                // Reading the APU status is internal to the CPU, so it does not change the data bus
+                return self.read_apu_status();
+            },
             0x4000..=0x401F => self.read_apu_io_registers(address), // NES APU and I/O registers
-            0x6000..=0xFFFF => self.cartridge.borrow().read_using_cpu_bus_address(address as usize), // Cartridge (PRG ROM, PRG RAM, and mapper)
-            _ => panic!("CPU bus: unknown address {}", address),
-        }
+            0x4020..=0x5FFF => self.cartridge.borrow_mut().read_expansion(address).unwrap_or(self.open_bus), // Cartridge expansion area
+            0x6000..=0xFFFF => self.cartridge.borrow().read_using_cpu_bus_address(address as usize).unwrap_or(self.open_bus), // Cartridge (PRG ROM, PRG RAM, and mapper)
+        };
+        self.open_bus = value;
+        value
     }
 
-
+    /// Returns the last value on the data bus.
+    pub fn open_bus(&self) -> u8 {
+        self.open_bus
+    }
 
     pub fn write(&mut self, address: u16, value: u8) {
+        self.open_bus = value;
         match address {
             0x0000..=0x07FF => self.ram.write(address as usize, value), // CPU RAM
             0x0800..=0x1FFF => self.ram.write((address % 0x0800) as usize, value), // CPU RAM (mirror)
             0x2000..=0x2007 => self.write_ppu_register(address, value), // PPU registers
             0x2008..=0x3FFF => self.write_ppu_register((address - 0x2008u16) % 0x0008u16 + 0x2000u16, value), // PPU registers (mirror)
             0x4000..=0x401F => self.write_apu_io_registers(address, value), // NES APU and I/O registers
-            0x6000..=0xFFFF => unimplemented!(), // Cartridge (PRG ROM, PRG RAM, and mapper)
-            _ => panic!("CPU bus: unknown address {}", address),
+            0x4020..=0x5FFF => self.cartridge.borrow_mut().write_expansion(address, value), // Cartridge expansion area
+            0x6000..=0xFFFF => self.cartridge.borrow_mut().write_using_cpu_bus_address(address as usize, value), // Cartridge (PRG ROM, PRG RAM, and mapper)
         }
     }
 
     fn read_ppu_register(&mut self, address: u16) -> u8 {
-        let ppu: &mut Ppu = self.ppu.as_mut().unwrap();
-        match address {
-            0x2000 => ppu.ppuctrl,
-            0x2001 => ppu.ppumask,
-            0x2002 => ppu.read_ppustatus(),
-            0x2003 => ppu.oamaddr,
-            0x2004 => ppu.oamdata,
-            0x2005 => ppu.ppuscroll,
-            0x2006 => panic!("CPU bus: reading from write-only PPU register {} aka PPUADDR.", address),
-            0x2007 => ppu.read_ppudata(),
-            _ => panic!("CPU bus: unknown PPU register {}", address)
+        match self.ppu.as_mut() {
+            Some(ppu) => ppu.read_register(address),
+            None => self.open_bus, // There is no PPU when playing NSF files
         }
     }
 
     fn write_ppu_register(&mut self, address: u16, value: u8) {
-        let ppu = self.ppu.as_mut().unwrap();
+        if let Some(ppu) = self.ppu.as_mut() {
+            ppu.write_register(address, value);
+        }
+    }
 
-        match address {
-            0x2000 => ppu.write_ppuctrl(value),
-            0x2001 => ppu.ppumask = value,
-            0x2002 => ppu.ppustatus = value,
-            0x2003 => ppu.oamaddr = value,
-            0x2004 => ppu.oamdata = value,
-            0x2005 => ppu.write_ppuscroll(value),
-            0x2006 => ppu.write_ppuaddr(value),
-            0x2007 => ppu.write_ppudata(value),
-            _ => panic!("CPU bus: unknown PPU register {}", address)
-        };
+    /// Reads APU status. Bit 5 is not driven by the APU, so it comes from the data bus.
+    fn read_apu_status(&mut self) -> u8 {
+        let status = self.apu.as_mut().map_or(0, |apu| apu.read_status());
+        self.update_apu_irq();
+        status | (self.open_bus & 0x20)
     }
 
     fn read_apu_io_registers(&mut self, address: u16) -> u8 {
         debug_assert!((0x4000..=0x401f).contains(&address));
         match address {
-            0x4000..=0x4015 => 0,
-            0x4016 => {
-                self.controller.as_mut().map_or(1, |c| c.read()) },
-            0x4017..=0x401f => 0,
+            0x4000..=0x4014 => self.open_bus, // Write-only APU registers and OAMDMA
+            0x4015 => self.read_apu_status(),
+            // The controller ports drive only the lower bits, the upper 3 bits come from the data bus
+            0x4016 => (self.open_bus & 0xE0) | self.controller.as_mut().map_or(1, |c| c.read()),
+            0x4017 => self.open_bus & 0xE0, // No controller in port 2
+            0x4018..=0x401f => self.open_bus, // APU and I/O functionality that is normally disabled
             _ => unreachable!()
         }
     }
@@ -103,11 +150,15 @@ impl Bus {
     fn write_apu_io_registers(&mut self, address: u16, value: u8) {
         debug_assert!((0x4000..=0x401f).contains(&address)); // TODO add this kind of assert to other similiar functions that uses match.
         match address {
-            0x4000..=0x4013 => (), // TODO Implement
+            0x4000..=0x4013 | 0x4015 | 0x4017 => {
+                if let Some(apu) = self.apu.as_mut() {
+                    apu.write_register(address, value);
+                }
+                self.update_apu_irq();
+            },
             0x4014 => self.oamdma(value),
-            0x4015 => (), // TODO Implement
             0x4016 => { if let Some(c) = self.controller.as_mut() { c.write(value)} },
-            0x4017..=0x401f => (), // TODO Implement
+            0x4018..=0x401f => (), // APU and I/O functionality that is normally disabled
             _ => unreachable!()
         }
     }
@@ -116,4 +167,44 @@ impl Bus {
         self.oamdma_occurred = true;
         self.oamdma_high_byte = (value as u16) << 8;
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::NametableMirroring;
+    use crate::mapper::{Memory, Nrom};
+
+    #[test]
+    fn test_open_bus() -> Result<(), std::io::Error> {
+        let mut bus = Bus::new(Ram::new(0x0800), Rc::new(RefCell::new(Cartridge::new())));
+        bus.set_apu(Apu::default());
+        bus.set_controller(Controller::new());
+
+        bus.write(0x0000, 0xA5);
+        assert_eq!(bus.read(0x0000), 0xA5);
+        assert_eq!(bus.read(0x4018), 0xA5);
+        assert_eq!(bus.read(0x5000), 0xA5);
+        assert_eq!(bus.read(0x4000), 0xA5);
+        assert_eq!(bus.read(0x2006), 0xA5, "No PPU");
+        assert_eq!(bus.read(0x8000), 0xA5, "No PRG ROM");
+        assert_eq!(bus.read(0x4016), 0xA0);
+        assert_eq!(bus.read(0x4017), 0xA0);
+
+        // Reading APU status does not change the data bus but bit 5 comes from it
+        bus.write(0x0000, 0xFF);
+        bus.read(0x0000);
+        assert_eq!(bus.read(0x4015), 0x20);
+        assert_eq!(bus.open_bus(), 0xFF);
+
+        // Boards without PRG RAM leave 0x6000..=0x7FFF undriven
+        let mut memory = Memory::new(vec![0xEA; 0x4000], Vec::new());
+        memory.prg_ram = Vec::new();
+        let cartridge = Cartridge::new_with_mapper(Box::new(Nrom::new(memory, NametableMirroring::Horizontal)));
+        let mut bus = Bus::new(Ram::new(0x0800), Rc::new(RefCell::new(cartridge)));
+        bus.write(0x0000, 0x5A);
+        assert_eq!(bus.read(0x6000), 0x5A);
+        assert_eq!(bus.read(0x8000), 0xEA);
+        assert_eq!(bus.read(0x7FFF), 0xEA);
+        Ok(())
+    }
+}
@@ -1,8 +1,8 @@
 use super::Cpu;
 
 #[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum Instruction {
-    Invalid,
     ADC, AND, ASL, BCC, BCS, BEQ, BIT,
     BMI, BNE, BPL, BRK, BVC, BVS, CLC,
     CLD, CLI, CLV, CMP, CPX, CPY, DEC,
@@ -11,352 +11,488 @@ pub enum Instruction {
     PHA, PHP, PLA, PLP, ROL, ROR, RTI,
     RTS, SBC, SEC, SED, SEI, STA, STX,
     STY, TAX, TAY, TSX, TXA, TXS, TYA,
+    // Unofficial instructions
+    ALR, ANC, ANE, ARR, DCP, ISB, JAM,
+    LAS, LAX, LXA, RLA, RRA, SAX, SBX,
+    SHA, SHX, SHY, SLO, SRE, TAS,
 }
 
-impl Cpu {
-    pub fn adc(&mut self, address: u16) {
-        let value = self.read_8(address);
-        let values_added = value as u16 + self.accumulator as u16 + self.status.carry as u16;
-
-        self.status.carry = values_added > 0xFF;
-        self.status.zero = values_added & 0xFF == 0;
-        self.status.negative = values_added & 0x80 == 0x80;
-        self.status.overflow = value & 0x80 == self.accumulator & 0x80 && value as u16 & 0x80 != values_added & 0x80;
-        self.accumulator = (values_added & 0xFF) as u8;
-        if self.page_crossed { self.skip_cycles += 1; }
-    }
-
-    pub fn and(&mut self, address: u16) {
-        let value = self.read_8(address) & self.accumulator;
-        self.status.negative = value & 0x80 == 0x80;
-        self.status.zero = value == 0;
-        self.accumulator = value;
-        if self.page_crossed { self.skip_cycles += 1; }
-    }
-
-    pub fn asl(&mut self, address: u16) {
-        let mut value = self.read_8(address);
-        self.status.carry = value & 0x80 == 0x80;
-        value <<= 1;
-        self.status.negative = value & 0x80 == 0x80;
-        self.status.zero = value == 0;
-        self.write_8(address, value);
-    }
-
-    pub fn asl_acc(&mut self, _address: u16) {
-        let mut value = self.accumulator;
-        self.status.carry = value & 0x80 == 0x80;
-        value <<= 1;
-        self.status.negative = value & 0x80 == 0x80;
-        self.status.zero = value == 0;
-        self.accumulator = value;
-    }
+/// How an instruction accesses the memory at its effective address.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Access {
+    /// One read cycle.
+    Read,
+    /// One write cycle.
+    Write,
+    /// Read cycle, dummy write of the unmodified value and write of the modified value.
+    ReadModifyWrite,
+}
 
-    pub fn bcc(&mut self, address: u16) {
-        if !self.status.carry {
-            if self.crossing_page(address, self.program_counter) { self.skip_cycles += 1;}
-            self.program_counter = address;
-            self.skip_cycles += 1;
+impl Instruction {
+    pub fn access(self) -> Access {
+        use Instruction::*;
+        match self {
+            STA | STX | STY | SAX | SHA | SHX | SHY | TAS => Access::Write,
+            ASL | LSR | ROL | ROR | INC | DEC | SLO | RLA | SRE | RRA | DCP | ISB => Access::ReadModifyWrite,
+            _ => Access::Read,
         }
     }
+}
 
-    pub fn bcs(&mut self, address: u16) {
-        if self.status.carry {
-            if self.crossing_page(address, self.program_counter) { self.skip_cycles += 1;}
-            self.program_counter = address;
-            self.skip_cycles += 1;
+/// Instruction sequences that do not follow the regular addressing modes.
+///
+/// Each function runs cycle `t` of the instruction (cycle 1 being the opcode fetch) and does exactly one bus access.
+/// They return true on the last cycle of the instruction.
+impl Cpu {
+    /// BRK and the IRQ and NMI interrupt sequences.
+    ///
+    /// The vector is selected while pushing the status. An NMI detected by then hijacks the sequence of BRK or IRQ.
+    pub fn interrupt_cycle(&mut self, t: u8) -> bool {
+        match t {
+            2 => {
+                // BRK skips the padding byte. Hardware interrupts do not increment the program counter.
+                self.read_8(self.program_counter);
+                if !self.hardware_interrupt {
+                    self.program_counter = self.program_counter.wrapping_add(1);
+                }
+                false
+            },
+            3 => {
+                self.stack_push_8((self.program_counter >> 8) as u8);
+                false
+            },
+            4 => {
+                self.stack_push_8(self.program_counter as u8);
+                false
+            },
+            5 => {
+                // B flag is set only when pushed by BRK
+                let b_flag = if self.hardware_interrupt { 0x00 } else { 0x10 };
+                self.stack_push_8(self.status.get_as_byte() | b_flag);
+                self.interrupt_vector = if self.nmi_detected {
+                    self.nmi_detected = false;
+                    super::NMI_VECTOR
+                } else {
+                    super::IRQ_VECTOR
+                };
+                false
+            },
+            6 => {
+                self.address = self.read_8(self.interrupt_vector) as u16;
+                self.status.interrupt = true;
+                false
+            },
+            _ => {
+                self.program_counter = self.address | (self.read_8(self.interrupt_vector + 1) as u16) << 8;
+                self.hardware_interrupt = false;
+                true
+            },
         }
     }
 
-    pub fn beq(&mut self, address: u16) {
-        if self.status.zero {
-            if self.crossing_page(address, self.program_counter) { self.skip_cycles += 1;}
-            self.program_counter = address;
-            self.skip_cycles += 1;
+    pub fn jmp_cycle(&mut self, t: u8) -> bool {
+        match t {
+            2 => {
+                self.address = self.fetch_operand() as u16;
+                false
+            },
+            _ => {
+                self.program_counter = self.address | (self.read_8(self.program_counter) as u16) << 8;
+                true
+            },
         }
     }
 
-    pub fn bit(&mut self, address: u16) {
-        let value = self.read_8(address);
-        let value_masked =  value & self.accumulator;
-        self.status.zero = value_masked == 0;
-        self.status.negative = value & 0x80 == 0x80;
-        self.status.overflow = value & 0x40 == 0x40;
-    }
-
-    pub fn bmi(&mut self, address: u16) {
-        if self.status.negative {
-            if self.crossing_page(address, self.program_counter) { self.skip_cycles += 1;}
-            self.program_counter = address;
-            self.skip_cycles += 1;
+    /// The high byte of the target is read without carrying to the high byte of the pointer.
+    pub fn jmp_indirect_cycle(&mut self, t: u8) -> bool {
+        match t {
+            2 => {
+                self.base_address = self.fetch_operand() as u16;
+                false
+            },
+            3 => {
+                self.base_address |= (self.fetch_operand() as u16) << 8;
+                false
+            },
+            4 => {
+                self.address = self.read_8(self.base_address) as u16;
+                false
+            },
+            _ => {
+                let pointer_high = (self.base_address & 0xFF00) | (self.base_address.wrapping_add(1) & 0x00FF);
+                self.program_counter = self.address | (self.read_8(pointer_high) as u16) << 8;
+                true
+            },
         }
     }
 
-    pub fn bne(&mut self, address: u16) {
-        if !self.status.zero {
-            if self.crossing_page(address, self.program_counter) { self.skip_cycles += 1;}
-            self.program_counter = address;
-            self.skip_cycles += 1;
+    pub fn jsr_cycle(&mut self, t: u8) -> bool {
+        match t {
+            2 => {
+                self.address = self.fetch_operand() as u16;
+                false
+            },
+            3 => {
+                self.read_8(0x0100 + self.stack_pointer as u16); // Dummy read
+                false
+            },
+            4 => {
+                self.stack_push_8((self.program_counter >> 8) as u8);
+                false
+            },
+            5 => {
+                self.stack_push_8(self.program_counter as u8);
+                false
+            },
+            _ => {
+                self.program_counter = self.address | (self.read_8(self.program_counter) as u16) << 8;
+                true
+            },
         }
     }
 
-    pub fn bpl(&mut self, address: u16) {
-        if !self.status.negative {
-            if self.crossing_page(address, self.program_counter) { self.skip_cycles += 1;}
-            self.program_counter = address;
-            self.skip_cycles += 1;
+    pub fn rts_cycle(&mut self, t: u8) -> bool {
+        match t {
+            2 => {
+                self.read_8(self.program_counter); // Dummy read
+                false
+            },
+            3 => {
+                self.read_8(0x0100 + self.stack_pointer as u16); // Dummy read
+                false
+            },
+            4 => {
+                self.program_counter = self.stack_pop_8() as u16;
+                false
+            },
+            5 => {
+                self.program_counter |= (self.stack_pop_8() as u16) << 8;
+                false
+            },
+            _ => {
+                self.read_8(self.program_counter); // Dummy read
+                self.program_counter = self.program_counter.wrapping_add(1);
+                true
+            },
         }
     }
 
-    pub fn brk(&mut self, _address: u16) {
-        self.program_counter += 1; // BRK is actually 2-byte instruction
-        self.stack_push_16(self.program_counter);
-        self.stack_push_8(self.status.get_as_byte());
-        self.status.interrupt = true;
-        let irq_vector = self.read_16(0xfffe);
-        self.program_counter = irq_vector;
-    }
-
-    pub fn bvc(&mut self, address: u16) {
-        if !self.status.overflow {
-            if self.crossing_page(address, self.program_counter) { self.skip_cycles += 1;}
-            self.program_counter = address;
-            self.skip_cycles += 1;
+    pub fn rti_cycle(&mut self, t: u8) -> bool {
+        match t {
+            2 => {
+                self.read_8(self.program_counter); // Dummy read
+                false
+            },
+            3 => {
+                self.read_8(0x0100 + self.stack_pointer as u16); // Dummy read
+                false
+            },
+            4 => {
+                let status = self.stack_pop_8();
+                self.status.set_from_byte(status);
+                false
+            },
+            5 => {
+                self.program_counter = self.stack_pop_8() as u16;
+                false
+            },
+            _ => {
+                self.program_counter |= (self.stack_pop_8() as u16) << 8;
+                true
+            },
         }
     }
 
-    pub fn bvs(&mut self, address: u16) {
-        if self.status.overflow {
-            if self.crossing_page(address, self.program_counter) { self.skip_cycles += 1;}
-            self.program_counter = address;
-            self.skip_cycles += 1;
+    /// PHA and PHP
+    pub fn push_cycle(&mut self, t: u8) -> bool {
+        match t {
+            2 => {
+                self.read_8(self.program_counter); // Dummy read
+                false
+            },
+            _ => {
+                let value = match self.opcode.instruction {
+                    Instruction::PHA => self.accumulator,
+                    // http://wiki.nesdev.com/w/index.php/Status_flags#The_B_flag
+                    _ => self.status.get_as_byte() | 0x10,
+                };
+                self.stack_push_8(value);
+                true
+            },
         }
     }
 
-    pub fn clc(&mut self, _address: u16) {
-        self.status.carry = false;
-    }
-
-    pub fn cld(&mut self, _address: u16) {
-        self.status.decimal = false;
-    }
-
-    pub fn cli(&mut self, _address: u16) {
-        self.status.interrupt = false;
-    }
-
-    pub fn clv(&mut self, _address: u16) {
-        self.status.overflow = false;
-    }
-
-    pub fn cmp(&mut self, address: u16) {
-        let value = (self.accumulator as u16).wrapping_sub(self.read_8(address) as u16);
-        self.status.carry = value < 0x100;
-        self.status.zero = value == 0;
-        self.status.negative = value & 0x80 == 0x80;
-        if self.page_crossed { self.skip_cycles += 1; }
+    /// PLA and PLP
+    pub fn pull_cycle(&mut self, t: u8) -> bool {
+        match t {
+            2 => {
+                self.read_8(self.program_counter); // Dummy read
+                false
+            },
+            3 => {
+                self.read_8(0x0100 + self.stack_pointer as u16); // Dummy read
+                false
+            },
+            _ => {
+                let value = self.stack_pop_8();
+                match self.opcode.instruction {
+                    Instruction::PLA => {
+                        self.accumulator = value;
+                        self.set_zero_negative(value);
+                    },
+                    _ => self.status.set_from_byte(value),
+                }
+                true
+            },
+        }
     }
 
-    pub fn cpx(&mut self, address: u16) {
-        let value = (self.x_index as u16).wrapping_sub(self.read_8(address) as u16);
-        self.status.carry = value < 0x100;
-        self.status.zero = value == 0;
-        self.status.negative = value & 0x80 == 0x80;
+    /// A taken branch that does not cross a page does not poll interrupts on its last cycle.
+    pub fn branch_cycle(&mut self, t: u8) -> bool {
+        match t {
+            2 => {
+                let offset = self.fetch_operand() as i8;
+                if !self.branch_condition() {
+                    return true;
+                }
+                self.address = self.program_counter.wrapping_add(offset as u16);
+                self.page_crossed = self.crossing_page(self.address, self.program_counter);
+                self.suppress_interrupt_poll = !self.page_crossed;
+                false
+            },
+            3 => {
+                self.read_8(self.program_counter); // Dummy read
+                self.program_counter = (self.program_counter & 0xFF00) | (self.address & 0x00FF);
+                !self.page_crossed
+            },
+            _ => {
+                self.read_8(self.program_counter); // Dummy read from the address before fixing the high byte
+                self.program_counter = self.address;
+                true
+            },
+        }
     }
 
-    pub fn cpy(&mut self, address: u16) {
-        let value = (self.y_index as u16).wrapping_sub(self.read_8(address) as u16);
-        self.status.carry = value < 0x100;
-        self.status.zero = value == 0;
-        self.status.negative = value & 0x80 == 0x80;
+    fn branch_condition(&self) -> bool {
+        use Instruction::*;
+        match self.opcode.instruction {
+            BCC => !self.status.carry,
+            BCS => self.status.carry,
+            BEQ => self.status.zero,
+            BMI => self.status.negative,
+            BNE => !self.status.zero,
+            BPL => !self.status.negative,
+            BVC => !self.status.overflow,
+            BVS => self.status.overflow,
+            _ => unreachable!(),
+        }
     }
 
-    pub fn dec(&mut self, address: u16) {
-        let value = self.read_8(address).wrapping_sub(1);
-        self.status.zero = value == 0;
-        self.status.negative = value & 0x80 == 0x80;
-        self.write_8(address, value);
+    /// Two cycle instructions that operate on registers only.
+    pub fn implied_cycle(&mut self) -> bool {
+        use Instruction::*;
+        self.read_8(self.program_counter); // Dummy read
+        match self.opcode.instruction {
+            CLC => self.status.carry = false,
+            CLD => self.status.decimal = false,
+            CLI => self.status.interrupt = false,
+            CLV => self.status.overflow = false,
+            SEC => self.status.carry = true,
+            SED => self.status.decimal = true,
+            SEI => self.status.interrupt = true,
+            DEX => {
+                self.x_index = self.x_index.wrapping_sub(1);
+                self.set_zero_negative(self.x_index);
+            },
+            DEY => {
+                self.y_index = self.y_index.wrapping_sub(1);
+                self.set_zero_negative(self.y_index);
+            },
+            INX => {
+                self.x_index = self.x_index.wrapping_add(1);
+                self.set_zero_negative(self.x_index);
+            },
+            INY => {
+                self.y_index = self.y_index.wrapping_add(1);
+                self.set_zero_negative(self.y_index);
+            },
+            TAX => {
+                self.x_index = self.accumulator;
+                self.set_zero_negative(self.x_index);
+            },
+            TAY => {
+                self.y_index = self.accumulator;
+                self.set_zero_negative(self.y_index);
+            },
+            TSX => {
+                self.x_index = self.stack_pointer;
+                self.set_zero_negative(self.x_index);
+            },
+            TXA => {
+                self.accumulator = self.x_index;
+                self.set_zero_negative(self.accumulator);
+            },
+            TXS => self.stack_pointer = self.x_index,
+            TYA => {
+                self.accumulator = self.y_index;
+                self.set_zero_negative(self.accumulator);
+            },
+            NOP => (),
+            // Accumulator address mode
+            ASL | LSR | ROL | ROR => self.accumulator = self.modify(self.accumulator),
+            _ => unreachable!("{:?} is not an implied instruction", self.opcode.instruction),
+        }
+        true
+    }
+
+    /// Runs data access cycle `step` (starting from 1) at the effective address.
+    /// Returns true on the last cycle of the instruction.
+    pub fn data_cycle(&mut self, step: u8) -> bool {
+        match (self.opcode.instruction.access(), step) {
+            (Access::Read, _) => {
+                let value = self.read_8(self.address);
+                self.read_operation(value);
+                true
+            },
+            (Access::Write, _) => {
+                let (address, value) = self.write_operation();
+                self.write_8(address, value);
+                true
+            },
+            (Access::ReadModifyWrite, 1) => {
+                self.data = self.read_8(self.address);
+                false
+            },
+            (Access::ReadModifyWrite, 2) => {
+                self.write_8(self.address, self.data); // Dummy write
+                self.data = self.modify(self.data);
+                false
+            },
+            (Access::ReadModifyWrite, _) => {
+                self.write_8(self.address, self.data);
+                self.read_modify_write_operation(self.data);
+                true
+            },
+        }
     }
 
-    pub fn dex(&mut self, _address: u16) {
-        self.x_index = self.x_index.wrapping_sub(1);
-        self.status.zero = self.x_index == 0;
-        self.status.negative = self.x_index & 0x80 == 0x80;
+    fn read_operation(&mut self, value: u8) {
+        use Instruction::*;
+        match self.opcode.instruction {
+            ADC => self.add_with_carry(value),
+            AND => self.and(value),
+            BIT => self.bit(value),
+            CMP => self.compare(self.accumulator, value),
+            CPX => self.compare(self.x_index, value),
+            CPY => self.compare(self.y_index, value),
+            EOR => self.eor(value),
+            LDA => {
+                self.accumulator = value;
+                self.set_zero_negative(value);
+            },
+            LDX => {
+                self.x_index = value;
+                self.set_zero_negative(value);
+            },
+            LDY => {
+                self.y_index = value;
+                self.set_zero_negative(value);
+            },
+            NOP => (),
+            ORA => self.ora(value),
+            SBC => self.subtract_with_carry(value),
+            ALR => self.alr(value),
+            ANC => self.anc(value),
+            ANE => self.ane(value),
+            ARR => self.arr(value),
+            LAS => self.las(value),
+            LAX => self.lax(value),
+            LXA => self.lxa(value),
+            SBX => self.sbx(value),
+            _ => unreachable!("{:?} is not a read instruction", self.opcode.instruction),
+        }
     }
 
-    pub fn dey(&mut self, _address: u16) {
-        self.y_index = self.y_index.wrapping_sub(1);
-        self.status.zero = self.y_index == 0;
-        self.status.negative = self.y_index & 0x80 == 0x80;
+    /// Returns the address and value to write.
+    fn write_operation(&mut self) -> (u16, u8) {
+        use Instruction::*;
+        match self.opcode.instruction {
+            STA => (self.address, self.accumulator),
+            STX => (self.address, self.x_index),
+            STY => (self.address, self.y_index),
+            SAX => (self.address, self.accumulator & self.x_index),
+            SHA => self.and_high_byte(self.accumulator & self.x_index),
+            SHX => self.and_high_byte(self.x_index),
+            SHY => self.and_high_byte(self.y_index),
+            TAS => {
+                self.stack_pointer = self.accumulator & self.x_index;
+                self.and_high_byte(self.stack_pointer)
+            },
+            _ => unreachable!("{:?} is not a write instruction", self.opcode.instruction),
+        }
     }
 
-    pub fn eor(&mut self, address: u16) {
-        self.accumulator ^= self.read_8(address);
-        self.status.zero = self.accumulator == 0;
-        self.status.negative = self.accumulator & 0x80 == 0x80;
-        if self.page_crossed { self.skip_cycles += 1; }
+    /// Shifts, increments and decrements. Returns the modified value.
+    fn modify(&mut self, value: u8) -> u8 {
+        use Instruction::*;
+        let result = match self.opcode.instruction {
+            ASL | SLO => {
+                self.status.carry = value & 0x80 == 0x80;
+                value << 1
+            },
+            LSR | SRE => {
+                self.status.carry = value & 0x01 == 0x01;
+                value >> 1
+            },
+            ROL | RLA => {
+                let result = (value << 1) | self.status.carry as u8;
+                self.status.carry = value & 0x80 == 0x80;
+                result
+            },
+            ROR | RRA => {
+                let result = (value >> 1) | ((self.status.carry as u8) << 7);
+                self.status.carry = value & 0x01 == 0x01;
+                result
+            },
+            INC | ISB => value.wrapping_add(1),
+            DEC | DCP => value.wrapping_sub(1),
+            _ => unreachable!("{:?} is not a read-modify-write instruction", self.opcode.instruction),
+        };
+        self.set_zero_negative(result);
+        result
+    }
+
+    /// Second operation of the unofficial read-modify-write instructions, done with the modified value.
+    fn read_modify_write_operation(&mut self, value: u8) {
+        use Instruction::*;
+        match self.opcode.instruction {
+            SLO => self.ora(value),
+            RLA => self.and(value),
+            SRE => self.eor(value),
+            RRA => self.add_with_carry(value),
+            DCP => self.compare(self.accumulator, value),
+            ISB => self.subtract_with_carry(value),
+            _ => (),
+        }
     }
 
-    pub fn inc(&mut self, address: u16) {
-        let value = self.read_8(address).wrapping_add(1);
+    fn set_zero_negative(&mut self, value: u8) {
         self.status.zero = value == 0;
         self.status.negative = value & 0x80 == 0x80;
-        self.write_8(address, value);
     }
 
-    pub fn inx(&mut self, _address: u16) {
-        self.x_index = self.x_index.wrapping_add(1);
-        self.status.zero = self.x_index == 0;
-        self.status.negative = self.x_index & 0x80 == 0x80;
-    }
-
-    pub fn iny(&mut self, _address: u16) {
-        self.y_index = self.y_index.wrapping_add(1);
-        self.status.zero = self.y_index == 0;
-        self.status.negative = self.y_index & 0x80 == 0x80;
-    }
-
-    pub fn jmp(&mut self, address: u16) {
-        self.program_counter = address;
-    }
-
-    pub fn jsr(&mut self, address: u16) {
-        self.stack_push_16(self.program_counter - 1);
-        self.program_counter = address;
-    }
-
-    pub fn lda(&mut self, address: u16) {
-        self.accumulator = self.read_8(address);
-        self.status.zero = self.accumulator == 0;
-        self.status.negative = self.accumulator & 0x80 == 0x80;
-        if self.page_crossed { self.skip_cycles += 1; }
-    }
-
-    pub fn ldx(&mut self, address: u16) {
-        self.x_index = self.read_8(address);
-        self.status.zero = self.x_index == 0;
-        self.status.negative = self.x_index & 0x80 == 0x80;
-        if self.page_crossed { self.skip_cycles += 1; }
-    }
-
-    pub fn ldy(&mut self, address: u16) {
-        self.y_index = self.read_8(address);
-        self.status.zero = self.y_index == 0;
-        self.status.negative = self.y_index & 0x80 == 0x80;
-        if self.page_crossed { self.skip_cycles += 1; }
-    }
-
-    pub fn lsr(&mut self, address: u16) {
-        let value = self.read_8(address);
-        let value_shifted_right = value >> 1;
-        self.status.zero = value_shifted_right == 0;
-        self.status.carry = value & 0x01 == 0x01;
-        self.status.negative = false;
-        self.write_8(address, value_shifted_right);
-    }
-
-    pub fn lsr_acc(&mut self, _address: u16) {
-        let value = self.accumulator;
-        let value_shifted_right = value >> 1;
-        self.status.zero = value_shifted_right == 0;
-        self.status.carry = value & 0x01 == 0x01;
-        self.status.negative = false;
-        self.accumulator = value_shifted_right;
-    }
-
-    pub fn nop(&mut self, _address: u16) {}
-
-    pub fn ora(&mut self, address: u16) {
-        self.accumulator |= self.read_8(address);
-        self.status.zero = self.accumulator == 0;
-        self.status.negative = self.accumulator & 0x80 == 0x80;
-        if self.page_crossed { self.skip_cycles += 1; }
-    }
-
-    pub fn pha(&mut self, _address: u16) {
-        self.stack_push_8(self.accumulator);
-    }
-
-    pub fn php(&mut self, _address: u16) {
-        let mut status: u8 = self.status.negative as u8;
-        status = (status << 1) | self.status.overflow as u8;
-        status = (status << 1) | 1; // http://wiki.nesdev.com/w/index.php/Status_flags#The_B_flag
-        status = (status << 1) | 1; // http://wiki.nesdev.com/w/index.php/Status_flags#The_B_flag
-        status = (status << 1) | self.status.decimal as u8;
-        status = (status << 1) | self.status.interrupt as u8;
-        status = (status << 1) | self.status.zero as u8;
-        status = (status << 1) | self.status.carry as u8;
-        self.stack_push_8(status);
-    }
-
-    pub fn pla(&mut self, _address: u16) {
-        self.accumulator = self.stack_pop_8();
-        self.status.zero = self.accumulator == 0;
-        self.status.negative = self.accumulator & 0x80 == 0x80;
-    }
-
-    pub fn plp(&mut self, _address: u16) {
-        let status = self.stack_pop_8();
-        self.status.carry = status & 0x01 == 0x01;
-        self.status.zero = (status >> 1) & 0x01 == 0x01;
-        self.status.interrupt = (status >> 2) & 0x01 == 0x01;
-        self.status.decimal = (status >> 3) & 0x01 == 0x01;
-        // Bits 5 and 4 are ignored. http://wiki.nesdev.com/w/index.php/Status_flags#The_B_flag
-        self.status.overflow = (status >> 6) & 0x01 == 0x01;
-        self.status.negative = (status >> 7) & 0x01 == 0x01;
-    }
-
-    pub fn rol(&mut self, address: u16) {
-        let old_carry = self.status.carry as u8;
-        let value = self.read_8(address);
-        let new_carry = value >> 7 == 1;
-        let new_value = (value << 1) | old_carry;
-        self.status.carry = new_carry;
-        self.status.negative = new_value & 0x80 == 0x80;
-        self.write_8(address, new_value);
-    }
-
-    pub fn rol_acc(&mut self, _address: u16) {
-        let old_carry = self.status.carry as u8;
-        let value = self.accumulator;
-        let new_carry = value >> 7 == 1;
-        let new_value = (value << 1) | old_carry;
-        self.status.carry = new_carry;
-        self.status.negative = new_value & 0x80 == 0x80;
-        self.accumulator = new_value;
-    }
-
-    pub fn ror(&mut self, address: u16) {
-        let old_carry = self.status.carry as u8;
-        let value = self.read_8(address);
-        let new_carry = value & 0x01 == 1;
-        let new_value = (value >> 1) | (old_carry << 7);
-        self.status.carry = new_carry;
-        self.status.negative = new_value & 0x80 == 0x80;
-        self.write_8(address, new_value);
-    }
-
-    pub fn ror_acc(&mut self, _address: u16) {
-        let old_carry = self.status.carry as u8;
-        let value = self.accumulator;
-        let new_carry = value & 0x01 == 1;
-        let new_value = (value >> 1) | (old_carry << 7);
-        self.status.carry = new_carry;
-        self.status.negative = new_value & 0x80 == 0x80;
-        self.accumulator = new_value;
-    }
-
-    pub fn rti(&mut self, address: u16) {
-        self.plp(address);
-        self.program_counter = self.stack_pop_16();
-    }
+    fn add_with_carry(&mut self, value: u8) {
+        let values_added = value as u16 + self.accumulator as u16 + self.status.carry as u16;
 
-    pub fn rts(&mut self, _address: u16) {
-        self.program_counter = self.stack_pop_16() + 1;
+        self.status.carry = values_added > 0xFF;
+        self.status.zero = values_added & 0xFF == 0;
+        self.status.negative = values_added & 0x80 == 0x80;
+        self.status.overflow = value & 0x80 == self.accumulator & 0x80 && value as u16 & 0x80 != values_added & 0x80;
+        self.accumulator = (values_added & 0xFF) as u8;
     }
 
-    pub fn sbc(&mut self, address: u16) {
-        let value = self.read_8(address) as u16;
+    fn subtract_with_carry(&mut self, value: u8) {
+        let value = value as u16;
         let carry = self.status.carry as u16;
 
         let result = (self.accumulator as u16).wrapping_sub(value).wrapping_sub(1 - carry);
@@ -368,64 +504,118 @@ impl Cpu {
             value & 0x80 != self.accumulator as u16 & 0x80 &&
             value & 0x80 == result & 0x80;
         self.accumulator = (result & 0xFF) as u8;
-        if self.page_crossed { self.skip_cycles += 1; }
     }
 
-    pub fn sec(&mut self, _address: u16) {
-        self.status.carry = true;
+    fn compare(&mut self, register: u8, value: u8) {
+        let value = (register as u16).wrapping_sub(value as u16);
+        self.status.carry = value < 0x100;
+        self.status.zero = value == 0;
+        self.status.negative = value & 0x80 == 0x80;
+    }
+
+    fn and(&mut self, value: u8) {
+        self.accumulator &= value;
+        self.set_zero_negative(self.accumulator);
+    }
+
+    fn bit(&mut self, value: u8) {
+        self.status.zero = value & self.accumulator == 0;
+        self.status.negative = value & 0x80 == 0x80;
+        self.status.overflow = value & 0x40 == 0x40;
     }
 
-    pub fn sed(&mut self, _address: u16) {
-        self.status.decimal = true;
+    fn eor(&mut self, value: u8) {
+        self.accumulator ^= value;
+        self.set_zero_negative(self.accumulator);
     }
 
-    pub fn sei(&mut self, _address: u16) {
-        self.status.interrupt = true;
+    fn ora(&mut self, value: u8) {
+        self.accumulator |= value;
+        self.set_zero_negative(self.accumulator);
     }
 
-    pub fn sta(&mut self, address: u16) {
-        self.write_8(address, self.accumulator);
+    // Unofficial instructions
+    //
+    // Useful links:
+    // [Nesdev wiki - CPU unofficial opcodes]
+    // [NMOS 6510 Unintended Opcodes]
+    //
+    // [Nesdev wiki - CPU unofficial opcodes]: https://www.nesdev.org/wiki/CPU_unofficial_opcodes
+    // [NMOS 6510 Unintended Opcodes]: https://csdb.dk/release/?id=198357
+
+    /// Magic constant used by the unstable ANE and LXA instructions. Varies between chips.
+    const UNSTABLE_MAGIC: u8 = 0xEE;
+
+    /// AND + LSR
+    fn alr(&mut self, value: u8) {
+        self.accumulator &= value;
+        self.status.carry = self.accumulator & 0x01 == 0x01;
+        self.accumulator >>= 1;
+        self.set_zero_negative(self.accumulator);
     }
 
-    pub fn stx(&mut self, address: u16) {
-        self.write_8(address, self.x_index);
+    /// AND, with carry set to bit 7 of the result
+    fn anc(&mut self, value: u8) {
+        self.and(value);
+        self.status.carry = self.status.negative;
     }
 
-    pub fn sty(&mut self, address: u16) {
-        self.write_8(address, self.y_index);
+    /// Unstable: A = (A | magic) & X & immediate
+    fn ane(&mut self, value: u8) {
+        self.accumulator = (self.accumulator | Self::UNSTABLE_MAGIC) & self.x_index & value;
+        self.set_zero_negative(self.accumulator);
     }
 
-    pub fn tax(&mut self, _address: u16) {
-        self.x_index = self.accumulator;
-        self.status.zero = self.x_index == 0;
-        self.status.negative = self.x_index & 0x80 == 0x80;
+    /// AND + ROR, with carry and overflow taken from bits 6 and 5 of the result
+    fn arr(&mut self, value: u8) {
+        let value = self.accumulator & value;
+        self.accumulator = (value >> 1) | ((self.status.carry as u8) << 7);
+        self.set_zero_negative(self.accumulator);
+        self.status.carry = self.accumulator & 0x40 == 0x40;
+        self.status.overflow = ((self.accumulator >> 6) ^ (self.accumulator >> 5)) & 0x01 == 0x01;
     }
 
-    pub fn tay(&mut self, _address: u16) {
-        self.y_index = self.accumulator;
-        self.status.zero = self.y_index == 0;
-        self.status.negative = self.y_index & 0x80 == 0x80;
+    /// A, X and S = memory & S
+    fn las(&mut self, value: u8) {
+        let value = value & self.stack_pointer;
+        self.accumulator = value;
+        self.x_index = value;
+        self.stack_pointer = value;
+        self.set_zero_negative(value);
     }
 
-    pub fn tsx(&mut self, _address: u16) {
-        self.x_index = self.stack_pointer;
-        self.status.zero = self.x_index == 0;
-        self.status.negative = self.x_index & 0x80 == 0x80;
+    /// LDA + LDX
+    fn lax(&mut self, value: u8) {
+        self.accumulator = value;
+        self.x_index = value;
+        self.set_zero_negative(value);
     }
 
-    pub fn txa(&mut self, _address: u16) {
-        self.accumulator = self.x_index;
-        self.status.zero = self.accumulator == 0;
-        self.status.negative = self.accumulator & 0x80 == 0x80;
+    /// Unstable: A and X = (A | magic) & immediate
+    fn lxa(&mut self, value: u8) {
+        let value = (self.accumulator | Self::UNSTABLE_MAGIC) & value;
+        self.accumulator = value;
+        self.x_index = value;
+        self.set_zero_negative(value);
     }
 
-    pub fn txs(&mut self, _address: u16) {
-        self.stack_pointer = self.x_index;
+    /// X = (A & X) - immediate, setting flags like CMP
+    fn sbx(&mut self, value: u8) {
+        let register = self.accumulator & self.x_index;
+        self.compare(register, value);
+        self.x_index = register.wrapping_sub(value);
     }
 
-    pub fn tya(&mut self, _address: u16) {
-        self.accumulator = self.y_index;
-        self.status.zero = self.accumulator == 0;
-        self.status.negative = self.accumulator & 0x80 == 0x80;
+    /// Unstable SHA, SHX, SHY and TAS store `value & (H + 1)` where H is the high byte of the base address.
+    ///
+    /// If indexing crosses a page, the stored value also replaces the high byte of the target address.
+    fn and_high_byte(&self, value: u8) -> (u16, u8) {
+        let value = value & ((self.base_address >> 8) as u8).wrapping_add(1);
+        let address = if self.page_crossed {
+            ((value as u16) << 8) | (self.address & 0x00FF)
+        } else {
+            self.address
+        };
+        (address, value)
     }
-}
\ No newline at end of file
+}
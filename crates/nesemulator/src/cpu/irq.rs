@@ -0,0 +1,86 @@
+/// A device that can pull the CPU IRQ line low.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum IrqSource {
+    Mapper,
+    FrameCounter,
+    Dmc,
+}
+
+impl IrqSource {
+    fn mask(self) -> u8 {
+        match self {
+            IrqSource::Mapper => 0b001,
+            IrqSource::FrameCounter => 0b010,
+            IrqSource::Dmc => 0b100,
+        }
+    }
+}
+
+/// The shared, level-triggered IRQ line of the CPU.
+///
+/// The line is open-collector: it is asserted as long as at least one source holds it.
+/// Each source acknowledges its own interrupt by releasing the line.
+///
+/// Useful links:
+/// [Nesdev wiki - IRQ]
+///
+/// [Nesdev wiki - IRQ]: https://www.nesdev.org/wiki/IRQ
+#[derive(Default)]
+pub struct IrqLine {
+    sources: u8,
+}
+
+impl IrqLine {
+    pub fn new() -> IrqLine {
+        IrqLine { sources: 0 }
+    }
+
+    pub fn assert(&mut self, source: IrqSource) {
+        self.sources |= source.mask();
+    }
+
+    pub fn release(&mut self, source: IrqSource) {
+        self.sources &= !source.mask();
+    }
+
+    pub fn set(&mut self, source: IrqSource, asserted: bool) {
+        if asserted {
+            self.assert(source);
+        } else {
+            self.release(source);
+        }
+    }
+
+    /// Returns true if given source is holding the line.
+    pub fn is_asserted_by(&self, source: IrqSource) -> bool {
+        self.sources & source.mask() != 0
+    }
+
+    /// Returns true if any source is holding the line.
+    pub fn is_asserted(&self) -> bool {
+        self.sources != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_irq_line_multiple_sources() -> Result<(), std::io::Error> {
+        let mut irq = IrqLine::new();
+        assert!(!irq.is_asserted());
+
+        irq.assert(IrqSource::Mapper);
+        irq.assert(IrqSource::Dmc);
+        assert!(irq.is_asserted());
+
+        // The line stays asserted until every source has released it
+        irq.release(IrqSource::Mapper);
+        assert!(irq.is_asserted());
+        assert!(irq.is_asserted_by(IrqSource::Dmc));
+        irq.set(IrqSource::Dmc, false);
+        assert!(!irq.is_asserted());
+        Ok(())
+    }
+}
@@ -1,12 +1,29 @@
 pub mod opcode;
 pub mod ram;
 pub mod bus;
+pub mod irq;
 mod instruction;
 mod address_mode;
 
 use crate::cpu::bus::Bus;
+use crate::error::{Error, Result};
+use crate::cpu::opcode::Opcode;
 
+const NMI_VECTOR: u16 = 0xFFFA;
+const RESET_VECTOR: u16 = 0xFFFC;
+const IRQ_VECTOR: u16 = 0xFFFE;
 
+/// Cycle-accurate 6502 core of the 2A03.
+///
+/// Each call to [`Cpu::step`] runs one CPU cycle and does exactly one bus access,
+/// including the dummy reads and writes of the real CPU.
+///
+/// Useful links:
+/// [Nesdev wiki - CPU]
+/// [6502_cpu.txt]
+///
+/// [Nesdev wiki - CPU]: https://www.nesdev.org/wiki/CPU
+/// [6502_cpu.txt]: https://www.nesdev.org/6502_cpu.txt
 pub struct Cpu {
     pub accumulator: u8,
     pub x_index: u8,
@@ -14,11 +31,35 @@ pub struct Cpu {
     pub status: Status,
     pub program_counter: u16,
     pub stack_pointer: u8,
-    pub skip_cycles: u8,
     pub bus: Bus,
     pub cycle: u64,
-    pub page_crossed: bool,
+    /// The instruction being executed.
+    opcode: Opcode,
+    /// Number of cycles of the current instruction that have been run. 0 means the CPU is between instructions.
+    instruction_cycle: u8,
+    /// Effective address of the current instruction.
+    address: u16,
+    /// Address before indexing or the pointer of an indirect jump.
+    base_address: u16,
+    /// Zero page pointer of indexed indirect address modes.
+    pointer: u8,
+    /// Value being modified by a read-modify-write instruction.
+    data: u8,
+    address_ready: bool,
+    /// Number of data access cycles run after the effective address was ready.
+    data_step: u8,
+    page_crossed: bool,
+    /// True when the BRK sequence was started by IRQ or NMI instead of the BRK instruction.
+    hardware_interrupt: bool,
+    interrupt_vector: u16,
+    suppress_interrupt_poll: bool,
     oamdma_cycles_left: u16,
+    dmc_dma_cycles_left: u8,
+    nmi_detected: bool,
+    nmi_pending: bool,
+    irq_pending: bool,
+    /// Address and opcode of the JAM instruction that halted the CPU. Cleared by reset.
+    jammed: Option<(u16, u8)>,
 }
 
 pub struct Status {
@@ -58,6 +99,16 @@ impl Status {
         result = (result << 1) | self.carry as u8;
         result
     }
+
+    pub fn set_from_byte(&mut self, status: u8) {
+        self.carry = status & 0x01 == 0x01;
+        self.zero = (status >> 1) & 0x01 == 0x01;
+        self.interrupt = (status >> 2) & 0x01 == 0x01;
+        self.decimal = (status >> 3) & 0x01 == 0x01;
+        // Bits 5 and 4 are ignored. http://wiki.nesdev.com/w/index.php/Status_flags#The_B_flag
+        self.overflow = (status >> 6) & 0x01 == 0x01;
+        self.negative = (status >> 7) & 0x01 == 0x01;
+    }
 }
 
 
@@ -71,16 +122,59 @@ impl Cpu {
                 status: { Status::default() },
                 program_counter: 0,
                 stack_pointer: 0xFD,
-                skip_cycles: 0,
                 bus,
                 cycle: 7, // TODO: fix cpu so that this can init as 0.
+                opcode: opcode::opcode_mapper(0xEA),
+                instruction_cycle: 0,
+                address: 0,
+                base_address: 0,
+                pointer: 0,
+                data: 0,
+                address_ready: false,
+                data_step: 0,
                 page_crossed: false,
+                hardware_interrupt: false,
+                interrupt_vector: 0,
+                suppress_interrupt_poll: false,
                 oamdma_cycles_left: 0,
+                dmc_dma_cycles_left: 0,
+                nmi_detected: false,
+                nmi_pending: false,
+                irq_pending: false,
+                jammed: None,
             };
         cpu.reset_program_counter();
         cpu
     }
 
+    /// Resets the CPU like the Reset button, abandoning the current instruction and DMA.
+    ///
+    /// This is an instant approximation of the reset sequence. The real CPU runs a 7 cycle interrupt
+    /// whose stack writes are turned into reads, while this only applies its end state at once:
+    /// the stack pointer decreases by 3 without anything being pushed,
+    /// the interrupt flag is set and execution continues from the reset vector.
+    /// The other registers and RAM keep their values.
+    ///
+    /// Useful links:
+    /// [Nesdev wiki - CPU power up state]
+    ///
+    /// [Nesdev wiki - CPU power up state]: https://www.nesdev.org/wiki/CPU_power_up_state#After_reset
+    pub fn reset(&mut self) {
+        self.instruction_cycle = 0;
+        self.hardware_interrupt = false;
+        self.suppress_interrupt_poll = false;
+        self.bus.oamdma_occurred = false;
+        self.oamdma_cycles_left = 0;
+        self.dmc_dma_cycles_left = 0;
+        self.nmi_detected = false;
+        self.nmi_pending = false;
+        self.irq_pending = false;
+        self.jammed = None;
+        self.stack_pointer = self.stack_pointer.wrapping_sub(3);
+        self.status.interrupt = true;
+        self.reset_program_counter();
+    }
+
     fn read_8(&mut self, address: u16) -> u8 {
         self.bus.read(address)
     }
@@ -101,58 +195,133 @@ impl Cpu {
         self.stack_pointer= self.stack_pointer.wrapping_sub(1);
     }
 
-    fn stack_push_16(&mut self, value: u16) {
-        self.stack_push_8((value >> 8)   as u8);
-        self.stack_push_8((value & 0xFF) as u8);
-    }
-
     fn stack_pop_8(&mut self) -> u8 {
         self.stack_pointer = self.stack_pointer.wrapping_add(1);
         self.read_8(0x0100 + self.stack_pointer as u16)
     }
 
+    #[cfg(test)]
     fn stack_pop_16(&mut self) -> u16 {
         (self.stack_pop_8() as u16) | ((self.stack_pop_8() as u16) << 8)
     }
 
     fn reset_program_counter(&mut self) {
-        self.program_counter = self.read_16(0xFFFC);
+        self.program_counter = self.read_16(RESET_VECTOR);
     }
 
     pub fn set_program_counter(&mut self, new_count: u16) {
         self.program_counter = new_count;
     }
 
-    pub fn step(&mut self) {
+    /// Calls a subroutine like JSR does, pushing the return address minus one to the stack.
+    ///
+    /// Used to run routines that the program itself does not call, such as the INIT and PLAY routines of NSF files.
+    /// A CPU halted by a JAM instruction starts running again.
+    pub(crate) fn call_subroutine(&mut self, address: u16, return_address: u16) {
+        debug_assert!(self.is_at_instruction_boundary());
+        self.jammed = None;
+        let [low, high] = return_address.wrapping_sub(1).to_le_bytes();
+        self.stack_push_8(high);
+        self.stack_push_8(low);
+        self.program_counter = address;
+    }
+
+    /// Returns true if the previous instruction has finished and the next one has not started yet.
+    pub fn is_at_instruction_boundary(&self) -> bool {
+        self.instruction_cycle == 0
+    }
+
+    /// Runs one CPU cycle.
+    ///
+    /// Returns an error when the CPU fetches an opcode that halts it.
+    /// The CPU stays halted until reset, so stepping further returns the same error again.
+    pub fn step(&mut self) -> Result<()> {
         self.cycle +=1;
+        self.bus.clock_cartridge();
+        self.bus.clock_apu();
+        if let Some((pc, opcode)) = self.jammed {
+            return Err(Error::InvalidOpcode { pc, opcode });
+        }
         if self.is_interrupted_by_nmi() {
-            self.handle_nmi();
+            self.nmi_detected = true;
         }
-        
-        if self.skip_cycles > 0 {
-            self.skip_cycles -= 1;
-            return;
+
+        // DMC DMA halts the CPU for 4 cycles, or 2 cycles when it happens during OAM DMA
+        if self.dmc_dma_cycles_left > 0 {
+            self.dmc_dma_cycles_left -= 1;
+            return Ok(());
+        }
+        if let Some(address) = self.bus.dmc_dma_request() {
+            let value = self.read_8(address);
+            self.bus.complete_dmc_dma(value);
+            self.dmc_dma_cycles_left = if self.oamdma_cycles_left > 0 { 1 } else { 3 };
+            return Ok(());
         }
 
-        if self.bus.oamdma_occurred {
-            self.bus.oamdma_occurred = false;
-            self.oamdma_cycles_left = 513 + ((self.cycle & 1) as u16); // TODO timing of 1st (and 2nd idle cycle)
+        if self.instruction_cycle == 0 {
+            if self.bus.oamdma_occurred {
+                self.bus.oamdma_occurred = false;
+                self.oamdma_cycles_left = 513 + ((self.cycle & 1) as u16); // TODO timing of 1st (and 2nd idle cycle)
+            }
+
+            if self.oamdma_cycles_left >= 513 {
+                self.oamdma_cycles_left -= 1;
+                return Ok(());
+            }
+            else if self.oamdma_cycles_left > 0 {
+                if (1..=512).contains(&self.oamdma_cycles_left) && self.oamdma_cycles_left % 2 == 1 {
+                    let address = self.bus.oamdma_high_byte | ((0x200 - self.oamdma_cycles_left) >> 1);
+                    let value = self.read_8(address);
+                    if let Some(ppu) = self.bus.ppu.as_mut() {
+                        ppu.write_oamdma(value);
+                    }
+                }
+                self.oamdma_cycles_left -= 1;
+                return Ok(());
+            }
+
+            self.start_instruction()?;
+        } else {
+            self.instruction_cycle += 1;
+            if self.execute_cycle(self.instruction_cycle) {
+                debug_assert!(
+                    (self.opcode.cycles..=self.opcode.cycles + 2).contains(&self.instruction_cycle),
+                    "{:?} took {} cycles", self.opcode.instruction, self.instruction_cycle
+                );
+                self.instruction_cycle = 0;
+                return Ok(());
+            }
         }
+        self.end_cycle();
+        Ok(())
+    }
 
-        if self.oamdma_cycles_left >= 513 {
-            self.oamdma_cycles_left -= 1;
+    /// Handles the interrupt logic that happens at the end of a cycle that is not the last cycle of an instruction.
+    ///
+    /// Interrupts are polled during the second-to-last cycle of an instruction.
+    /// Polling on every cycle and using the result of the latest poll gives the same result.
+    /// If an interrupt was pending at that point, the interrupt sequence runs instead of the next instruction.
+    ///
+    /// The interrupt sequence itself does not poll for interrupts.
+    /// Instead, an NMI that occurs before the vector is fetched hijacks the sequence of BRK or IRQ.
+    ///
+    /// Useful links:
+    /// [Nesdev wiki - CPU interrupts]
+    ///
+    /// [Nesdev wiki - CPU interrupts]: https://www.nesdev.org/wiki/CPU_interrupts
+    fn end_cycle(&mut self) {
+        if self.suppress_interrupt_poll {
+            self.suppress_interrupt_poll = false;
             return;
         }
-        else if self.oamdma_cycles_left > 0 {
-            if (1..=512).contains(&self.oamdma_cycles_left) && self.oamdma_cycles_left % 2 == 1 {
-                let address = self.bus.oamdma_high_byte | ((0x200 - self.oamdma_cycles_left) >> 1);
-                let value = self.read_8(address);
-                self.bus.ppu.as_mut().unwrap().write_oamdma(value);
-            }
-            self.oamdma_cycles_left -= 1;
-            return;
+        if self.opcode.instruction != instruction::Instruction::BRK {
+            self.poll_interrupts();
         }
-        self.execute_next_opcode();
+    }
+
+    fn poll_interrupts(&mut self) {
+        self.nmi_pending = self.nmi_detected;
+        self.irq_pending = !self.status.interrupt && self.bus.irq.is_asserted();
     }
 
     pub fn is_interrupted_by_nmi(&mut self) -> bool {
@@ -162,116 +331,208 @@ impl Cpu {
         }
     }
 
-    pub fn handle_nmi(&mut self) {
-        self.stack_push_16(self.program_counter); // TODO: Check if stack pushes are in correct order
-        self.stack_push_8(self.status.get_as_byte());
-        let nmi_address = self.read_16(0xFFFA);
-        self.set_program_counter(nmi_address);
-    }
-
     pub fn get_next_opcode(&mut self) -> u8 {
         self.read_8(self.program_counter)
     }
 
-    pub fn execute_next_opcode(&mut self) {
-        let next_opcode = self.get_next_opcode();
-        let op = opcode::opcode_mapper(next_opcode);
-        self.program_counter += 1;
-        self.skip_cycles += op.cycles - 1;
-        self.page_crossed = false; // Reset page_crossed flag
-        let address = self.execute_address_mode(&op.address_mode);
-        self.execute_instruction(&op, address);
+    /// Runs the first cycle of an instruction: the opcode fetch.
+    ///
+    /// A pending interrupt replaces the fetched opcode with BRK without incrementing the program counter.
+    fn start_instruction(&mut self) -> Result<()> {
+        let pc = self.program_counter;
+        let opcode = if self.nmi_pending || self.irq_pending {
+            self.nmi_pending = false;
+            self.irq_pending = false;
+            self.read_8(self.program_counter); // Dummy read
+            self.hardware_interrupt = true;
+            0x00
+        } else {
+            self.fetch_operand()
+        };
+        self.opcode = opcode::opcode_mapper(opcode);
+        self.instruction_cycle = 1;
+        self.address_ready = false;
+        self.data_step = 0;
+        self.page_crossed = false;
+
+        if self.opcode.instruction == instruction::Instruction::JAM {
+            self.jammed = Some((pc, opcode));
+            self.instruction_cycle = 0;
+            return Err(Error::InvalidOpcode { pc, opcode });
+        }
+        Ok(())
+    }
+
+    /// Runs cycle `t` (2 or greater) of the current instruction. Returns true on the last cycle of the instruction.
+    fn execute_cycle(&mut self, t: u8) -> bool {
+        use instruction::Instruction::*;
+        use address_mode::AddressMode::*;
+        match (self.opcode.instruction, self.opcode.address_mode) {
+            (BRK, _)       => self.interrupt_cycle(t),
+            (JMP, Abs)     => self.jmp_cycle(t),
+            (JMP, _)       => self.jmp_indirect_cycle(t),
+            (JSR, _)       => self.jsr_cycle(t),
+            (RTS, _)       => self.rts_cycle(t),
+            (RTI, _)       => self.rti_cycle(t),
+            (PHA | PHP, _) => self.push_cycle(t),
+            (PLA | PLP, _) => self.pull_cycle(t),
+            (JAM, _)       => unreachable!("JAM halts the CPU on its first cycle"),
+            (_, Rel)       => self.branch_cycle(t),
+            (_, Imp | Acc) => self.implied_cycle(),
+            (_, Imm)       => {
+                self.address = self.program_counter;
+                self.program_counter = self.program_counter.wrapping_add(1);
+                self.data_cycle(1)
+            },
+            (instruction, address_mode) => {
+                if !self.address_ready {
+                    self.address_ready = self.address_mode_cycle(address_mode, instruction.access(), t);
+                    return false;
+                }
+                self.data_step += 1;
+                self.data_cycle(self.data_step)
+            },
+        }
     }
 
-    fn crossing_page(&mut self, address_1: u16, address_2: u16) -> bool {
+    fn crossing_page(&self, address_1: u16, address_2: u16) -> bool {
         address_1 & 0xFF00 != address_2 & 0xFF00
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::result::Result;
+    use crate::cartridge::{Cartridge, NametableMirroring};
+    use crate::cpu::irq::IrqSource;
+    use crate::cpu::ram::Ram;
+    use crate::mapper::{Memory, Nrom};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// Creates a CPU running given program from 0x8000.
+    /// NMI handler is at 0x9000 and IRQ handler at 0xA000.
+    fn new_cpu(program: &[u8]) -> Cpu {
+        let mut prg_rom = vec![0xEA; 0x8000]; // NOP
+        prg_rom[..program.len()].copy_from_slice(program);
+        prg_rom[0x7FFA..].copy_from_slice(&[0x00, 0x90, 0x00, 0x80, 0x00, 0xA0]);
+        let mapper = Nrom::new(Memory::new(prg_rom, Vec::new()), NametableMirroring::Horizontal);
+        let cartridge = Rc::new(RefCell::new(Cartridge::new_with_mapper(Box::new(mapper))));
+        Cpu::new(Bus::new(Ram::default(), cartridge))
+    }
 
-    fn execute_address_mode(&mut self, address_mode: &address_mode::AddressMode) -> u16 {
-        use address_mode::AddressMode::*;
-        match address_mode {
-            Abs  => self.abs(),
-            AbsX => self.abs_x(),
-            AbsY => self.abs_y(),
-            Ind  => self.ind(),
-            IndX => self.ind_x(),
-            IndY => self.ind_y(),
-            Zpg  => self.zpg(),
-            ZpgX => self.zpg_x(),
-            ZpgY => self.zpg_y(),
-            Imp  => self.imp(),
-            Rel  => self.rel(),
-            Acc  => self.acc(),
-            Imm  => self.imm(),
-            Invalid => panic!("Unsupported address mode.")
+    fn step_instruction(cpu: &mut Cpu) {
+        cpu.step().unwrap();
+        while !cpu.is_at_instruction_boundary() {
+            cpu.step().unwrap();
         }
     }
 
-    fn execute_instruction(&mut self, opcode: &opcode::Opcode, address: u16) {
-        use instruction::Instruction::*;
-        use address_mode::AddressMode::Acc;
-        match (&opcode.instruction, &opcode.address_mode) {
-            (ADC, _)     => self.adc(address),
-            (AND, _)     => self.and(address),
-            (ASL, Acc)   => self.asl_acc(address),
-            (ASL, _)     => self.asl(address),
-            (BCC, _)     => self.bcc(address),
-            (BCS, _)     => self.bcs(address),
-            (BEQ, _)     => self.beq(address),
-            (BIT, _)     => self.bit(address),
-            (BMI, _)     => self.bmi(address),
-            (BNE, _)     => self.bne(address),
-            (BPL, _)     => self.bpl(address),
-            (BRK, _)     => self.brk(address),
-            (BVC, _)     => self.bvc(address),
-            (BVS, _)     => self.bvs(address),
-            (CLC, _)     => self.clc(address),
-            (CLD, _)     => self.cld(address),
-            (CLI, _)     => self.cli(address),
-            (CLV, _)     => self.clv(address),
-            (CMP, _)     => self.cmp(address),
-            (CPX, _)     => self.cpx(address),
-            (CPY, _)     => self.cpy(address),
-            (DEC, _)     => self.dec(address),
-            (DEX, _)     => self.dex(address),
-            (DEY, _)     => self.dey(address),
-            (EOR, _)     => self.eor(address),
-            (INC, _)     => self.inc(address),
-            (INX, _)     => self.inx(address),
-            (INY, _)     => self.iny(address),
-            (JMP, _)     => self.jmp(address),
-            (JSR, _)     => self.jsr(address),
-            (LDA, _)     => self.lda(address),
-            (LDX, _)     => self.ldx(address),
-            (LDY, _)     => self.ldy(address),
-            (LSR, Acc)   => self.lsr_acc(address),
-            (LSR, _)     => self.lsr(address),
-            (NOP, _)     => self.nop(address),
-            (ORA, _)     => self.ora(address),
-            (PHA, _)     => self.pha(address),
-            (PHP, _)     => self.php(address),
-            (PLA, _)     => self.pla(address),
-            (PLP, _)     => self.plp(address),
-            (ROL, Acc)   => self.rol_acc(address),
-            (ROL, _)     => self.rol(address),
-            (ROR, Acc)   => self.ror_acc(address),
-            (ROR, _)     => self.ror(address),
-            (RTI, _)     => self.rti(address),
-            (RTS, _)     => self.rts(address),
-            (SBC, _)     => self.sbc(address),
-            (SEC, _)     => self.sec(address),
-            (SED, _)     => self.sed(address),
-            (SEI, _)     => self.sei(address),
-            (STA, _)     => self.sta(address),
-            (STX, _)     => self.stx(address),
-            (STY, _)     => self.sty(address),
-            (TAX, _)     => self.tax(address),
-            (TAY, _)     => self.tay(address),
-            (TSX, _)     => self.tsx(address),
-            (TXA, _)     => self.txa(address),
-            (TXS, _)     => self.txs(address),
-            (TYA, _)     => self.tya(address),
-            (Invalid, _) => panic!("Unsupported opcode"),
+    fn pop_return_address(cpu: &mut Cpu) -> u16 {
+        cpu.stack_pop_8();
+        cpu.stack_pop_16()
+    }
+
+    #[test]
+    fn test_irq_masked_by_interrupt_flag() -> Result<(), std::io::Error> {
+        let mut cpu = new_cpu(&[]);
+        cpu.bus.irq.assert(IrqSource::Dmc);
+        for _ in 0..10 {
+            step_instruction(&mut cpu);
         }
+        assert_eq!(cpu.program_counter, 0x800A);
+        Ok(())
+    }
+
+    #[test]
+    fn test_irq_delayed_after_cli() -> Result<(), std::io::Error> {
+        let mut cpu = new_cpu(&[0x58, 0xEA, 0xEA]); // CLI, NOP, NOP
+        cpu.bus.irq.assert(IrqSource::Dmc);
+
+        step_instruction(&mut cpu); // CLI
+        step_instruction(&mut cpu); // NOP runs before the IRQ is taken
+        assert_eq!(cpu.program_counter, 0x8002);
+        step_instruction(&mut cpu); // IRQ sequence
+        assert_eq!(cpu.program_counter, 0xA000);
+        assert!(cpu.status.interrupt);
+        assert_eq!(pop_return_address(&mut cpu), 0x8002);
+        Ok(())
+    }
+
+    #[test]
+    fn test_irq_released_before_poll() -> Result<(), std::io::Error> {
+        let mut cpu = new_cpu(&[0x58, 0xEA, 0xEA]); // CLI, NOP, NOP
+        step_instruction(&mut cpu);
+        cpu.bus.irq.assert(IrqSource::FrameCounter);
+        cpu.bus.irq.release(IrqSource::FrameCounter);
+        step_instruction(&mut cpu);
+        step_instruction(&mut cpu);
+        assert_eq!(cpu.program_counter, 0x8003);
+        Ok(())
+    }
+
+    #[test]
+    fn test_nmi_hijacks_brk() -> Result<(), std::io::Error> {
+        let mut cpu = new_cpu(&[0x00, 0x00]); // BRK
+        cpu.step().unwrap();
+        cpu.nmi_detected = true;
+        while !cpu.is_at_instruction_boundary() {
+            cpu.step().unwrap();
+        }
+        assert_eq!(cpu.program_counter, 0x9000);
+        let status = cpu.stack_pop_8();
+        assert_eq!(status & 0x10, 0x10, "B flag should still be set");
+        assert_eq!(cpu.stack_pop_16(), 0x8002);
+        Ok(())
+    }
+
+    #[test]
+    fn test_unofficial_shy_page_crossing() -> Result<(), std::io::Error> {
+        // LDY #$05, LDX #$10, SHY $02F8,X, LDX #$01, SHY $0400,X
+        let mut cpu = new_cpu(&[0xA0, 0x05, 0xA2, 0x10, 0x9C, 0xF8, 0x02, 0xA2, 0x01, 0x9C, 0x00, 0x04]);
+        for _ in 0..3 {
+            step_instruction(&mut cpu);
+        }
+        // Page crossed: the stored value 0x05 & 0x03 also becomes the high byte of the address
+        assert_eq!(cpu.read_8(0x0308), 0x00);
+        assert_eq!(cpu.read_8(0x0108), 0x01);
+        assert_eq!(cpu.cycle, 7 + 2 + 2 + 5);
+
+        for _ in 0..2 {
+            step_instruction(&mut cpu);
+        }
+        assert_eq!(cpu.read_8(0x0401), 0x05);
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_happens_on_last_cycle() -> Result<(), std::io::Error> {
+        let mut cpu = new_cpu(&[0xA9, 0x42, 0x8D, 0x00, 0x02]); // LDA #$42, STA $0200
+        step_instruction(&mut cpu);
+        for _ in 0..3 {
+            cpu.step().unwrap();
+            assert_eq!(cpu.read_8(0x0200), 0x00);
+        }
+        cpu.step().unwrap();
+        assert_eq!(cpu.read_8(0x0200), 0x42);
+        assert!(cpu.is_at_instruction_boundary());
+        Ok(())
+    }
+
+    #[test]
+    fn test_jam_returns_invalid_opcode() -> Result<(), std::io::Error> {
+        let mut cpu = new_cpu(&[0xEA, 0x02]); // NOP, JAM
+        step_instruction(&mut cpu);
+        assert!(matches!(cpu.step(), Err(Error::InvalidOpcode { pc: 0x8001, opcode: 0x02 })));
+        for _ in 0..10 {
+            assert!(matches!(cpu.step(), Err(Error::InvalidOpcode { pc: 0x8001, opcode: 0x02 })), "CPU should stay halted");
+        }
+
+        // Only reset starts the CPU again
+        cpu.reset();
+        step_instruction(&mut cpu);
+        assert_eq!(cpu.program_counter, 0x8001);
+        Ok(())
     }
 }
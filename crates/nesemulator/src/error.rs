@@ -0,0 +1,80 @@
+use std::fmt;
+use std::io;
+
+/// Errors that can happen when loading a ROM or running the emulator.
+#[derive(Debug)]
+pub enum Error {
+    /// Reading the ROM file failed.
+    Io(io::Error),
+    /// The zip, gzip or 7z archive could not be decompressed.
+    InvalidArchive(String),
+    /// The archive does not contain a ROM file, or the requested entry.
+    RomNotFoundInArchive,
+    /// The file is not an iNES or NES 2.0 ROM: the identification string "NES<EOF>" is missing.
+    InvalidRom,
+    /// The file is shorter than the sizes given in its header.
+    TruncatedRom { expected: usize, actual: usize },
+    /// The header describes a ROM without PRG ROM.
+    MissingPrgRom,
+    /// A Famicom Disk System image was loaded without a BIOS, and no `disksys.rom` was found next to it.
+    FdsBiosNotFound,
+    /// The Famicom Disk System BIOS is not 8 KiB.
+    InvalidFdsBios { size: usize },
+    /// The Famicom Disk System image contains no complete disk side.
+    InvalidDiskImage,
+    /// The UNIF file is invalid.
+    InvalidUnif(String),
+    /// The UNIF file uses a board that is not implemented.
+    UnsupportedBoard(String),
+    /// The NSF or NSFe music file is invalid.
+    InvalidNsf(String),
+    /// The ROM uses a mapper that is not implemented.
+    UnsupportedMapper(u16),
+    /// A ROM database file has an invalid line.
+    InvalidRomDatabase(String),
+    /// The CPU executed an opcode that halts it (JAM, also known as KIL or HLT).
+    InvalidOpcode { pc: u16, opcode: u8 },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(error) => write!(f, "Failed to read ROM: {}", error),
+            Error::InvalidArchive(error) => write!(f, "Failed to decompress archive: {}", error),
+            Error::RomNotFoundInArchive => write!(f, "No ROM file found in archive"),
+            Error::InvalidRom => write!(f, "Invalid ROM: Missing identification string \"NES<EOF>\""),
+            Error::TruncatedRom { expected, actual } => write!(
+                f,
+                "Invalid ROM: Header describes {} bytes but the file has {} bytes",
+                expected, actual
+            ),
+            Error::MissingPrgRom => write!(f, "Invalid ROM: Header describes no PRG ROM"),
+            Error::FdsBiosNotFound => write!(f, "Famicom Disk System BIOS disksys.rom not found"),
+            Error::InvalidFdsBios { size } => write!(f, "Invalid Famicom Disk System BIOS: Expected 8192 bytes, found {} bytes", size),
+            Error::InvalidDiskImage => write!(f, "Invalid disk image: No complete disk side found"),
+            Error::InvalidUnif(message) => write!(f, "Invalid UNIF file: {}", message),
+            Error::UnsupportedBoard(board_name) => write!(f, "Unsupported ROM: Board {} not supported", board_name),
+            Error::InvalidNsf(message) => write!(f, "Invalid NSF file: {}", message),
+            Error::UnsupportedMapper(mapper_number) => write!(f, "Unsupported ROM: Mapper number {} not supported", mapper_number),
+            Error::InvalidRomDatabase(message) => write!(f, "Invalid ROM database: {}", message),
+            Error::InvalidOpcode { pc, opcode } => write!(f, "CPU halted by opcode {:#04X} at {:#06X}", opcode, pc),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
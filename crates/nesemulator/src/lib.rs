@@ -1,64 +1,286 @@
+mod archive;
 mod cartridge;
+mod error;
+mod mapper;
 mod controller;
 pub mod cpu;
 pub mod ppu;
+pub mod apu;
+pub mod rom_header;
+pub mod rom_database;
+pub mod nsf;
+mod unif;
 
 use crate::cartridge::Cartridge;
 use crate::controller::Controller;
 use crate::cpu::Cpu;
+use crate::nsf::NsfPlayer;
 use crate::ppu::Ppu;
+use crate::apu::Apu;
+use crate::rom_header::TimingRegion;
 
 pub use crate::controller::Button;
+pub use crate::error::{Error, Result};
+pub use crate::cartridge::{LoadOptions, NametableMirroring};
+pub use crate::nsf::NsfFile;
+pub use crate::rom_database::RomDatabaseEntry;
+pub use crate::rom_header::RomHeader;
 
+use log::warn;
 use std::cell::RefCell;
+use std::io::Read;
 use std::rc::Rc;
 
+/// Battery-backed PRG RAM is written to the save file this often if it has changed. About 5 seconds.
+const BATTERY_SAVE_INTERVAL_FRAMES: u32 = 300;
+
 pub struct Emulator {
-    _cartridge: Rc<RefCell<Cartridge>>,
+    cartridge: Rc<RefCell<Cartridge>>,
     pub cpu: Cpu,
+    frames_since_battery_save: u32,
+    /// Calls the routines of the music file when playing an NSF file.
+    nsf_player: Option<NsfPlayer>,
 }
 
 impl Emulator {
-    pub fn new(path: &str) -> Emulator {
-        // Some test code
+    /// Loads the ROM file at the given path and creates an emulator running it.
+    ///
+    /// The file may be a zip, gzip or 7z archive, in which case the first `.nes` file inside it is loaded.
+    /// Battery-backed RAM is loaded from and saved to a `.sav` file next to the ROM file.
+    pub fn new(path: &str) -> Result<Emulator> {
+        Emulator::new_with_options(path, &LoadOptions::default())
+    }
 
-        let cartridge = Rc::new(RefCell::new(Cartridge::new_from_file(path.to_owned())));
-        let ppu_bus = ppu::bus::Bus::new(cartridge.clone());
+    /// Loads the ROM file with the given name from a zip or 7z archive at the given path.
+    pub fn new_from_archive(path: &str, entry_name: &str) -> Result<Emulator> {
+        let options = LoadOptions {
+            archive_entry: Some(entry_name.to_owned()),
+            ..LoadOptions::default()
+        };
+        Emulator::new_with_options(path, &options)
+    }
 
-        let ppu = Ppu::new(ppu_bus);
+    /// Loads the ROM file at the given path using the given options. See [`Emulator::new`].
+    pub fn new_with_options(path: &str, options: &LoadOptions) -> Result<Emulator> {
+        Ok(Emulator::new_with_cartridge(Cartridge::new_from_file(path.to_owned(), options)?))
+    }
 
-        let cpu_ram = cpu::ram::Ram::new(0x0800);
-        let cpu_bus = cpu::bus::Bus::new(cpu_ram, cartridge.clone());
+    /// Creates an emulator running a ROM from memory. The data may also be a zip, gzip or 7z archive.
+    ///
+    /// Battery-backed RAM is not saved to a file.
+    pub fn from_bytes(data: &[u8]) -> Result<Emulator> {
+        Emulator::from_bytes_with_options(data, &LoadOptions::default())
+    }
 
-        let cpu = cpu::Cpu::new(cpu_bus);
+    /// Creates an emulator running a ROM from memory using the given options. See [`Emulator::from_bytes`].
+    pub fn from_bytes_with_options(data: &[u8], options: &LoadOptions) -> Result<Emulator> {
+        Ok(Emulator::new_with_cartridge(Cartridge::new_from_bytes(data, options)?))
+    }
+
+    /// Creates an emulator running a ROM read from a reader. See [`Emulator::from_bytes`].
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<Emulator> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        Emulator::from_bytes(&data)
+    }
+
+    fn new_with_cartridge(cartridge: Cartridge) -> Emulator {
+        let cartridge = Rc::new(RefCell::new(cartridge));
+        if let Err(error) = cartridge.borrow_mut().load_battery_ram() {
+            warn!("Failed to load save file: {}", error);
+        }
+        let timing = cartridge.borrow().header().timing;
+        if timing != TimingRegion::Ntsc && timing != TimingRegion::MultipleRegion {
+            warn!("ROM is made for {:?} timing, emulating NTSC", timing);
+        }
+        let nsf_player = cartridge.borrow().nsf().cloned().map(NsfPlayer::new);
+        let cpu = Emulator::power_on(&cartridge, nsf_player.is_none(), Apu::default());
 
         let mut emulator = Emulator {
-            _cartridge: cartridge,
+            cartridge,
             cpu,
+            frames_since_battery_save: 0,
+            nsf_player,
         };
-
-        emulator.cpu.bus.set_ppu(ppu);
-        emulator.cpu.bus.set_controller(Controller::new());
+        emulator.start_nsf_song();
         emulator
     }
 
-    pub fn step(&mut self) {
-        self.cpu.step();
-        let ppu = self.cpu.bus.ppu.as_mut().unwrap();
-        ppu.step();
-        ppu.step();
-        ppu.step();
+    /// Creates the CPU with fresh RAM and connects the devices to its bus.
+    ///
+    /// NSF files only need the CPU and APU, so the PPU is left out when playing them.
+    fn power_on(cartridge: &Rc<RefCell<Cartridge>>, with_ppu: bool, apu: Apu) -> Cpu {
+        let cpu_ram = cpu::ram::Ram::new(0x0800);
+        let cpu_bus = cpu::bus::Bus::new(cpu_ram, cartridge.clone());
+
+        let mut cpu = cpu::Cpu::new(cpu_bus);
+        cpu.bus.set_apu(apu);
+        cpu.bus.set_controller(Controller::new());
+        if with_ppu {
+            let ppu_bus = ppu::bus::Bus::new(cartridge.clone());
+            cpu.bus.set_ppu(Ppu::new(ppu_bus));
+        }
+        cpu
+    }
+
+    /// Starts the current song again when playing an NSF file.
+    fn start_nsf_song(&mut self) {
+        if let Some(player) = self.nsf_player.as_mut() {
+            let song = player.song();
+            player.start_song(&mut self.cpu, song);
+        }
+    }
+
+    /// Presses the Reset button of the console.
+    ///
+    /// The CPU runs its reset sequence and the PPU and APU clear some of their registers.
+    /// RAM, VRAM and the state of most mappers are kept, which is how some games detect a reset.
+    /// When playing an NSF file, the current song starts again.
+    pub fn reset(&mut self) -> Result<()> {
+        if self.nsf_player.is_some() {
+            let song = self.song().unwrap_or(0);
+            return self.select_song(song);
+        }
+        self.cpu.reset();
+        if let Some(ppu) = self.cpu.bus.ppu.as_mut() {
+            ppu.reset();
+        }
+        if let Some(apu) = self.cpu.bus.apu.as_mut() {
+            apu.reset();
+        }
+        self.cartridge.borrow_mut().reset();
+        Ok(())
+    }
+
+    /// Turns the console off and on again.
+    ///
+    /// CPU RAM, VRAM, OAM, palette RAM and the cartridge RAM start over from a cleared state,
+    /// and the mapper returns to its power-up state. Battery-backed RAM keeps its contents.
+    pub fn power_cycle(&mut self) {
+        self.save_battery_ram();
+        self.cartridge.borrow_mut().power_cycle();
+        let sample_rate = self.cpu.bus.apu.as_ref().map_or(apu::DEFAULT_SAMPLE_RATE, Apu::sample_rate);
+        self.cpu = Emulator::power_on(&self.cartridge, self.nsf_player.is_none(), Apu::new(sample_rate));
+        self.start_nsf_song();
+    }
+
+    /// Runs one CPU cycle and three PPU cycles.
+    pub fn step(&mut self) -> Result<()> {
+        if let Some(player) = self.nsf_player.as_mut() {
+            player.step(&mut self.cpu);
+        }
+        let result = self.cpu.step();
+        if let Some(ppu) = self.cpu.bus.ppu.as_mut() {
+            ppu.step();
+            ppu.step();
+            ppu.step();
+        }
+        result
     }
 
-    // Run emulator steps until a frame is ready.
-    pub fn step_frame(&mut self) {
+    // Run emulator steps until a frame is ready. When playing an NSF file, a frame is one play period.
+    pub fn step_frame(&mut self) -> Result<()> {
         loop {
-            self.step();
-            let ppu = self.cpu.bus.ppu.as_ref().unwrap();
-            if ppu.y == 240 && (0..=2).contains(&ppu.x) {
+            self.step()?;
+            let frame_ready = match (self.cpu.bus.ppu.as_ref(), self.nsf_player.as_ref()) {
+                (Some(ppu), _) => ppu.y == 240 && (0..=2).contains(&ppu.x),
+                (None, Some(player)) => player.is_new_period(),
+                (None, None) => true,
+            };
+            if frame_ready {
                 break;
             }
         }
+
+        self.frames_since_battery_save += 1;
+        if self.frames_since_battery_save >= BATTERY_SAVE_INTERVAL_FRAMES {
+            self.frames_since_battery_save = 0;
+            self.save_battery_ram();
+        }
+        Ok(())
+    }
+
+    /// Returns the header of the loaded ROM.
+    pub fn rom_header(&self) -> RomHeader {
+        self.cartridge.borrow().header().clone()
+    }
+
+    /// Returns the ROM database entry that corrected the header of the loaded ROM, if any.
+    pub fn rom_database_entry(&self) -> Option<RomDatabaseEntry> {
+        self.cartridge.borrow().database_entry().cloned()
+    }
+
+    /// Returns the number of disk sides of a Famicom Disk System image, 0 for cartridges.
+    pub fn disk_side_count(&self) -> usize {
+        self.cartridge.borrow().disk_side_count()
+    }
+
+    /// Returns the inserted disk side, or None if the disk is ejected.
+    pub fn disk_side(&self) -> Option<usize> {
+        self.cartridge.borrow().disk_side()
+    }
+
+    /// Inserts a disk side. Side 0 is side A of the first disk, side 1 is side B and so on.
+    /// Sides that are not in the image are ignored.
+    ///
+    /// Games ask to eject the disk before the next side can be inserted.
+    pub fn insert_disk(&mut self, side: usize) {
+        self.cartridge.borrow_mut().set_disk_side(Some(side));
+    }
+
+    pub fn eject_disk(&mut self) {
+        self.cartridge.borrow_mut().set_disk_side(None);
+    }
+
+    /// Returns the music file if an NSF file is loaded.
+    pub fn nsf_file(&self) -> Option<NsfFile> {
+        self.nsf_player.as_ref().map(|player| player.file().clone())
+    }
+
+    /// Returns the song being played from an NSF file, counting from 0.
+    pub fn song(&self) -> Option<u8> {
+        self.nsf_player.as_ref().map(NsfPlayer::song)
+    }
+
+    /// Starts playing a song of an NSF file, counting from 0. Songs that are not in the file are ignored.
+    ///
+    /// The audio is taken with [`Emulator::take_audio_samples`] as with games.
+    pub fn select_song(&mut self, song: u8) -> Result<()> {
+        let player = match self.nsf_player.as_mut() {
+            Some(player) if song < player.file().song_count => player,
+            _ => return Ok(()),
+        };
+        while !self.cpu.is_at_instruction_boundary() {
+            self.cpu.step()?;
+        }
+        player.start_song(&mut self.cpu, song);
+        Ok(())
+    }
+
+    /// Writes battery-backed PRG RAM to a `.sav` file next to the ROM if the cartridge has a battery.
+    ///
+    /// This is done periodically while running and when the emulator is dropped.
+    pub fn save_battery_ram(&mut self) {
+        if let Err(error) = self.cartridge.borrow_mut().save_battery_ram() {
+            warn!("Failed to write save file: {}", error);
+        }
+    }
+
+    /// Sets the sample rate of the audio output. Defaults to 44100 Hz.
+    ///
+    /// Rates outside range 1 Hz..=CPU clock rate are clamped.
+    pub fn set_audio_sample_rate(&mut self, sample_rate: u32) {
+        if let Some(apu) = self.cpu.bus.apu.as_mut() {
+            apu.set_sample_rate(sample_rate);
+        }
+    }
+
+    /// Returns the audio samples produced since the last call.
+    ///
+    /// Samples are mono `f32` values at the rate set with [`Emulator::set_audio_sample_rate`].
+    /// Running one frame produces roughly `sample_rate / 60` samples.
+    pub fn take_audio_samples(&mut self) -> Vec<f32> {
+        self.cpu.bus.apu.as_mut().map_or_else(Vec::new, |apu| apu.take_samples())
     }
 
     pub fn set_controller_state(&mut self, button: Button, value: bool) {
@@ -68,24 +290,224 @@ impl Emulator {
     }
 }
 
+impl Drop for Emulator {
+    fn drop(&mut self) {
+        self.save_battery_ram();
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cartridge::nrom_test_rom;
+    use crate::rom_database::RomDatabase;
     use std::fs::File;
     use std::io::prelude::*;
     use std::io::BufReader;
     use std::result::Result;
 
     #[test]
-    #[ignore = "not yet implemented"]
     fn test_nmi_timing() -> Result<(), std::io::Error> {
-        unimplemented!();
+        let mut prg = vec![0xEA; 0x103];
+        prg[0x00..0x08].copy_from_slice(&[0xA9, 0x80, 0x8D, 0x00, 0x20, 0x4C, 0x05, 0xC0]); // LDA #$80, STA $2000, JMP $C005
+        prg[0x100..0x103].copy_from_slice(&[0xE6, 0x00, 0x40]); // NMI: INC $00, RTI
+        let rom = nrom_test_rom(&prg, [0xC100, 0xC000, 0xEAEA]);
+        let mut emulator = Emulator::from_bytes(&rom).unwrap();
+
+        // Frames end before vertical blank, so the NMI of a frame is counted after the next one
+        for frame in 0..4 {
+            emulator.step_frame().unwrap();
+            assert_eq!(emulator.cpu.bus.read(0x0000), frame);
+        }
+
+        // Reading PPUSTATUS just as the vblank flag is set suppresses the NMI
+        let ppu_position = |emulator: &Emulator| {
+            let ppu = emulator.cpu.bus.ppu.as_ref().unwrap();
+            (ppu.y, ppu.x)
+        };
+        while !(ppu_position(&emulator).0 == 241 && (1..=3).contains(&ppu_position(&emulator).1)) {
+            emulator.step().unwrap();
+        }
+        let status = emulator.cpu.bus.read(0x2002);
+        assert_eq!(status & 0x80 != 0, ppu_position(&emulator).1 != 1);
+        emulator.step_frame().unwrap();
+        assert_eq!(emulator.cpu.bus.read(0x0000), 3);
+
+        // Enabling NMI during vertical blank causes an NMI
+        while ppu_position(&emulator).0 != 250 {
+            emulator.step().unwrap();
+        }
+        emulator.cpu.bus.write(0x2000, 0x00);
+        emulator.step().unwrap();
+        assert_eq!(emulator.cpu.bus.read(0x0000), 4);
+        emulator.cpu.bus.write(0x2000, 0x80);
+        for _ in 0..20 {
+            emulator.step().unwrap();
+        }
+        assert_eq!(emulator.cpu.bus.read(0x0000), 5);
+        Ok(())
+    }
+
+    /// Runs one of blargg's vbl_nmi_timing test ROMs and checks its result code.
+    ///
+    /// The ROMs print the result on screen and store the result code at $F8, 1 meaning passed.
+    fn run_vbl_nmi_timing_rom(name: &str) -> Result<(), std::io::Error> {
+        let rom_path = format!("tests/nes-test-roms/vbl_nmi_timing/{}.nes", name);
+        let mut emulator = Emulator::new(&rom_path).unwrap();
+        for _ in 0..10 * 60 {
+            emulator.step_frame().unwrap();
+        }
+        let result = emulator.cpu.bus.read(0x00F8);
+        assert_eq!(result, 1, "{} failed with result code {}", name, result);
+        Ok(())
+    }
+
+    #[test]
+    #[ignore = "needs nes-test-roms/vbl_nmi_timing"]
+    fn test_vbl_nmi_timing_frame_basics() -> Result<(), std::io::Error> {
+        run_vbl_nmi_timing_rom("1.frame_basics")
+    }
+
+    #[test]
+    #[ignore = "needs nes-test-roms/vbl_nmi_timing"]
+    fn test_vbl_nmi_timing_vbl_timing() -> Result<(), std::io::Error> {
+        run_vbl_nmi_timing_rom("2.vbl_timing")
+    }
+
+    #[test]
+    #[ignore = "needs nes-test-roms/vbl_nmi_timing"]
+    fn test_vbl_nmi_timing_even_odd_frames() -> Result<(), std::io::Error> {
+        run_vbl_nmi_timing_rom("3.even_odd_frames")
+    }
+
+    #[test]
+    #[ignore = "needs nes-test-roms/vbl_nmi_timing"]
+    fn test_vbl_nmi_timing_vbl_clear_timing() -> Result<(), std::io::Error> {
+        run_vbl_nmi_timing_rom("4.vbl_clear_timing")
+    }
+
+    #[test]
+    #[ignore = "needs nes-test-roms/vbl_nmi_timing"]
+    fn test_vbl_nmi_timing_nmi_suppression() -> Result<(), std::io::Error> {
+        run_vbl_nmi_timing_rom("5.nmi_suppression")
+    }
+
+    #[test]
+    #[ignore = "needs nes-test-roms/vbl_nmi_timing"]
+    fn test_vbl_nmi_timing_nmi_disable() -> Result<(), std::io::Error> {
+        run_vbl_nmi_timing_rom("6.nmi_disable")
+    }
+
+    #[test]
+    #[ignore = "needs nes-test-roms/vbl_nmi_timing"]
+    fn test_vbl_nmi_timing_nmi_timing() -> Result<(), std::io::Error> {
+        run_vbl_nmi_timing_rom("7.nmi_timing")
+    }
+
+    #[test]
+    fn test_rom_database_corrects_header() -> Result<(), std::io::Error> {
+        let mut rom = std::fs::read("tests/nes-test-roms/other/nestest.nes")?;
+        rom[6] = 0x41; // Mapper 4, vertical mirroring
+        let database = RomDatabase::parse("crc32=348611F5 mapper=0 mirroring=h battery=0 region=ntsc # nestest").unwrap();
+        let options = LoadOptions {
+            rom_database: Some(std::sync::Arc::new(database)),
+            ..LoadOptions::default()
+        };
+        let emulator = Emulator::from_bytes_with_options(&rom, &options).unwrap();
+        let header = emulator.rom_header();
+        assert_eq!((header.mapper_number, header.mirroring), (0, NametableMirroring::Horizontal));
+        assert_eq!(emulator.rom_database_entry().unwrap().crc32, Some(0x348611F5));
+
+        let options = LoadOptions {
+            use_rom_database: false,
+            ..options
+        };
+        let emulator = Emulator::from_bytes_with_options(&rom, &options).unwrap();
+        assert_eq!(emulator.rom_header().mapper_number, 4);
+        assert!(emulator.rom_database_entry().is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_from_bytes() -> Result<(), std::io::Error> {
+        let rom = nrom_test_rom(&[], [0xC000; 3]);
+        let mut emulator = Emulator::from_reader(&rom[..]).unwrap();
+        emulator.step_frame().unwrap();
+        assert!(matches!(Emulator::from_bytes(&rom[1..]), Err(Error::InvalidRom)));
+        Ok(())
     }
 
     #[test]
+    fn test_reset_and_power_cycle() -> Result<(), std::io::Error> {
+        let rom = nrom_test_rom(&[0xA9, 0x42, 0x85, 0x10, 0x58], [0xEAEA, 0xC000, 0xEAEA]); // LDA #$42, STA $10, CLI
+        let mut emulator = Emulator::from_bytes(&rom).unwrap();
+        emulator.step_frame().unwrap();
+        assert_eq!(emulator.cpu.bus.read(0x0010), 0x42);
+        assert!(!emulator.cpu.status.interrupt);
+
+        emulator.cpu.bus.write(0x2000, 0x80);
+        emulator.reset().unwrap();
+        assert_eq!(emulator.cpu.stack_pointer, 0xFA);
+        assert!(emulator.cpu.status.interrupt);
+        assert_eq!(emulator.cpu.program_counter, 0xC000);
+        assert_eq!(emulator.cpu.bus.read(0x0010), 0x42);
+        let ppu = emulator.cpu.bus.ppu.as_mut().unwrap();
+        assert_eq!(ppu.ppuctrl, 0);
+        // PPUCTRL ignores writes until the end of vertical blank
+        ppu.write_register(0x2000, 0x80);
+        assert_eq!(ppu.ppuctrl, 0);
+        emulator.step_frame().unwrap();
+        emulator.cpu.bus.write(0x2000, 0x80);
+        assert_eq!(emulator.cpu.bus.ppu.as_ref().unwrap().ppuctrl, 0x80);
+
+        emulator.cpu.bus.write(0x0010, 0x00);
+        emulator.cpu.bus.write(0x0011, 0x55);
+        emulator.power_cycle();
+        assert_eq!(emulator.cpu.stack_pointer, 0xFD);
+        assert_eq!(emulator.cpu.bus.read(0x0011), 0x00);
+        assert_eq!(emulator.cpu.bus.ppu.as_ref().unwrap().ppuctrl, 0);
+        emulator.step_frame().unwrap();
+        assert_eq!(emulator.cpu.bus.read(0x0010), 0x42);
+        Ok(())
+    }
+
+    #[test]
+    fn test_nsf_playback() -> Result<(), std::io::Error> {
+        let mut nsf = b"NESM\x1a\x01\x03\x02\x00\x80\x00\x80\x04\x80".to_vec();
+        nsf.resize(0x80, 0);
+        nsf[0x6E..0x70].copy_from_slice(&16639u16.to_le_bytes());
+        nsf.extend_from_slice(&[
+            0x85, 0x00, 0x60, 0x00, // INIT: STA $00, RTS
+            0xE6, 0x01, 0x60, // PLAY: INC $01, RTS
+        ]);
+        let mut emulator = Emulator::from_bytes(&nsf).unwrap();
+        assert!(emulator.cpu.bus.ppu.is_none());
+        assert_eq!(emulator.nsf_file().unwrap().song_count, 3);
+        assert_eq!(emulator.song(), Some(1));
+
+        // Each frame ends when PLAY is called, so the last call has not run yet
+        for _ in 0..3 {
+            emulator.step_frame().unwrap();
+        }
+        assert_eq!(emulator.cpu.bus.read(0x0000), 1);
+        assert_eq!(emulator.cpu.bus.read(0x0001), 2);
+        assert!(!emulator.take_audio_samples().is_empty());
+
+        emulator.select_song(2).unwrap();
+        emulator.select_song(3).unwrap();
+        assert_eq!(emulator.song(), Some(2));
+        emulator.step_frame().unwrap();
+        emulator.step_frame().unwrap();
+        assert_eq!(emulator.cpu.bus.read(0x0000), 2);
+        assert_eq!(emulator.cpu.bus.read(0x0001), 1);
+        Ok(())
+    }
+
+    #[test]
+    #[allow(clippy::explicit_counter_loop)]
     fn test_official_opcodes_with_nestest() -> Result<(), std::io::Error> {
         let rom_path = String::from("tests/nes-test-roms/other/nestest.nes");
-        let cartridge = Rc::new(RefCell::new(Cartridge::new_from_file(rom_path)));
+        let cartridge = Rc::new(RefCell::new(Cartridge::new_from_file(rom_path, &LoadOptions::default()).unwrap()));
         let cpu_ram = cpu::ram::Ram::new(0x0800);
         let cpu_bus = cpu::bus::Bus::new(cpu_ram, cartridge.clone());
         let mut cpu = cpu::Cpu::new(cpu_bus);
@@ -107,17 +529,12 @@ mod tests {
             base: u32,
         }
 
-        let last_line_official_opcodes = 5004;
         let mut line_number: u32 = 1;
 
         for line in lines_iter {
-            if line_number == last_line_official_opcodes {
-                break;
-            }
-
-            // Skip cycles
-            while cpu.skip_cycles != 0 {
-                cpu.step();
+            // Run the rest of the previous instruction
+            while !cpu.is_at_instruction_boundary() {
+                cpu.step().unwrap();
                 let ppu = cpu.bus.ppu.as_mut().unwrap();
                 ppu.step();
                 ppu.step();
@@ -219,7 +636,7 @@ mod tests {
 
             // Prepare for next line
             line_number += 1;
-            cpu.step();
+            cpu.step().unwrap();
             let ppu = cpu.bus.ppu.as_mut().unwrap();
             ppu.step();
             ppu.step();
@@ -0,0 +1,101 @@
+use super::{Mapper, Memory, CHR_BANK_SIZE_8K, PRG_BANK_SIZE_8K, PRG_BANK_SIZE_32K};
+use crate::cartridge::NametableMirroring;
+
+const MASK_PRG_BANK: u8 = 0b0000_0111;
+const MASK_NAMETABLE_SELECT: u8 = 0b0001_0000;
+
+/// AxROM (mapper 7)
+///
+/// Switchable 32 KiB PRG ROM bank, 8 KiB of CHR RAM and single-screen mirroring.
+/// Bit 4 of the bank register selects which 1 KiB of VRAM is used for all nametables.
+///
+/// Useful links:
+/// [Nesdev wiki - AxROM]
+///
+/// [Nesdev wiki - AxROM]: https://www.nesdev.org/wiki/AxROM
+pub struct Axrom {
+    memory: Memory,
+    bus_conflicts: bool,
+    register: u8,
+}
+
+impl Axrom {
+    pub fn new(memory: Memory, bus_conflicts: bool) -> Axrom {
+        Axrom {
+            memory,
+            bus_conflicts,
+            register: 0,
+        }
+    }
+}
+
+impl Mapper for Axrom {
+    fn read_prg(&self, address: u16) -> Option<u8> {
+        match address {
+            0x6000..=0x7FFF => self.memory.read_prg_ram(PRG_BANK_SIZE_8K, 0, address as usize),
+            0x8000..=0xFFFF => {
+                let bank = (self.register & MASK_PRG_BANK) as usize;
+                self.memory.read_prg_rom(PRG_BANK_SIZE_32K, bank, address as usize)
+            },
+            _ => panic!("Trying to read from invalid ROM address."),
+        }
+    }
+
+    fn write_prg(&mut self, address: u16, mut value: u8) {
+        if address < 0x8000 {
+            self.memory.write_prg_ram(PRG_BANK_SIZE_8K, 0, address as usize, value);
+            return;
+        }
+        if self.bus_conflicts {
+            value &= self.read_prg(address).unwrap_or(value);
+        }
+        self.register = value;
+    }
+
+    fn read_chr(&self, address: u16) -> u8 {
+        self.memory.read_chr(CHR_BANK_SIZE_8K, 0, address as usize)
+    }
+
+    fn write_chr(&mut self, address: u16, value: u8) {
+        self.memory.write_chr(CHR_BANK_SIZE_8K, 0, address as usize, value);
+    }
+
+    fn mirroring(&self) -> NametableMirroring {
+        if self.register & MASK_NAMETABLE_SELECT == 0 {
+            NametableMirroring::SingleScreenA
+        } else {
+            NametableMirroring::SingleScreenB
+        }
+    }
+
+    fn power_cycle(&mut self) {
+        *self = Axrom::new(std::mem::take(&mut self.memory), self.bus_conflicts);
+    }
+
+    fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    fn memory_mut(&mut self) -> &mut Memory {
+        &mut self.memory
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_axrom_banks_and_mirroring() -> Result<(), std::io::Error> {
+        let prg_rom = (0..8).flat_map(|bank| vec![bank as u8; PRG_BANK_SIZE_32K]).collect();
+        let mut axrom = Axrom::new(Memory::new(prg_rom, Vec::new()), false);
+        assert_eq!(axrom.read_prg(0x8000), Some(0));
+        assert!(axrom.mirroring() == NametableMirroring::SingleScreenA);
+
+        axrom.write_prg(0x8000, 0x15);
+        assert_eq!(axrom.read_prg(0x8000), Some(5));
+        assert_eq!(axrom.read_prg(0xFFFF), Some(5));
+        assert!(axrom.mirroring() == NametableMirroring::SingleScreenB);
+        Ok(())
+    }
+}
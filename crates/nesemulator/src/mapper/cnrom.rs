@@ -0,0 +1,73 @@
+use super::{Mapper, Memory, CHR_BANK_SIZE_8K, PRG_BANK_SIZE_8K, PRG_BANK_SIZE_32K};
+use crate::cartridge::NametableMirroring;
+
+/// CNROM (mapper 3)
+///
+/// Fixed 16 or 32 KiB of PRG ROM and a switchable 8 KiB CHR ROM bank.
+///
+/// Useful links:
+/// [Nesdev wiki - CNROM]
+///
+/// [Nesdev wiki - CNROM]: https://www.nesdev.org/wiki/CNROM
+pub struct Cnrom {
+    memory: Memory,
+    mirroring: NametableMirroring,
+    bus_conflicts: bool,
+    chr_bank: u8,
+}
+
+impl Cnrom {
+    pub fn new(memory: Memory, mirroring: NametableMirroring, bus_conflicts: bool) -> Cnrom {
+        Cnrom {
+            memory,
+            mirroring,
+            bus_conflicts,
+            chr_bank: 0,
+        }
+    }
+}
+
+impl Mapper for Cnrom {
+    fn read_prg(&self, address: u16) -> Option<u8> {
+        match address {
+            0x6000..=0x7FFF => self.memory.read_prg_ram(PRG_BANK_SIZE_8K, 0, address as usize),
+            0x8000..=0xFFFF => self.memory.read_prg_rom(PRG_BANK_SIZE_32K, 0, address as usize),
+            _ => panic!("Trying to read from invalid ROM address."),
+        }
+    }
+
+    fn write_prg(&mut self, address: u16, mut value: u8) {
+        if address < 0x8000 {
+            self.memory.write_prg_ram(PRG_BANK_SIZE_8K, 0, address as usize, value);
+            return;
+        }
+        if self.bus_conflicts {
+            value &= self.read_prg(address).unwrap_or(value);
+        }
+        self.chr_bank = value;
+    }
+
+    fn read_chr(&self, address: u16) -> u8 {
+        self.memory.read_chr(CHR_BANK_SIZE_8K, self.chr_bank as usize, address as usize)
+    }
+
+    fn write_chr(&mut self, address: u16, value: u8) {
+        self.memory.write_chr(CHR_BANK_SIZE_8K, self.chr_bank as usize, address as usize, value);
+    }
+
+    fn mirroring(&self) -> NametableMirroring {
+        self.mirroring
+    }
+
+    fn power_cycle(&mut self) {
+        *self = Cnrom::new(std::mem::take(&mut self.memory), self.mirroring, self.bus_conflicts);
+    }
+
+    fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    fn memory_mut(&mut self) -> &mut Memory {
+        &mut self.memory
+    }
+}
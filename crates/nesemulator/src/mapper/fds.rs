@@ -0,0 +1,484 @@
+use super::{Mapper, Memory, CHR_BANK_SIZE_8K, PRG_BANK_SIZE_8K};
+use crate::cartridge::NametableMirroring;
+
+/// Size of one disk side in an .fds image. The image does not contain the gaps and CRCs of the real disk.
+pub const DISK_SIDE_SIZE: usize = 65500;
+/// Size of the BIOS ROM (disksys.rom).
+pub const BIOS_SIZE: usize = 0x2000;
+/// Size of the RAM adapter's PRG RAM at 0x6000..=0xDFFF.
+pub const PRG_RAM_SIZE: usize = 0x8000;
+
+const FWNES_HEADER_SIZE: usize = 16;
+/// The disk starts with a gap of 28300 bits before the first block.
+const LEADING_GAP_SIZE: usize = 28300 / 8;
+/// There is a gap of 976 bits after each block.
+const BLOCK_GAP_SIZE: usize = 976 / 8;
+/// The block start mark is the first set bit after a gap.
+const BLOCK_START_MARK: u8 = 0x80;
+/// CPU cycles it takes to transfer one byte at the drive's rate of about 96.4 kbit/s.
+const BYTE_TRANSFER_CYCLES: u32 = 150;
+/// CPU cycles it takes for the head to return to the start of the disk after reaching the end.
+const HEAD_RETURN_CYCLES: u32 = 50000;
+
+/// Returns true if the data is a Famicom Disk System image, with or without the fwNES header.
+pub fn is_disk_image(data: &[u8]) -> bool {
+    data.starts_with(b"FDS\x1a") || data.get(1..15) == Some(&b"*NINTENDO-HVC*"[..])
+}
+
+/// Splits an .fds image into disk sides and adds the gaps, block start marks and CRCs that the drive expects.
+/// Returns None if the image contains no disk sides.
+pub fn disk_sides_from_image(data: &[u8]) -> Option<Vec<Vec<u8>>> {
+    let data = if data.starts_with(b"FDS\x1a") { &data[FWNES_HEADER_SIZE.min(data.len())..] } else { data };
+    let sides: Vec<Vec<u8>> = data.chunks(DISK_SIDE_SIZE)
+        .filter(|side| side.len() == DISK_SIDE_SIZE)
+        .map(add_gaps)
+        .collect();
+    if sides.is_empty() {
+        None
+    } else {
+        Some(sides)
+    }
+}
+
+/// Converts a disk side to the byte stream read by the drive.
+///
+/// Block lengths are given by the block type, except for file data blocks (type 4)
+/// whose length is in the preceding file header block (type 3).
+fn add_gaps(side: &[u8]) -> Vec<u8> {
+    let mut disk = vec![0; LEADING_GAP_SIZE];
+    let mut position = 0;
+    let mut file_size = 0;
+    while position < side.len() {
+        let block_length = match side[position] {
+            1 => 56, // Disk info
+            2 => 2, // File amount
+            3 => {
+                // File header. The file size is at bytes 13 and 14
+                file_size = side.get(position + 13..position + 15)
+                    .map_or(0, |size| u16::from_le_bytes([size[0], size[1]]) as usize);
+                16
+            },
+            4 => 1 + file_size, // File data
+            _ => break,
+        };
+        let block = &side[position..(position + block_length).min(side.len())];
+        disk.push(BLOCK_START_MARK);
+        disk.extend_from_slice(block);
+        disk.extend_from_slice(&crc(block).to_le_bytes());
+        disk.resize(disk.len() + BLOCK_GAP_SIZE, 0);
+        position += block_length;
+    }
+    if disk.len() < DISK_SIDE_SIZE {
+        disk.resize(DISK_SIDE_SIZE, 0);
+    }
+    disk
+}
+
+/// CRC-16/KERMIT of a block including the block start mark, as the drive calculates it.
+fn crc(block: &[u8]) -> u16 {
+    let mut crc: u16 = 0x8000;
+    for &byte in block.iter().chain([0, 0].iter()) {
+        for bit in 0..8 {
+            let carry = crc & 1 == 1;
+            crc = (crc >> 1) | ((((byte >> bit) & 1) as u16) << 15);
+            if carry {
+                crc ^= 0x8408;
+            }
+        }
+    }
+    crc
+}
+
+/// Famicom Disk System
+///
+/// The RAM adapter provides 32 KiB of PRG RAM at 0x6000..=0xDFFF, the 8 KiB BIOS at 0xE000..=0xFFFF,
+/// 8 KiB of CHR RAM and mirroring control.
+/// Its registers at 0x4020..=0x4033 control a timer IRQ and the disk drive:
+/// - 0x4020..=0x4022: IRQ reload value and IRQ control
+/// - 0x4023: Master I/O enable
+/// - 0x4024: Write data
+/// - 0x4025: Drive control and mirroring
+/// - 0x4030..=0x4032: Disk status, read data and drive status
+///
+/// The drive reads the disk one byte every 150 CPU cycles. When the head reaches the end of the disk,
+/// it takes a while until it is back at the start and the disk can be read again.
+/// The FDS sound channel is not emulated.
+///
+/// Useful links:
+/// [Nesdev wiki - Family Computer Disk System]
+///
+/// [Nesdev wiki - Family Computer Disk System]: https://www.nesdev.org/wiki/Family_Computer_Disk_System
+pub struct Fds {
+    memory: Memory,
+    mirroring: NametableMirroring,
+    disk_sides: Vec<Vec<u8>>,
+    disk_side: Option<usize>,
+
+    irq_reload_value: u16,
+    irq_counter: u16,
+    irq_repeat: bool,
+    irq_enabled: bool,
+    timer_irq: bool,
+
+    disk_registers_enabled: bool,
+    motor_on: bool,
+    reset_transfer: bool,
+    read_mode: bool,
+    crc_control: bool,
+    disk_ready: bool,
+    disk_irq_enabled: bool,
+    disk_irq: bool,
+
+    read_data: u8,
+    write_data: u8,
+    transfer_complete: bool,
+    end_of_head: bool,
+    scanning_disk: bool,
+    gap_ended: bool,
+    disk_position: usize,
+    delay: u32,
+}
+
+impl Fds {
+    /// Creates the RAM adapter with the BIOS as PRG ROM and inserts the first disk side.
+    pub fn new(memory: Memory, disk_sides: Vec<Vec<u8>>) -> Fds {
+        Fds {
+            memory,
+            mirroring: NametableMirroring::Horizontal,
+            disk_side: if disk_sides.is_empty() { None } else { Some(0) },
+            disk_sides,
+            irq_reload_value: 0,
+            irq_counter: 0,
+            irq_repeat: false,
+            irq_enabled: false,
+            timer_irq: false,
+            disk_registers_enabled: true,
+            motor_on: false,
+            reset_transfer: false,
+            read_mode: true,
+            crc_control: false,
+            disk_ready: false,
+            disk_irq_enabled: false,
+            disk_irq: false,
+            read_data: 0,
+            write_data: 0,
+            transfer_complete: false,
+            end_of_head: true,
+            scanning_disk: false,
+            gap_ended: false,
+            disk_position: 0,
+            delay: 0,
+        }
+    }
+
+    fn clock_timer(&mut self) {
+        if !self.irq_enabled {
+            return;
+        }
+        if self.irq_counter == 0 {
+            self.timer_irq = true;
+            self.irq_counter = self.irq_reload_value;
+            if !self.irq_repeat {
+                self.irq_enabled = false;
+            }
+        } else {
+            self.irq_counter -= 1;
+        }
+    }
+
+    fn clock_disk(&mut self) {
+        let side = match self.disk_side {
+            Some(side) if self.motor_on => side,
+            _ => {
+                self.end_of_head = true;
+                self.scanning_disk = false;
+                return;
+            },
+        };
+        if self.reset_transfer && !self.scanning_disk {
+            return;
+        }
+        if self.end_of_head {
+            self.delay = HEAD_RETURN_CYCLES;
+            self.end_of_head = false;
+            self.disk_position = 0;
+            self.gap_ended = false;
+            return;
+        }
+        if self.delay > 0 {
+            self.delay -= 1;
+            return;
+        }
+
+        self.scanning_disk = true;
+        if self.read_mode {
+            let data = self.disk_sides[side][self.disk_position];
+            if !self.disk_ready {
+                self.gap_ended = false;
+            } else if data != 0 && !self.gap_ended {
+                // The block start mark itself is not transferred
+                self.gap_ended = true;
+            } else if self.gap_ended {
+                self.transfer_complete = true;
+                self.read_data = data;
+                self.disk_irq |= self.disk_irq_enabled;
+            }
+        } else {
+            let mut data = 0;
+            if !self.crc_control {
+                self.transfer_complete = true;
+                self.disk_irq |= self.disk_irq_enabled;
+                data = self.write_data;
+            }
+            if !self.disk_ready {
+                data = 0;
+            }
+            self.disk_sides[side][self.disk_position] = data;
+            self.gap_ended = false;
+        }
+
+        self.disk_position += 1;
+        if self.disk_position >= self.disk_sides[side].len() {
+            self.motor_on = false;
+            self.end_of_head = true;
+        } else {
+            self.delay = BYTE_TRANSFER_CYCLES - 1;
+        }
+    }
+
+    fn read_disk_status(&mut self) -> u8 {
+        let mut value = 0;
+        value |= self.timer_irq as u8;
+        value |= (self.transfer_complete as u8) << 1;
+        value |= (self.end_of_head as u8) << 6;
+        value |= 0x80; // Disk read/write enabled
+        self.timer_irq = false;
+        self.transfer_complete = false;
+        self.disk_irq = false;
+        value
+    }
+
+    fn read_drive_status(&self) -> u8 {
+        let no_disk = self.disk_side.is_none();
+        let mut value = 0;
+        value |= no_disk as u8;
+        value |= ((no_disk || !self.scanning_disk) as u8) << 1;
+        value |= (no_disk as u8) << 2; // Write protected
+        value
+    }
+
+    fn write_control(&mut self, value: u8) {
+        self.motor_on = value & 0x01 == 0x01;
+        self.reset_transfer = value & 0x02 == 0x02;
+        self.read_mode = value & 0x04 == 0x04;
+        self.mirroring = if value & 0x08 == 0x08 {
+            NametableMirroring::Horizontal
+        } else {
+            NametableMirroring::Vertical
+        };
+        self.crc_control = value & 0x10 == 0x10;
+        self.disk_ready = value & 0x40 == 0x40;
+        self.disk_irq_enabled = value & 0x80 == 0x80;
+        self.disk_irq = false;
+    }
+}
+
+impl Mapper for Fds {
+    fn read_prg(&self, address: u16) -> Option<u8> {
+        match address {
+            0x6000..=0xDFFF => self.memory.read_prg_ram(PRG_RAM_SIZE, 0, address as usize - 0x6000),
+            0xE000..=0xFFFF => self.memory.read_prg_rom(PRG_BANK_SIZE_8K, 0, address as usize),
+            _ => panic!("Trying to read from invalid ROM address."),
+        }
+    }
+
+    fn write_prg(&mut self, address: u16, value: u8) {
+        // The BIOS is ROM
+        if address < 0xE000 {
+            self.memory.write_prg_ram(PRG_RAM_SIZE, 0, address as usize - 0x6000, value);
+        }
+    }
+
+    fn read_expansion(&mut self, address: u16) -> Option<u8> {
+        if !self.disk_registers_enabled {
+            return None;
+        }
+        match address {
+            0x4030 => Some(self.read_disk_status()),
+            0x4031 => {
+                self.transfer_complete = false;
+                self.disk_irq = false;
+                Some(self.read_data)
+            },
+            0x4032 => Some(self.read_drive_status()),
+            0x4033 => Some(0x80), // Battery good
+            _ => None,
+        }
+    }
+
+    fn write_expansion(&mut self, address: u16, value: u8) {
+        if !self.disk_registers_enabled && address != 0x4023 {
+            return;
+        }
+        match address {
+            0x4020 => self.irq_reload_value = (self.irq_reload_value & 0xFF00) | value as u16,
+            0x4021 => self.irq_reload_value = (self.irq_reload_value & 0x00FF) | (value as u16) << 8,
+            0x4022 => {
+                self.irq_repeat = value & 0x01 == 0x01;
+                self.irq_enabled = value & 0x02 == 0x02;
+                if self.irq_enabled {
+                    self.irq_counter = self.irq_reload_value;
+                } else {
+                    self.timer_irq = false;
+                }
+            },
+            0x4023 => {
+                self.disk_registers_enabled = value & 0x01 == 0x01;
+                if !self.disk_registers_enabled {
+                    self.irq_enabled = false;
+                    self.timer_irq = false;
+                    self.disk_irq = false;
+                }
+            },
+            0x4024 => {
+                self.write_data = value;
+                self.transfer_complete = false;
+                self.disk_irq = false;
+            },
+            0x4025 => self.write_control(value),
+            _ => (),
+        }
+    }
+
+    fn read_chr(&self, address: u16) -> u8 {
+        self.memory.read_chr(CHR_BANK_SIZE_8K, 0, address as usize)
+    }
+
+    fn write_chr(&mut self, address: u16, value: u8) {
+        self.memory.write_chr(CHR_BANK_SIZE_8K, 0, address as usize, value);
+    }
+
+    fn mirroring(&self) -> NametableMirroring {
+        self.mirroring
+    }
+
+    fn clock_cpu(&mut self) {
+        self.clock_timer();
+        self.clock_disk();
+    }
+
+    fn irq(&self) -> bool {
+        self.timer_irq || self.disk_irq
+    }
+
+    fn disk_side_count(&self) -> usize {
+        self.disk_sides.len()
+    }
+
+    fn disk_side(&self) -> Option<usize> {
+        self.disk_side
+    }
+
+    fn set_disk_side(&mut self, side: Option<usize>) {
+        if side.is_some_and(|side| side >= self.disk_sides.len()) {
+            return;
+        }
+        self.disk_side = side;
+        self.end_of_head = true;
+        self.scanning_disk = false;
+    }
+
+    /// The disks keep what was written to them and the inserted side stays in the drive.
+    fn power_cycle(&mut self) {
+        let disk_side = self.disk_side;
+        *self = Fds::new(std::mem::take(&mut self.memory), std::mem::take(&mut self.disk_sides));
+        self.disk_side = disk_side;
+    }
+
+    fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    fn memory_mut(&mut self) -> &mut Memory {
+        &mut self.memory
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates a disk side with a disk info block, a file amount block and one file of 3 bytes.
+    fn disk_side() -> Vec<u8> {
+        let mut side = vec![0x01];
+        side.extend_from_slice(b"*NINTENDO-HVC*");
+        side.resize(56, 0);
+        side.extend_from_slice(&[0x02, 0x01]);
+        side.extend_from_slice(&[0x03, 0x00, 0x00, b'F', b'I', b'L', b'E', b' ', b' ', b' ', b' ', 0x00, 0x60, 0x03, 0x00, 0x00]);
+        side.extend_from_slice(&[0x04, 0xAA, 0xBB, 0xCC]);
+        side.resize(DISK_SIDE_SIZE, 0);
+        side
+    }
+
+    #[test]
+    fn test_disk_image_gaps() -> Result<(), std::io::Error> {
+        let mut image = b"FDS\x1a\x01".to_vec();
+        image.resize(FWNES_HEADER_SIZE, 0);
+        image.extend(disk_side());
+        assert!(is_disk_image(&image));
+        assert!(is_disk_image(&disk_side()));
+
+        let sides = disk_sides_from_image(&image).unwrap();
+        assert_eq!(sides.len(), 1);
+        let disk = &sides[0];
+        assert!(disk[..LEADING_GAP_SIZE].iter().all(|&byte| byte == 0));
+        assert_eq!(disk[LEADING_GAP_SIZE], BLOCK_START_MARK);
+        assert_eq!(disk[LEADING_GAP_SIZE + 1], 0x01);
+        let file_data = LEADING_GAP_SIZE + 3 * (1 + 2 + BLOCK_GAP_SIZE) + 56 + 2 + 16;
+        // The block is followed by its CRC-16/KERMIT, which covers the start mark too
+        assert_eq!(disk[file_data..file_data + 7], [BLOCK_START_MARK, 0x04, 0xAA, 0xBB, 0xCC, 0xB2, 0x56]);
+
+        assert!(disk_sides_from_image(b"FDS\x1a\x01").is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_disk_read_and_timer_irq() -> Result<(), std::io::Error> {
+        let mut fds = Fds::new(Memory::new(vec![0; BIOS_SIZE], Vec::new()), disk_sides_from_image(&disk_side()).unwrap());
+        assert_eq!(fds.read_expansion(0x4032), Some(0x02), "Disk inserted but not scanning");
+
+        // Motor on, read mode, disk ready, IRQ on byte transfer
+        fds.write_expansion(0x4025, 0xC5);
+        let mut bytes = Vec::new();
+        for _ in 0..(HEAD_RETURN_CYCLES as usize + (LEADING_GAP_SIZE + 4) * BYTE_TRANSFER_CYCLES as usize) {
+            fds.clock_cpu();
+            if fds.irq() {
+                bytes.push(fds.read_expansion(0x4031).unwrap());
+                assert!(!fds.irq());
+            }
+        }
+        assert_eq!(bytes, [0x01, b'*', b'N']);
+        assert_eq!(fds.read_expansion(0x4032), Some(0x00));
+
+        fds.set_disk_side(None);
+        assert_eq!(fds.read_expansion(0x4032), Some(0x07));
+        assert_eq!(fds.disk_side_count(), 1);
+
+        // Timer IRQ after reload value + 1 cycles, once
+        fds.write_expansion(0x4020, 0x02);
+        fds.write_expansion(0x4021, 0x00);
+        fds.write_expansion(0x4022, 0x02);
+        fds.clock_cpu();
+        fds.clock_cpu();
+        assert!(!fds.irq());
+        fds.clock_cpu();
+        assert!(fds.irq());
+        assert_eq!(fds.read_expansion(0x4030).map(|status| status & 0x01), Some(0x01));
+        assert!(!fds.irq());
+        for _ in 0..10 {
+            fds.clock_cpu();
+        }
+        assert!(!fds.irq());
+        Ok(())
+    }
+}
@@ -0,0 +1,107 @@
+use super::{Mapper, Memory, CHR_BANK_SIZE_8K, PRG_BANK_SIZE_8K, PRG_BANK_SIZE_32K};
+use crate::cartridge::NametableMirroring;
+
+const MASK_CHR_BANK: u8 = 0b0000_0011;
+const MASK_PRG_BANK: u8 = 0b0011_0000;
+
+/// GxROM (mapper 66)
+///
+/// Switchable 32 KiB PRG ROM bank and switchable 8 KiB CHR ROM bank selected by a single register.
+///
+/// Useful links:
+/// [Nesdev wiki - GxROM]
+///
+/// [Nesdev wiki - GxROM]: https://www.nesdev.org/wiki/GxROM
+pub struct Gxrom {
+    memory: Memory,
+    mirroring: NametableMirroring,
+    bus_conflicts: bool,
+    register: u8,
+}
+
+impl Gxrom {
+    pub fn new(memory: Memory, mirroring: NametableMirroring, bus_conflicts: bool) -> Gxrom {
+        Gxrom {
+            memory,
+            mirroring,
+            bus_conflicts,
+            register: 0,
+        }
+    }
+
+    fn chr_bank(&self) -> usize {
+        (self.register & MASK_CHR_BANK) as usize
+    }
+}
+
+impl Mapper for Gxrom {
+    fn read_prg(&self, address: u16) -> Option<u8> {
+        match address {
+            0x6000..=0x7FFF => self.memory.read_prg_ram(PRG_BANK_SIZE_8K, 0, address as usize),
+            0x8000..=0xFFFF => {
+                let bank = ((self.register & MASK_PRG_BANK) >> 4) as usize;
+                self.memory.read_prg_rom(PRG_BANK_SIZE_32K, bank, address as usize)
+            },
+            _ => panic!("Trying to read from invalid ROM address."),
+        }
+    }
+
+    fn write_prg(&mut self, address: u16, mut value: u8) {
+        if address < 0x8000 {
+            self.memory.write_prg_ram(PRG_BANK_SIZE_8K, 0, address as usize, value);
+            return;
+        }
+        if self.bus_conflicts {
+            value &= self.read_prg(address).unwrap_or(value);
+        }
+        self.register = value;
+    }
+
+    fn read_chr(&self, address: u16) -> u8 {
+        self.memory.read_chr(CHR_BANK_SIZE_8K, self.chr_bank(), address as usize)
+    }
+
+    fn write_chr(&mut self, address: u16, value: u8) {
+        self.memory.write_chr(CHR_BANK_SIZE_8K, self.chr_bank(), address as usize, value);
+    }
+
+    fn mirroring(&self) -> NametableMirroring {
+        self.mirroring
+    }
+
+    fn power_cycle(&mut self) {
+        *self = Gxrom::new(std::mem::take(&mut self.memory), self.mirroring, self.bus_conflicts);
+    }
+
+    fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    fn memory_mut(&mut self) -> &mut Memory {
+        &mut self.memory
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gxrom_banks() -> Result<(), std::io::Error> {
+        let prg_rom = (0..4).flat_map(|bank| vec![bank as u8; PRG_BANK_SIZE_32K]).collect();
+        let chr_rom = (0..4).flat_map(|bank| vec![bank as u8 + 0x10; CHR_BANK_SIZE_8K]).collect();
+        let mut gxrom = Gxrom::new(Memory::new(prg_rom, chr_rom), NametableMirroring::Horizontal, true);
+        assert_eq!(gxrom.read_prg(0x8000), Some(0));
+        assert_eq!(gxrom.read_chr(0x0000), 0x10);
+
+        // Bus conflict with ROM byte 0x00 of bank 0 masks the whole write
+        gxrom.write_prg(0x8000, 0x21);
+        assert_eq!(gxrom.read_prg(0x8000), Some(0));
+
+        gxrom.bus_conflicts = false;
+        gxrom.write_prg(0x8000, 0x21);
+        assert_eq!(gxrom.read_prg(0x8000), Some(2));
+        assert_eq!(gxrom.read_chr(0x1FFF), 0x11);
+        Ok(())
+    }
+}
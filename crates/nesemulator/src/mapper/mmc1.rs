@@ -0,0 +1,332 @@
+use super::{Mapper, Memory, CHR_BANK_SIZE_4K, PRG_BANK_SIZE_8K, PRG_BANK_SIZE_16K};
+use crate::cartridge::NametableMirroring;
+
+const MASK_SHIFT_RESET: u8 = 0b1000_0000;
+const MASK_CONTROL_MIRRORING: u8 = 0b0_0011;
+const MASK_CONTROL_PRG_BANK_MODE: u8 = 0b0_1100;
+const MASK_CONTROL_CHR_BANK_MODE: u8 = 0b1_0000;
+const MASK_PRG_BANK: u8 = 0b0_1111;
+const MASK_PRG_RAM_DISABLE: u8 = 0b1_0000;
+const MASK_CHR_PRG_OUTER_BANK: u8 = 0b1_0000;
+
+/// MMC1 (mapper 1)
+///
+/// Registers are written serially one bit at a time through a 5-bit shift register.
+/// The fifth write copies the shift register into the register selected by address bits 13 and 14.
+///
+/// Registers:
+/// - 0x8000..=0x9FFF: Control (mirroring, PRG bank mode, CHR bank mode)
+/// - 0xA000..=0xBFFF: CHR bank 0
+/// - 0xC000..=0xDFFF: CHR bank 1
+/// - 0xE000..=0xFFFF: PRG bank and PRG RAM enable
+///
+/// A write on the CPU cycle right after another write is ignored, so read-modify-write instructions
+/// that write twice shift only one bit.
+///
+/// Boards with 512 KiB of PRG ROM (SUROM) use bit 4 of CHR bank 0 to select the 256 KiB outer PRG bank.
+///
+/// Useful links:
+/// [Nesdev wiki - MMC1]
+///
+/// [Nesdev wiki - MMC1]: https://www.nesdev.org/wiki/MMC1
+pub struct Mmc1 {
+    memory: Memory,
+    shift_register: u8,
+    shift_count: u8,
+    control: u8,
+    chr_bank_0: u8,
+    chr_bank_1: u8,
+    prg_bank: u8,
+    cycle: u64,
+    last_write_cycle: Option<u64>,
+}
+
+impl Mmc1 {
+    pub fn new(memory: Memory) -> Mmc1 {
+        Mmc1 {
+            memory,
+            shift_register: 0,
+            shift_count: 0,
+            control: MASK_CONTROL_PRG_BANK_MODE, // PRG ROM bank mode 3 at power-up
+            chr_bank_0: 0,
+            chr_bank_1: 0,
+            prg_bank: 0,
+            cycle: 0,
+            last_write_cycle: None,
+        }
+    }
+
+    fn write_register(&mut self, address: u16, value: u8) {
+        match address {
+            0x8000..=0x9FFF => self.control = value,
+            0xA000..=0xBFFF => self.chr_bank_0 = value,
+            0xC000..=0xDFFF => self.chr_bank_1 = value,
+            0xE000..=0xFFFF => self.prg_bank = value,
+            _ => unreachable!(),
+        }
+    }
+
+    fn write_shift_register(&mut self, address: u16, value: u8) {
+        let consecutive = self.last_write_cycle == Some(self.cycle.wrapping_sub(1));
+        self.last_write_cycle = Some(self.cycle);
+        if consecutive {
+            return;
+        }
+
+        if value & MASK_SHIFT_RESET != 0 {
+            self.shift_register = 0;
+            self.shift_count = 0;
+            self.control |= MASK_CONTROL_PRG_BANK_MODE;
+            return;
+        }
+
+        self.shift_register |= (value & 1) << self.shift_count;
+        self.shift_count += 1;
+        if self.shift_count == 5 {
+            self.write_register(address, self.shift_register);
+            self.shift_register = 0;
+            self.shift_count = 0;
+        }
+    }
+
+    fn prg_ram_enabled(&self) -> bool {
+        self.prg_bank & MASK_PRG_RAM_DISABLE == 0
+    }
+
+    /// Returns the 256 KiB outer PRG bank used by SUROM.
+    fn prg_outer_bank(&self) -> usize {
+        if self.memory.prg_rom.len() > 0x40000 {
+            ((self.chr_bank_0 & MASK_CHR_PRG_OUTER_BANK) >> 4) as usize * 16
+        } else {
+            0
+        }
+    }
+
+    /// Returns the 16 KiB PRG ROM bank mapped at given CPU address.
+    fn prg_bank_at(&self, address: u16) -> usize {
+        let outer_bank = self.prg_outer_bank();
+        let bank = (self.prg_bank & MASK_PRG_BANK) as usize;
+        let last_bank = (self.memory.prg_bank_count(PRG_BANK_SIZE_16K) - 1).min(15);
+        let upper_half = address >= 0xC000;
+        match ((self.control & MASK_CONTROL_PRG_BANK_MODE) >> 2, upper_half) {
+            // Switch 32 KiB at 0x8000, ignoring low bit of bank number
+            (0 | 1, false) => outer_bank + (bank & !1),
+            (0 | 1, true) => outer_bank + (bank | 1),
+            // Fix first bank at 0x8000 and switch 16 KiB bank at 0xC000
+            (2, false) => outer_bank,
+            (2, true) => outer_bank + bank,
+            // Fix last bank at 0xC000 and switch 16 KiB bank at 0x8000
+            (3, false) => outer_bank + bank,
+            (3, true) => outer_bank + last_bank,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Returns the 4 KiB CHR bank mapped at given PPU address.
+    fn chr_bank_at(&self, address: u16) -> usize {
+        let upper_half = address >= 0x1000;
+        if self.control & MASK_CONTROL_CHR_BANK_MODE == 0 {
+            // Switch 8 KiB at a time, ignoring low bit of bank number
+            (self.chr_bank_0 & !1) as usize + upper_half as usize
+        } else if upper_half {
+            self.chr_bank_1 as usize
+        } else {
+            self.chr_bank_0 as usize
+        }
+    }
+}
+
+impl Mapper for Mmc1 {
+    fn read_prg(&self, address: u16) -> Option<u8> {
+        match address {
+            0x6000..=0x7FFF => {
+                if self.prg_ram_enabled() {
+                    self.memory.read_prg_ram(PRG_BANK_SIZE_8K, 0, address as usize)
+                } else {
+                    None
+                }
+            },
+            0x8000..=0xFFFF => {
+                let bank = self.prg_bank_at(address);
+                self.memory.read_prg_rom(PRG_BANK_SIZE_16K, bank, address as usize)
+            },
+            _ => panic!("Trying to read from invalid ROM address."),
+        }
+    }
+
+    fn write_prg(&mut self, address: u16, value: u8) {
+        match address {
+            0x6000..=0x7FFF => {
+                if self.prg_ram_enabled() {
+                    self.memory.write_prg_ram(PRG_BANK_SIZE_8K, 0, address as usize, value);
+                }
+            },
+            0x8000..=0xFFFF => self.write_shift_register(address, value),
+            _ => panic!("Trying to write to invalid ROM address."),
+        }
+    }
+
+    fn read_chr(&self, address: u16) -> u8 {
+        let bank = self.chr_bank_at(address);
+        self.memory.read_chr(CHR_BANK_SIZE_4K, bank, address as usize)
+    }
+
+    fn write_chr(&mut self, address: u16, value: u8) {
+        let bank = self.chr_bank_at(address);
+        self.memory.write_chr(CHR_BANK_SIZE_4K, bank, address as usize, value);
+    }
+
+    fn clock_cpu(&mut self) {
+        self.cycle += 1;
+    }
+
+    fn mirroring(&self) -> NametableMirroring {
+        match self.control & MASK_CONTROL_MIRRORING {
+            0 => NametableMirroring::SingleScreenA,
+            1 => NametableMirroring::SingleScreenB,
+            2 => NametableMirroring::Vertical,
+            3 => NametableMirroring::Horizontal,
+            _ => unreachable!(),
+        }
+    }
+
+    fn power_cycle(&mut self) {
+        *self = Mmc1::new(std::mem::take(&mut self.memory));
+    }
+
+    fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    fn memory_mut(&mut self) -> &mut Memory {
+        &mut self.memory
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates MMC1 with 256 KiB PRG ROM and 128 KiB CHR ROM.
+    /// Each byte of a bank contains the number of that bank.
+    fn new_mmc1() -> Mmc1 {
+        let prg_rom = (0..16).flat_map(|bank| vec![bank as u8; PRG_BANK_SIZE_16K]).collect();
+        let chr_rom = (0..32).flat_map(|bank| vec![bank as u8; CHR_BANK_SIZE_4K]).collect();
+        Mmc1::new(Memory::new(prg_rom, chr_rom))
+    }
+
+    fn write_serial(mmc1: &mut Mmc1, address: u16, value: u8) {
+        for i in 0..5 {
+            mmc1.write_prg(address, (value >> i) & 1);
+        }
+    }
+
+    #[test]
+    fn test_mmc1_power_up_state() -> Result<(), std::io::Error> {
+        let mmc1 = new_mmc1();
+        assert_eq!(mmc1.read_prg(0x8000), Some(0));
+        assert_eq!(mmc1.read_prg(0xC000), Some(15));
+        assert_eq!(mmc1.read_prg(0xFFFF), Some(15));
+        Ok(())
+    }
+
+    #[test]
+    fn test_mmc1_prg_bank_modes() -> Result<(), std::io::Error> {
+        let mut mmc1 = new_mmc1();
+
+        // Mode 3: switch 0x8000, fix last bank at 0xC000
+        write_serial(&mut mmc1, 0xE000, 5);
+        assert_eq!(mmc1.read_prg(0x8000), Some(5));
+        assert_eq!(mmc1.read_prg(0xC000), Some(15));
+
+        // Mode 2: fix first bank at 0x8000, switch 0xC000
+        write_serial(&mut mmc1, 0x8000, 0b0_1000);
+        assert_eq!(mmc1.read_prg(0x8000), Some(0));
+        assert_eq!(mmc1.read_prg(0xC000), Some(5));
+
+        // Mode 0: switch 32 KiB, low bit ignored
+        write_serial(&mut mmc1, 0x8000, 0b0_0000);
+        assert_eq!(mmc1.read_prg(0x8000), Some(4));
+        assert_eq!(mmc1.read_prg(0xC000), Some(5));
+
+        // Disabled PRG RAM leaves the data bus open
+        assert_eq!(mmc1.read_prg(0x6000), Some(0));
+        write_serial(&mut mmc1, 0xE000, 0b1_0101);
+        assert_eq!(mmc1.read_prg(0x6000), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_mmc1_shift_register_reset() -> Result<(), std::io::Error> {
+        let mut mmc1 = new_mmc1();
+        write_serial(&mut mmc1, 0x8000, 0b0_0000);
+
+        // Partial write followed by reset must not modify the PRG bank
+        mmc1.write_prg(0xE000, 1);
+        mmc1.write_prg(0xE000, 1);
+        mmc1.write_prg(0xE000, 0x80);
+        assert_eq!(mmc1.read_prg(0x8000), Some(0));
+        assert_eq!(mmc1.read_prg(0xC000), Some(15)); // Reset sets PRG bank mode 3
+
+        write_serial(&mut mmc1, 0xE000, 3);
+        assert_eq!(mmc1.read_prg(0x8000), Some(3));
+        Ok(())
+    }
+
+    #[test]
+    fn test_mmc1_consecutive_writes() -> Result<(), std::io::Error> {
+        let mut mmc1 = new_mmc1();
+
+        // The second write of a read-modify-write instruction is ignored
+        for bit in [1, 1, 0, 1, 0] {
+            mmc1.clock_cpu();
+            mmc1.write_prg(0xE000, 0);
+            mmc1.clock_cpu();
+            mmc1.write_prg(0xE000, bit);
+            mmc1.clock_cpu();
+        }
+        assert_eq!(mmc1.read_prg(0x8000), Some(0));
+
+        for bit in [1, 1, 0, 0, 0] {
+            mmc1.clock_cpu();
+            mmc1.write_prg(0xE000, bit);
+            mmc1.clock_cpu();
+            mmc1.write_prg(0xE000, 0);
+            mmc1.clock_cpu();
+        }
+        assert_eq!(mmc1.read_prg(0x8000), Some(3));
+        Ok(())
+    }
+
+    #[test]
+    fn test_mmc1_chr_bank_modes() -> Result<(), std::io::Error> {
+        let mut mmc1 = new_mmc1();
+        write_serial(&mut mmc1, 0xA000, 7);
+        write_serial(&mut mmc1, 0xC000, 20);
+
+        // 8 KiB mode ignores CHR bank 1 and the low bit of CHR bank 0
+        assert_eq!(mmc1.read_chr(0x0000), 6);
+        assert_eq!(mmc1.read_chr(0x1000), 7);
+
+        // 4 KiB mode
+        write_serial(&mut mmc1, 0x8000, 0b1_1100);
+        assert_eq!(mmc1.read_chr(0x0000), 7);
+        assert_eq!(mmc1.read_chr(0x1FFF), 20);
+        Ok(())
+    }
+
+    #[test]
+    fn test_mmc1_mirroring() -> Result<(), std::io::Error> {
+        let mut mmc1 = new_mmc1();
+        for (value, mirroring) in [
+            (0, NametableMirroring::SingleScreenA),
+            (1, NametableMirroring::SingleScreenB),
+            (2, NametableMirroring::Vertical),
+            (3, NametableMirroring::Horizontal),
+        ] {
+            write_serial(&mut mmc1, 0x8000, 0b0_1100 | value);
+            assert!(mmc1.mirroring() == mirroring);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,339 @@
+use super::{Mapper, Memory, CHR_BANK_SIZE_1K, PRG_BANK_SIZE_8K};
+use crate::cartridge::NametableMirroring;
+
+const MASK_BANK_SELECT_REGISTER: u8 = 0b0000_0111;
+const MASK_BANK_SELECT_PRG_MODE: u8 = 0b0100_0000;
+const MASK_BANK_SELECT_CHR_INVERSION: u8 = 0b1000_0000;
+const MASK_PRG_RAM_ENABLE: u8 = 0b1000_0000;
+const MASK_PRG_RAM_WRITE_PROTECT: u8 = 0b0100_0000;
+const MASK_A12: u16 = 0x1000;
+
+/// Number of CPU cycles A12 has to stay low before a rising edge clocks the IRQ counter.
+const A12_LOW_CYCLES: u8 = 3;
+
+/// MMC3 (mapper 4)
+///
+/// Registers (even and odd addresses of each range select different registers):
+/// - 0x8000..=0x9FFF: Bank select / bank data
+/// - 0xA000..=0xBFFF: Mirroring / PRG RAM protect
+/// - 0xC000..=0xDFFF: IRQ latch / IRQ reload
+/// - 0xE000..=0xFFFF: IRQ disable / IRQ enable
+///
+/// The scanline counter is clocked by rising edges of PPU address line A12.
+/// With the usual setup of background from pattern table 0x0000 and sprites from 0x1000,
+/// this happens once per scanline during the sprite pattern fetches.
+/// Short low periods of A12 are filtered out like the real chip does
+/// by counting CPU cycles since A12 went low.
+///
+/// Useful links:
+/// [Nesdev wiki - MMC3]
+///
+/// [Nesdev wiki - MMC3]: https://www.nesdev.org/wiki/MMC3
+pub struct Mmc3 {
+    memory: Memory,
+    mirroring: NametableMirroring,
+    bank_select: u8,
+    bank_registers: [u8; 8],
+    prg_ram_protect: u8,
+    irq_latch: u8,
+    irq_counter: u8,
+    irq_reload: bool,
+    irq_enabled: bool,
+    irq_pending: bool,
+    a12: bool,
+    a12_low_cycles: u8,
+}
+
+impl Mmc3 {
+    pub fn new(memory: Memory, mirroring: NametableMirroring) -> Mmc3 {
+        Mmc3 {
+            memory,
+            mirroring,
+            bank_select: 0,
+            bank_registers: [0, 2, 4, 5, 6, 7, 0, 1],
+            prg_ram_protect: MASK_PRG_RAM_ENABLE,
+            irq_latch: 0,
+            irq_counter: 0,
+            irq_reload: false,
+            irq_enabled: false,
+            irq_pending: false,
+            a12: false,
+            a12_low_cycles: 0,
+        }
+    }
+
+    fn write_register(&mut self, address: u16, value: u8) {
+        let even = address & 1 == 0;
+        match (address, even) {
+            (0x8000..=0x9FFF, true) => self.bank_select = value,
+            (0x8000..=0x9FFF, false) => {
+                let register = self.bank_select & MASK_BANK_SELECT_REGISTER;
+                self.bank_registers[register as usize] = value;
+            },
+            (0xA000..=0xBFFF, true) => {
+                self.mirroring = if value & 1 == 0 {
+                    NametableMirroring::Vertical
+                } else {
+                    NametableMirroring::Horizontal
+                };
+            },
+            (0xA000..=0xBFFF, false) => self.prg_ram_protect = value,
+            (0xC000..=0xDFFF, true) => self.irq_latch = value,
+            (0xC000..=0xDFFF, false) => {
+                self.irq_counter = 0;
+                self.irq_reload = true;
+            },
+            (0xE000..=0xFFFF, true) => {
+                self.irq_enabled = false;
+                self.irq_pending = false;
+            },
+            (0xE000..=0xFFFF, false) => self.irq_enabled = true,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Returns the 8 KiB PRG ROM bank mapped at given CPU address.
+    fn prg_bank_at(&self, address: u16) -> usize {
+        let second_last_bank = self.memory.prg_bank_count(PRG_BANK_SIZE_8K).saturating_sub(2);
+        let prg_mode = self.bank_select & MASK_BANK_SELECT_PRG_MODE != 0;
+        match (address, prg_mode) {
+            (0x8000..=0x9FFF, false) => self.bank_registers[6] as usize,
+            (0x8000..=0x9FFF, true) => second_last_bank,
+            (0xA000..=0xBFFF, _) => self.bank_registers[7] as usize,
+            (0xC000..=0xDFFF, false) => second_last_bank,
+            (0xC000..=0xDFFF, true) => self.bank_registers[6] as usize,
+            (0xE000..=0xFFFF, _) => second_last_bank + 1,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Returns the 1 KiB CHR bank mapped at given PPU address.
+    fn chr_bank_at(&self, address: u16) -> usize {
+        let mut address = address;
+        if self.bank_select & MASK_BANK_SELECT_CHR_INVERSION != 0 {
+            address ^= 0x1000;
+        }
+        let r = &self.bank_registers;
+        let bank = match address {
+            // Two 2 KiB banks, low bit ignored
+            0x0000..=0x03FF => r[0] & !1,
+            0x0400..=0x07FF => r[0] | 1,
+            0x0800..=0x0BFF => r[1] & !1,
+            0x0C00..=0x0FFF => r[1] | 1,
+            // Four 1 KiB banks
+            0x1000..=0x13FF => r[2],
+            0x1400..=0x17FF => r[3],
+            0x1800..=0x1BFF => r[4],
+            0x1C00..=0x1FFF => r[5],
+            _ => unreachable!(),
+        };
+        bank as usize
+    }
+
+    fn clock_irq_counter(&mut self) {
+        if self.irq_counter == 0 || self.irq_reload {
+            self.irq_counter = self.irq_latch;
+            self.irq_reload = false;
+        } else {
+            self.irq_counter -= 1;
+        }
+
+        if self.irq_counter == 0 && self.irq_enabled {
+            self.irq_pending = true;
+        }
+    }
+
+    fn prg_ram_readable(&self) -> bool {
+        self.prg_ram_protect & MASK_PRG_RAM_ENABLE != 0
+    }
+
+    fn prg_ram_writable(&self) -> bool {
+        self.prg_ram_readable() && self.prg_ram_protect & MASK_PRG_RAM_WRITE_PROTECT == 0
+    }
+}
+
+impl Mapper for Mmc3 {
+    fn read_prg(&self, address: u16) -> Option<u8> {
+        match address {
+            0x6000..=0x7FFF => {
+                if self.prg_ram_readable() {
+                    self.memory.read_prg_ram(PRG_BANK_SIZE_8K, 0, address as usize)
+                } else {
+                    None
+                }
+            },
+            0x8000..=0xFFFF => {
+                let bank = self.prg_bank_at(address);
+                self.memory.read_prg_rom(PRG_BANK_SIZE_8K, bank, address as usize)
+            },
+            _ => panic!("Trying to read from invalid ROM address."),
+        }
+    }
+
+    fn write_prg(&mut self, address: u16, value: u8) {
+        match address {
+            0x6000..=0x7FFF => {
+                if self.prg_ram_writable() {
+                    self.memory.write_prg_ram(PRG_BANK_SIZE_8K, 0, address as usize, value);
+                }
+            },
+            0x8000..=0xFFFF => self.write_register(address, value),
+            _ => panic!("Trying to write to invalid ROM address."),
+        }
+    }
+
+    fn read_chr(&self, address: u16) -> u8 {
+        let bank = self.chr_bank_at(address);
+        self.memory.read_chr(CHR_BANK_SIZE_1K, bank, address as usize)
+    }
+
+    fn write_chr(&mut self, address: u16, value: u8) {
+        let bank = self.chr_bank_at(address);
+        self.memory.write_chr(CHR_BANK_SIZE_1K, bank, address as usize, value);
+    }
+
+    fn mirroring(&self) -> NametableMirroring {
+        self.mirroring
+    }
+
+    fn notify_ppu_address(&mut self, address: u16) {
+        let a12 = address & MASK_A12 != 0;
+        if a12 && !self.a12 && self.a12_low_cycles >= A12_LOW_CYCLES {
+            self.clock_irq_counter();
+        }
+        if !a12 && self.a12 {
+            self.a12_low_cycles = 0;
+        }
+        self.a12 = a12;
+    }
+
+    fn clock_cpu(&mut self) {
+        if !self.a12 {
+            self.a12_low_cycles = self.a12_low_cycles.saturating_add(1);
+        }
+    }
+
+    fn irq(&self) -> bool {
+        self.irq_pending
+    }
+
+    fn power_cycle(&mut self) {
+        *self = Mmc3::new(std::mem::take(&mut self.memory), self.mirroring);
+    }
+
+    fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    fn memory_mut(&mut self) -> &mut Memory {
+        &mut self.memory
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates MMC3 with 128 KiB PRG ROM and 128 KiB CHR ROM.
+    /// Each byte of a bank contains the number of that bank.
+    fn new_mmc3() -> Mmc3 {
+        let prg_rom = (0..16).flat_map(|bank| vec![bank as u8; PRG_BANK_SIZE_8K]).collect();
+        let chr_rom = (0..128).flat_map(|bank| vec![bank as u8; CHR_BANK_SIZE_1K]).collect();
+        Mmc3::new(Memory::new(prg_rom, chr_rom), NametableMirroring::Vertical)
+    }
+
+    /// Simulates the PPU fetching sprite patterns from 0x1000 after a scanline of background fetches from 0x0000.
+    fn scanline(mmc3: &mut Mmc3) {
+        mmc3.notify_ppu_address(0x0000);
+        for _ in 0..100 {
+            mmc3.clock_cpu();
+        }
+        mmc3.notify_ppu_address(0x1000);
+        mmc3.notify_ppu_address(0x1008);
+        mmc3.clock_cpu();
+    }
+
+    #[test]
+    fn test_mmc3_prg_banks() -> Result<(), std::io::Error> {
+        let mut mmc3 = new_mmc3();
+        mmc3.write_prg(0x8000, 6);
+        mmc3.write_prg(0x8001, 3);
+        mmc3.write_prg(0x8000, 7);
+        mmc3.write_prg(0x8001, 4);
+        assert_eq!(mmc3.read_prg(0x8000), Some(3));
+        assert_eq!(mmc3.read_prg(0xA000), Some(4));
+        assert_eq!(mmc3.read_prg(0xC000), Some(14));
+        assert_eq!(mmc3.read_prg(0xE000), Some(15));
+
+        // Swap 0x8000 and 0xC000
+        mmc3.write_prg(0x8000, 0x40 | 7);
+        assert_eq!(mmc3.read_prg(0x8000), Some(14));
+        assert_eq!(mmc3.read_prg(0xC000), Some(3));
+
+        // A single 8 KiB bank is mirrored in every window
+        let mut mmc3 = Mmc3::new(Memory::new(vec![0x42; PRG_BANK_SIZE_8K], Vec::new()), NametableMirroring::Vertical);
+        assert_eq!(mmc3.read_prg(0xC000), Some(0x42));
+        assert_eq!(mmc3.read_prg(0xE000), Some(0x42));
+        mmc3.write_prg(0x8000, 0x40);
+        assert_eq!(mmc3.read_prg(0x8000), Some(0x42));
+        Ok(())
+    }
+
+    #[test]
+    fn test_mmc3_chr_banks() -> Result<(), std::io::Error> {
+        let mut mmc3 = new_mmc3();
+        for (register, bank) in [(0, 9), (1, 20), (2, 30), (3, 31), (4, 32), (5, 33)] {
+            mmc3.write_prg(0x8000, register);
+            mmc3.write_prg(0x8001, bank);
+        }
+        assert_eq!(mmc3.read_chr(0x0000), 8);
+        assert_eq!(mmc3.read_chr(0x0400), 9);
+        assert_eq!(mmc3.read_chr(0x0800), 20);
+        assert_eq!(mmc3.read_chr(0x0C00), 21);
+        assert_eq!(mmc3.read_chr(0x1C00), 33);
+
+        // Invert A12
+        mmc3.write_prg(0x8000, 0x80);
+        assert_eq!(mmc3.read_chr(0x0000), 30);
+        assert_eq!(mmc3.read_chr(0x1000), 8);
+        Ok(())
+    }
+
+    #[test]
+    fn test_mmc3_scanline_irq() -> Result<(), std::io::Error> {
+        let mut mmc3 = new_mmc3();
+        mmc3.write_prg(0xC000, 3); // Latch
+        mmc3.write_prg(0xC001, 0); // Reload
+        mmc3.write_prg(0xE001, 0); // Enable
+
+        scanline(&mut mmc3); // Reload to 3
+        scanline(&mut mmc3); // 2
+        scanline(&mut mmc3); // 1
+        assert!(!mmc3.irq());
+        scanline(&mut mmc3); // 0
+        assert!(mmc3.irq());
+
+        // Acknowledge
+        mmc3.write_prg(0xE000, 0);
+        assert!(!mmc3.irq());
+        Ok(())
+    }
+
+    #[test]
+    fn test_mmc3_a12_filter() -> Result<(), std::io::Error> {
+        let mut mmc3 = new_mmc3();
+        mmc3.write_prg(0xC000, 0);
+        mmc3.write_prg(0xE001, 0);
+
+        // Rising edges of A12 right after a short low period are ignored
+        mmc3.notify_ppu_address(0x1000);
+        mmc3.notify_ppu_address(0x2000);
+        mmc3.clock_cpu();
+        mmc3.notify_ppu_address(0x1000);
+        assert!(!mmc3.irq());
+
+        scanline(&mut mmc3);
+        assert!(mmc3.irq());
+        Ok(())
+    }
+}
@@ -0,0 +1,279 @@
+mod axrom;
+mod cnrom;
+pub mod fds;
+mod gxrom;
+mod mmc1;
+mod mmc3;
+mod nrom;
+pub mod nsf;
+mod uxrom;
+
+use crate::cartridge::NametableMirroring;
+use crate::error::Error;
+use crate::rom_header::RomHeader;
+
+pub use axrom::Axrom;
+pub use cnrom::Cnrom;
+pub use fds::Fds;
+pub use gxrom::Gxrom;
+pub use mmc1::Mmc1;
+pub use mmc3::Mmc3;
+pub use nrom::Nrom;
+pub use nsf::Nsf;
+pub use uxrom::Uxrom;
+
+pub const PRG_BANK_SIZE_8K: usize = 0x2000;
+pub const PRG_BANK_SIZE_16K: usize = 0x4000;
+pub const PRG_BANK_SIZE_32K: usize = 0x8000;
+pub const CHR_BANK_SIZE_1K: usize = 0x0400;
+pub const CHR_BANK_SIZE_4K: usize = 0x1000;
+pub const CHR_BANK_SIZE_8K: usize = 0x2000;
+
+/// Address decoding logic of a cartridge board.
+///
+/// A mapper sees every access that the CPU makes to the cartridge space
+/// and every pattern table access that the PPU makes.
+/// It decides which bank of PRG and CHR memory is visible in each address window
+/// and how the nametables are mirrored.
+///
+/// Useful links:
+/// [Nesdev wiki - Mapper]
+///
+/// [Nesdev wiki - Mapper]: https://www.nesdev.org/wiki/Mapper
+pub trait Mapper {
+    /// Reads a byte using a CPU bus address in range 0x6000..=0xFFFF.
+    ///
+    /// Returns None if nothing drives the data bus, such as missing or disabled PRG RAM.
+    fn read_prg(&self, address: u16) -> Option<u8>;
+
+    /// Writes a byte using a CPU bus address in range 0x6000..=0xFFFF.
+    ///
+    /// Writes to the ROM area usually end up in the mapper registers.
+    fn write_prg(&mut self, address: u16, value: u8);
+
+    /// Reads a byte using a PPU bus address in range 0x0000..=0x1FFF.
+    fn read_chr(&self, address: u16) -> u8;
+
+    /// Writes a byte using a PPU bus address in range 0x0000..=0x1FFF.
+    fn write_chr(&mut self, address: u16, value: u8);
+
+    /// Returns the current nametable mirroring.
+    fn mirroring(&self) -> NametableMirroring;
+
+    /// Called on every PPU bus access with the address put on the bus.
+    ///
+    /// Mappers such as MMC3 watch the address lines to count scanlines.
+    fn notify_ppu_address(&mut self, _address: u16) {}
+
+    /// Called once every CPU cycle (M2).
+    fn clock_cpu(&mut self) {}
+
+    /// Returns true if the mapper is asserting the CPU IRQ line.
+    fn irq(&self) -> bool {
+        false
+    }
+
+    /// Reads a byte using a CPU bus address in range 0x4020..=0x5FFF.
+    ///
+    /// Returns None if the mapper does not respond to the address.
+    fn read_expansion(&mut self, _address: u16) -> Option<u8> {
+        None
+    }
+
+    /// Writes a byte using a CPU bus address in range 0x4020..=0x5FFF.
+    fn write_expansion(&mut self, _address: u16, _value: u8) {}
+
+    /// Returns the number of disk sides, 0 if the cartridge has no disk drive.
+    fn disk_side_count(&self) -> usize {
+        0
+    }
+
+    /// Returns the inserted disk side, None if no disk is inserted.
+    fn disk_side(&self) -> Option<usize> {
+        None
+    }
+
+    /// Inserts a disk side, or ejects the disk with None.
+    fn set_disk_side(&mut self, _side: Option<usize>) {}
+
+    /// Called when the Reset button is pressed.
+    ///
+    /// The reset line is not on the cartridge connector, so most boards keep their state.
+    fn reset(&mut self) {}
+
+    /// Returns the registers to their power-up state. Memory contents are kept.
+    fn power_cycle(&mut self) {}
+
+    /// Returns the memory chips of the board.
+    fn memory(&self) -> &Memory;
+
+    fn memory_mut(&mut self) -> &mut Memory;
+}
+
+/// Memory chips found on a cartridge board.
+///
+/// If the board has no CHR ROM, an 8 KiB CHR RAM is used in its place.
+/// PRG RAM (work RAM or battery-backed save RAM) defaults to 8 KiB and can be resized from the ROM header.
+pub struct Memory {
+    pub prg_rom: Vec<u8>,
+    pub chr_rom: Vec<u8>,
+    pub chr_ram: Vec<u8>,
+    pub prg_ram: Vec<u8>,
+}
+
+impl Memory {
+    pub fn new(prg_rom: Vec<u8>, chr_rom: Vec<u8>) -> Memory {
+        Memory {
+            prg_rom,
+            chr_rom,
+            chr_ram: vec![0; CHR_BANK_SIZE_8K],
+            prg_ram: vec![0; PRG_BANK_SIZE_8K],
+        }
+    }
+
+    /// Returns the number of PRG ROM banks of given size.
+    pub fn prg_bank_count(&self, bank_size: usize) -> usize {
+        (self.prg_rom.len() / bank_size).max(1)
+    }
+
+    /// Returns the number of CHR banks of given size.
+    pub fn chr_bank_count(&self, bank_size: usize) -> usize {
+        (self.chr().len() / bank_size).max(1)
+    }
+
+    /// Reads a byte from PRG ROM bank. Returns None if the board has no PRG ROM.
+    ///
+    /// Bank numbers wrap around the size of the ROM.
+    /// A ROM smaller than the bank size is mirrored to fill the bank.
+    pub fn read_prg_rom(&self, bank_size: usize, bank: usize, offset: usize) -> Option<u8> {
+        if self.prg_rom.is_empty() {
+            return None;
+        }
+        let bank = bank % self.prg_bank_count(bank_size);
+        Some(self.prg_rom[(bank * bank_size + offset % bank_size) % self.prg_rom.len()])
+    }
+
+    /// Reads a byte from PRG RAM bank. Returns None if the board has no PRG RAM.
+    ///
+    /// Bank numbers wrap around the size of the RAM.
+    /// A RAM smaller than the bank size is mirrored to fill the bank.
+    pub fn read_prg_ram(&self, bank_size: usize, bank: usize, offset: usize) -> Option<u8> {
+        self.prg_ram_index(bank_size, bank, offset).map(|index| self.prg_ram[index])
+    }
+
+    /// Writes a byte to PRG RAM bank. Writes are ignored if the board has no PRG RAM.
+    pub fn write_prg_ram(&mut self, bank_size: usize, bank: usize, offset: usize, value: u8) {
+        if let Some(index) = self.prg_ram_index(bank_size, bank, offset) {
+            self.prg_ram[index] = value;
+        }
+    }
+
+    fn prg_ram_index(&self, bank_size: usize, bank: usize, offset: usize) -> Option<usize> {
+        if self.prg_ram.is_empty() {
+            return None;
+        }
+        let bank = bank % (self.prg_ram.len() / bank_size).max(1);
+        Some((bank * bank_size + offset % bank_size) % self.prg_ram.len())
+    }
+
+    /// Reads a byte from CHR ROM or RAM bank.
+    ///
+    /// Bank numbers wrap around the size of the memory.
+    pub fn read_chr(&self, bank_size: usize, bank: usize, offset: usize) -> u8 {
+        let bank = bank % self.chr_bank_count(bank_size);
+        let chr = self.chr();
+        chr[(bank * bank_size + offset % bank_size) % chr.len()]
+    }
+
+    /// Writes a byte to CHR RAM bank. Writes are ignored if the board has CHR ROM.
+    pub fn write_chr(&mut self, bank_size: usize, bank: usize, offset: usize, value: u8) {
+        if !self.chr_rom.is_empty() {
+            return;
+        }
+        let bank = bank % self.chr_bank_count(bank_size);
+        let len = self.chr_ram.len();
+        self.chr_ram[(bank * bank_size + offset % bank_size) % len] = value;
+    }
+
+    fn chr(&self) -> &[u8] {
+        if self.chr_rom.is_empty() {
+            &self.chr_ram
+        } else {
+            &self.chr_rom
+        }
+    }
+}
+
+impl Default for Memory {
+    fn default() -> Self {
+        Self::new(Vec::default(), Vec::default())
+    }
+}
+
+/// Returns true if the board described by the header has [bus conflicts].
+///
+/// On boards with bus conflicts the ROM drives the data bus at the same time as the CPU
+/// when the CPU writes to a mapper register, so the register receives the written value
+/// ANDed with the ROM byte at that address.
+///
+/// NES 2.0 submappers of UxROM, CNROM and AxROM tell whether the board has them:
+/// submapper 1 means no bus conflicts and submapper 2 means bus conflicts.
+/// Otherwise UxROM, CNROM and GxROM boards always have them,
+/// and most AxROM games run on ANROM or AOROM boards without bus conflicts.
+///
+/// [bus conflicts]: https://www.nesdev.org/wiki/Bus_conflict
+pub fn has_bus_conflicts(header: &RomHeader) -> bool {
+    match (header.mapper_number, header.submapper_number) {
+        (2, 1) | (3, 1) => false,
+        (2, 2) | (3, 2) => true,
+        (7, 2) => true,
+        (7, _) => false,
+        (mapper_number, _) => matches!(mapper_number, 2 | 3 | 66),
+    }
+}
+
+/// Creates the mapper given by the mapper number of the header.
+///
+/// `bus_conflicts` overrides whether the board has bus conflicts, which is otherwise derived from the header.
+pub fn new_mapper(header: &RomHeader, memory: Memory, bus_conflicts: Option<bool>) -> Result<Box<dyn Mapper>, Error> {
+    let bus_conflicts = bus_conflicts.unwrap_or_else(|| has_bus_conflicts(header));
+    let mirroring = header.mirroring;
+    Ok(match header.mapper_number {
+        0 => Box::new(Nrom::new(memory, mirroring)),
+        1 => Box::new(Mmc1::new(memory)),
+        2 => Box::new(Uxrom::new(memory, mirroring, bus_conflicts)),
+        3 => Box::new(Cnrom::new(memory, mirroring, bus_conflicts)),
+        4 => Box::new(Mmc3::new(memory, mirroring)),
+        7 => Box::new(Axrom::new(memory, bus_conflicts)),
+        66 => Box::new(Gxrom::new(memory, mirroring, bus_conflicts)),
+        mapper_number => return Err(Error::UnsupportedMapper(mapper_number)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prg_ram_mirroring() -> Result<(), std::io::Error> {
+        let mut memory = Memory::new(Vec::new(), Vec::new());
+        memory.prg_ram = vec![0; 0x0800];
+        memory.write_prg_ram(PRG_BANK_SIZE_8K, 0, 0x6001, 0x42);
+        assert_eq!(memory.read_prg_ram(PRG_BANK_SIZE_8K, 0, 0x6801), Some(0x42));
+        assert_eq!(memory.read_prg_ram(PRG_BANK_SIZE_8K, 0, 0x7801), Some(0x42));
+
+        // Boards without PRG RAM ignore writes
+        memory.prg_ram = Vec::new();
+        memory.write_prg_ram(PRG_BANK_SIZE_8K, 0, 0x6001, 0x42);
+        assert_eq!(memory.read_prg_ram(PRG_BANK_SIZE_8K, 0, 0x6001), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_empty_prg_rom() -> Result<(), std::io::Error> {
+        let nrom = Nrom::new(Memory::default(), NametableMirroring::Horizontal);
+        assert_eq!(nrom.read_prg(0x8000), None);
+        assert_eq!(nrom.read_prg(0xFFFC), None);
+        Ok(())
+    }
+}
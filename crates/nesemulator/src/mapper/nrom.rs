@@ -0,0 +1,58 @@
+use super::{Mapper, Memory, CHR_BANK_SIZE_8K, PRG_BANK_SIZE_32K, PRG_BANK_SIZE_8K};
+use crate::cartridge::NametableMirroring;
+
+/// NROM (mapper 0)
+///
+/// No bank switching. 16 KiB PRG ROM is mirrored to fill the whole 0x8000..=0xFFFF range.
+///
+/// Useful links:
+/// [Nesdev wiki - NROM]
+///
+/// [Nesdev wiki - NROM]: https://www.nesdev.org/wiki/NROM
+pub struct Nrom {
+    memory: Memory,
+    mirroring: NametableMirroring,
+}
+
+impl Nrom {
+    pub fn new(memory: Memory, mirroring: NametableMirroring) -> Nrom {
+        Nrom { memory, mirroring }
+    }
+}
+
+impl Mapper for Nrom {
+    fn read_prg(&self, address: u16) -> Option<u8> {
+        match address {
+            0x6000..=0x7FFF => self.memory.read_prg_ram(PRG_BANK_SIZE_8K, 0, address as usize),
+            0x8000..=0xFFFF => self.memory.read_prg_rom(PRG_BANK_SIZE_32K, 0, address as usize),
+            _ => panic!("Trying to read from invalid ROM address."),
+        }
+    }
+
+    fn write_prg(&mut self, address: u16, value: u8) {
+        // No registers. Writing to ROM does nothing.
+        if address < 0x8000 {
+            self.memory.write_prg_ram(PRG_BANK_SIZE_8K, 0, address as usize, value);
+        }
+    }
+
+    fn read_chr(&self, address: u16) -> u8 {
+        self.memory.read_chr(CHR_BANK_SIZE_8K, 0, address as usize)
+    }
+
+    fn write_chr(&mut self, address: u16, value: u8) {
+        self.memory.write_chr(CHR_BANK_SIZE_8K, 0, address as usize, value);
+    }
+
+    fn mirroring(&self) -> NametableMirroring {
+        self.mirroring
+    }
+
+    fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    fn memory_mut(&mut self) -> &mut Memory {
+        &mut self.memory
+    }
+}
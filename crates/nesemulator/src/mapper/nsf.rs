@@ -0,0 +1,87 @@
+use super::{Mapper, Memory, CHR_BANK_SIZE_8K, PRG_BANK_SIZE_8K};
+use crate::cartridge::NametableMirroring;
+
+const PRG_BANK_SIZE_4K: usize = 0x1000;
+
+/// Address of the idle loop that INIT and PLAY return to.
+pub const IDLE_LOOP_ADDRESS: u16 = 0x4100;
+/// JMP IDLE_LOOP_ADDRESS
+const IDLE_LOOP: [u8; 3] = [0x4C, IDLE_LOOP_ADDRESS as u8, (IDLE_LOOP_ADDRESS >> 8) as u8];
+
+/// Hardware of an NSF player
+///
+/// Eight 4 KiB PRG ROM banks at 0x8000..=0xFFFF are selected by writing to 0x5FF8..=0x5FFF,
+/// and there is 8 KiB of PRG RAM at 0x6000..=0x7FFF.
+/// The player code is an idle loop at 0x4100 that the CPU runs while no routine of the file is running.
+///
+/// Useful links:
+/// [Nesdev wiki - NSF]
+///
+/// [Nesdev wiki - NSF]: https://www.nesdev.org/wiki/NSF#Bankswitching
+pub struct Nsf {
+    memory: Memory,
+    banks: [usize; 8],
+}
+
+impl Nsf {
+    pub fn new(memory: Memory) -> Nsf {
+        Nsf {
+            memory,
+            banks: [0, 1, 2, 3, 4, 5, 6, 7],
+        }
+    }
+}
+
+impl Mapper for Nsf {
+    fn read_prg(&self, address: u16) -> Option<u8> {
+        match address {
+            0x6000..=0x7FFF => self.memory.read_prg_ram(PRG_BANK_SIZE_8K, 0, address as usize),
+            0x8000..=0xFFFF => {
+                let bank = self.banks[(address as usize - 0x8000) / PRG_BANK_SIZE_4K];
+                self.memory.read_prg_rom(PRG_BANK_SIZE_4K, bank, address as usize)
+            },
+            _ => panic!("Trying to read from invalid ROM address."),
+        }
+    }
+
+    fn write_prg(&mut self, address: u16, value: u8) {
+        if address < 0x8000 {
+            self.memory.write_prg_ram(PRG_BANK_SIZE_8K, 0, address as usize, value);
+        }
+    }
+
+    fn read_expansion(&mut self, address: u16) -> Option<u8> {
+        let offset = address.wrapping_sub(IDLE_LOOP_ADDRESS) as usize;
+        IDLE_LOOP.get(offset).copied()
+    }
+
+    fn write_expansion(&mut self, address: u16, value: u8) {
+        if let 0x5FF8..=0x5FFF = address {
+            self.banks[address as usize - 0x5FF8] = value as usize;
+        }
+    }
+
+    fn read_chr(&self, address: u16) -> u8 {
+        self.memory.read_chr(CHR_BANK_SIZE_8K, 0, address as usize)
+    }
+
+    fn write_chr(&mut self, address: u16, value: u8) {
+        self.memory.write_chr(CHR_BANK_SIZE_8K, 0, address as usize, value);
+    }
+
+    fn mirroring(&self) -> NametableMirroring {
+        NametableMirroring::Horizontal
+    }
+
+    fn power_cycle(&mut self) {
+        *self = Nsf::new(std::mem::take(&mut self.memory));
+    }
+
+    fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    fn memory_mut(&mut self) -> &mut Memory {
+        &mut self.memory
+    }
+}
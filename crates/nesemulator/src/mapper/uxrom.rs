@@ -0,0 +1,123 @@
+use super::{Mapper, Memory, CHR_BANK_SIZE_8K, PRG_BANK_SIZE_8K, PRG_BANK_SIZE_16K};
+use crate::cartridge::NametableMirroring;
+
+/// UxROM (mapper 2)
+///
+/// Switchable 16 KiB PRG ROM bank at 0x8000..=0xBFFF and the last bank fixed at 0xC000..=0xFFFF.
+/// The boards use 8 KiB of CHR RAM and fixed mirroring.
+///
+/// Useful links:
+/// [Nesdev wiki - UxROM]
+///
+/// [Nesdev wiki - UxROM]: https://www.nesdev.org/wiki/UxROM
+pub struct Uxrom {
+    memory: Memory,
+    mirroring: NametableMirroring,
+    bus_conflicts: bool,
+    prg_bank: u8,
+}
+
+impl Uxrom {
+    pub fn new(memory: Memory, mirroring: NametableMirroring, bus_conflicts: bool) -> Uxrom {
+        Uxrom {
+            memory,
+            mirroring,
+            bus_conflicts,
+            prg_bank: 0,
+        }
+    }
+}
+
+impl Mapper for Uxrom {
+    fn read_prg(&self, address: u16) -> Option<u8> {
+        match address {
+            0x6000..=0x7FFF => self.memory.read_prg_ram(PRG_BANK_SIZE_8K, 0, address as usize),
+            0x8000..=0xBFFF => self.memory.read_prg_rom(PRG_BANK_SIZE_16K, self.prg_bank as usize, address as usize),
+            0xC000..=0xFFFF => {
+                let last_bank = self.memory.prg_bank_count(PRG_BANK_SIZE_16K) - 1;
+                self.memory.read_prg_rom(PRG_BANK_SIZE_16K, last_bank, address as usize)
+            },
+            _ => panic!("Trying to read from invalid ROM address."),
+        }
+    }
+
+    fn write_prg(&mut self, address: u16, mut value: u8) {
+        if address < 0x8000 {
+            self.memory.write_prg_ram(PRG_BANK_SIZE_8K, 0, address as usize, value);
+            return;
+        }
+        if self.bus_conflicts {
+            value &= self.read_prg(address).unwrap_or(value);
+        }
+        self.prg_bank = value;
+    }
+
+    fn read_chr(&self, address: u16) -> u8 {
+        self.memory.read_chr(CHR_BANK_SIZE_8K, 0, address as usize)
+    }
+
+    fn write_chr(&mut self, address: u16, value: u8) {
+        self.memory.write_chr(CHR_BANK_SIZE_8K, 0, address as usize, value);
+    }
+
+    fn mirroring(&self) -> NametableMirroring {
+        self.mirroring
+    }
+
+    fn power_cycle(&mut self) {
+        *self = Uxrom::new(std::mem::take(&mut self.memory), self.mirroring, self.bus_conflicts);
+    }
+
+    fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    fn memory_mut(&mut self) -> &mut Memory {
+        &mut self.memory
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates UxROM with 128 KiB PRG ROM where each byte of a bank contains the number of that bank.
+    fn new_uxrom(bus_conflicts: bool) -> Uxrom {
+        let prg_rom = (0..8).flat_map(|bank| vec![bank as u8; PRG_BANK_SIZE_16K]).collect();
+        Uxrom::new(Memory::new(prg_rom, Vec::new()), NametableMirroring::Vertical, bus_conflicts)
+    }
+
+    #[test]
+    fn test_uxrom_prg_banks() -> Result<(), std::io::Error> {
+        let mut uxrom = new_uxrom(false);
+        assert_eq!(uxrom.read_prg(0x8000), Some(0));
+        assert_eq!(uxrom.read_prg(0xC000), Some(7));
+
+        uxrom.write_prg(0x8000, 3);
+        assert_eq!(uxrom.read_prg(0xBFFF), Some(3));
+        assert_eq!(uxrom.read_prg(0xFFFF), Some(7));
+
+        // Bank numbers wrap around the size of the ROM
+        uxrom.write_prg(0x8000, 9);
+        assert_eq!(uxrom.read_prg(0x8000), Some(1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_uxrom_bus_conflicts() -> Result<(), std::io::Error> {
+        let mut uxrom = new_uxrom(true);
+
+        // The byte in the fixed bank is 7, so 0x0E & 0x07 == 6
+        uxrom.write_prg(0xC000, 0x0E);
+        assert_eq!(uxrom.read_prg(0x8000), Some(6));
+        Ok(())
+    }
+
+    #[test]
+    fn test_uxrom_chr_ram() -> Result<(), std::io::Error> {
+        let mut uxrom = new_uxrom(false);
+        uxrom.write_chr(0x1234, 0xAB);
+        assert_eq!(uxrom.read_chr(0x1234), 0xAB);
+        Ok(())
+    }
+}
@@ -0,0 +1,375 @@
+use crate::apu::CPU_CLOCK_RATE;
+use crate::cpu::Cpu;
+use crate::error::{Error, Result};
+use crate::mapper::nsf::IDLE_LOOP_ADDRESS;
+
+use std::time::Duration;
+
+const NSF_MAGIC: &[u8] = b"NESM\x1a";
+const NSFE_MAGIC: &[u8] = b"NSFE";
+const NSF_HEADER_SIZE: usize = 0x80;
+const BANK_SIZE: usize = 0x1000;
+/// Play period of files that do not give one, about 60.002 Hz.
+const DEFAULT_PLAY_PERIOD_US: u16 = 16639;
+
+/// Returns true if the data is an NSF or NSFe file.
+pub fn is_nsf(data: &[u8]) -> bool {
+    data.starts_with(NSF_MAGIC) || data.starts_with(NSFE_MAGIC)
+}
+
+/// Music file containing the sound code and data of a game.
+///
+/// The file gives the addresses of an INIT routine, which is called once to start a song,
+/// and a PLAY routine, which is called at the play rate, usually once per frame.
+/// Both are parsed from NSF files and from NSFe files, which store the same information in chunks
+/// together with track names and lengths.
+///
+/// Useful links:
+/// [Nesdev wiki - NSF]
+/// [Nesdev wiki - NSFe]
+///
+/// [Nesdev wiki - NSF]: https://www.nesdev.org/wiki/NSF
+/// [Nesdev wiki - NSFe]: https://www.nesdev.org/wiki/NSFe
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct NsfFile {
+    pub title: String,
+    pub artist: String,
+    pub copyright: String,
+    pub song_count: u8,
+    /// First song to play, counting from 0.
+    pub starting_song: u8,
+    pub load_address: u16,
+    pub init_address: u16,
+    pub play_address: u16,
+    /// Time between PLAY calls on NTSC in microseconds.
+    pub play_period_us: u16,
+    /// Initial values of the bank registers at 0x5FF8..=0x5FFF. All zero if the file does not use bankswitching.
+    pub initial_banks: [u8; 8],
+    /// The file is made for PAL consoles only.
+    pub pal: bool,
+    /// Expansion sound chips the file uses, one bit per chip. They are not emulated.
+    pub expansion_chips: u8,
+    /// Track names from an NSFe file.
+    pub track_names: Vec<String>,
+    /// Track lengths from an NSFe file. None if the length is not known.
+    pub track_lengths: Vec<Option<Duration>>,
+    /// Program data loaded at the load address.
+    pub data: Vec<u8>,
+}
+
+impl NsfFile {
+    /// Parses an NSF or NSFe file.
+    pub fn parse(data: &[u8]) -> Result<NsfFile> {
+        let file = if data.starts_with(NSF_MAGIC) {
+            NsfFile::parse_nsf(data)?
+        } else if data.starts_with(NSFE_MAGIC) {
+            NsfFile::parse_nsfe(data)?
+        } else {
+            return Err(invalid_nsf("missing identification string"));
+        };
+        if file.song_count == 0 {
+            return Err(invalid_nsf("no songs"));
+        }
+        if file.data.is_empty() {
+            return Err(invalid_nsf("no program data"));
+        }
+        if !file.is_bankswitched() && file.load_address < 0x8000 {
+            return Err(invalid_nsf("load address below 0x8000"));
+        }
+        Ok(file)
+    }
+
+    fn parse_nsf(data: &[u8]) -> Result<NsfFile> {
+        if data.len() < NSF_HEADER_SIZE {
+            return Err(invalid_nsf("truncated header"));
+        }
+        let mut initial_banks = [0; 8];
+        initial_banks.copy_from_slice(&data[0x70..0x78]);
+        Ok(NsfFile {
+            title: string(&data[0x0E..0x2E]),
+            artist: string(&data[0x2E..0x4E]),
+            copyright: string(&data[0x4E..0x6E]),
+            song_count: data[0x06],
+            starting_song: data[0x07].saturating_sub(1),
+            load_address: word(data, 0x08),
+            init_address: word(data, 0x0A),
+            play_address: word(data, 0x0C),
+            play_period_us: word(data, 0x6E),
+            initial_banks,
+            pal: data[0x7A] & 0x03 == 0x01,
+            expansion_chips: data[0x7B],
+            track_names: Vec::new(),
+            track_lengths: Vec::new(),
+            data: data[NSF_HEADER_SIZE..].to_vec(),
+        })
+    }
+
+    /// Parses the chunks of an NSFe file. Unknown chunks are skipped unless their ID starts with
+    /// an uppercase letter, which marks chunks that are needed to play the file.
+    fn parse_nsfe(data: &[u8]) -> Result<NsfFile> {
+        let mut file = NsfFile::default();
+        let mut has_info = false;
+        let mut has_data = false;
+        let mut position = NSFE_MAGIC.len();
+        loop {
+            let header = data.get(position..position + 8).ok_or_else(|| invalid_nsf("missing NEND chunk"))?;
+            let length = u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as usize;
+            let id = &header[4..8];
+            let chunk = data.get(position + 8..position + 8 + length).ok_or_else(|| invalid_nsf("truncated chunk"))?;
+            position += 8 + length;
+            match id {
+                b"INFO" => {
+                    if chunk.len() < 8 {
+                        return Err(invalid_nsf("truncated INFO chunk"));
+                    }
+                    file.load_address = word(chunk, 0);
+                    file.init_address = word(chunk, 2);
+                    file.play_address = word(chunk, 4);
+                    file.pal = chunk[6] & 0x03 == 0x01;
+                    file.expansion_chips = chunk[7];
+                    file.song_count = chunk.get(8).copied().unwrap_or(1);
+                    file.starting_song = chunk.get(9).copied().unwrap_or(0);
+                    has_info = true;
+                },
+                b"DATA" => {
+                    file.data = chunk.to_vec();
+                    has_data = true;
+                },
+                b"BANK" => {
+                    let len = chunk.len().min(8);
+                    file.initial_banks[..len].copy_from_slice(&chunk[..len]);
+                },
+                b"RATE" if chunk.len() >= 2 => file.play_period_us = word(chunk, 0),
+                b"auth" => {
+                    let mut strings = chunk.split(|&byte| byte == 0).map(string);
+                    file.title = strings.next().unwrap_or_default();
+                    file.artist = strings.next().unwrap_or_default();
+                    file.copyright = strings.next().unwrap_or_default();
+                },
+                b"tlbl" => {
+                    let names = chunk.strip_suffix(&[0]).unwrap_or(chunk);
+                    file.track_names = names.split(|&byte| byte == 0).map(string).collect();
+                },
+                b"time" => {
+                    file.track_lengths = chunk.chunks_exact(4)
+                        .map(|time| i32::from_le_bytes([time[0], time[1], time[2], time[3]]))
+                        .map(|ms| if ms < 0 { None } else { Some(Duration::from_millis(ms as u64)) })
+                        .collect();
+                },
+                b"NEND" => break,
+                id if id[0].is_ascii_uppercase() => {
+                    return Err(invalid_nsf(&format!("unsupported chunk {}", String::from_utf8_lossy(id))));
+                },
+                _ => (),
+            }
+        }
+        if !has_info || !has_data {
+            return Err(invalid_nsf("missing INFO or DATA chunk"));
+        }
+        Ok(file)
+    }
+
+    /// Returns true if the file uses the bank registers at 0x5FF8..=0x5FFF.
+    pub fn is_bankswitched(&self) -> bool {
+        self.initial_banks.iter().any(|&bank| bank != 0)
+    }
+
+    /// Returns the values written to the bank registers when a song starts.
+    ///
+    /// Files without bankswitching see their data at the load address through banks 0..=7.
+    pub fn bank_register_values(&self) -> [u8; 8] {
+        if self.is_bankswitched() {
+            self.initial_banks
+        } else {
+            [0, 1, 2, 3, 4, 5, 6, 7]
+        }
+    }
+
+    /// Returns the program data arranged in 4 KiB banks.
+    ///
+    /// With bankswitching, bank 0 starts at the 4 KiB boundary below the load address.
+    /// Otherwise the data is placed at the load address of a 32 KiB image of 0x8000..=0xFFFF.
+    pub fn prg_rom(&self) -> Vec<u8> {
+        let padding = if self.is_bankswitched() {
+            self.load_address as usize % BANK_SIZE
+        } else {
+            self.load_address as usize - 0x8000
+        };
+        let mut prg_rom = vec![0; padding];
+        prg_rom.extend_from_slice(&self.data);
+        let size = if self.is_bankswitched() {
+            prg_rom.len().div_ceil(BANK_SIZE) * BANK_SIZE
+        } else {
+            0x8000
+        };
+        prg_rom.resize(size, 0);
+        prg_rom
+    }
+}
+
+fn word(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+/// Reads a null-terminated string.
+fn string(data: &[u8]) -> String {
+    let end = data.iter().position(|&byte| byte == 0).unwrap_or(data.len());
+    String::from_utf8_lossy(&data[..end]).into_owned()
+}
+
+fn invalid_nsf(message: &str) -> Error {
+    Error::InvalidNsf(message.to_owned())
+}
+
+/// Calls the INIT and PLAY routines of an NSF file on the CPU.
+///
+/// The routines are called like JSR would call them, with a return address that points to an idle loop
+/// provided by the NSF mapper. A PLAY call is made once the play period has passed and the previous call has returned.
+pub(crate) struct NsfPlayer {
+    file: NsfFile,
+    song: u8,
+    cycles_per_play: u32,
+    cycles_until_play: u32,
+    play_pending: bool,
+}
+
+impl NsfPlayer {
+    pub fn new(file: NsfFile) -> NsfPlayer {
+        let play_period_us = match file.play_period_us {
+            0 => DEFAULT_PLAY_PERIOD_US,
+            period => period,
+        };
+        let cycles_per_play = (play_period_us as u64 * CPU_CLOCK_RATE as u64 / 1_000_000) as u32;
+        NsfPlayer {
+            song: file.starting_song,
+            file,
+            cycles_per_play,
+            cycles_until_play: cycles_per_play,
+            play_pending: false,
+        }
+    }
+
+    pub fn file(&self) -> &NsfFile {
+        &self.file
+    }
+
+    pub fn song(&self) -> u8 {
+        self.song
+    }
+
+    /// Resets the sound hardware and calls INIT for a song, counting from 0.
+    ///
+    /// The CPU must be at an instruction boundary.
+    pub fn start_song(&mut self, cpu: &mut Cpu, song: u8) {
+        self.song = song;
+        for address in (0x0000..0x0800).chain(0x6000..0x8000) {
+            cpu.bus.write(address, 0);
+        }
+        for address in 0x4000..=0x4013 {
+            cpu.bus.write(address, 0);
+        }
+        cpu.bus.write(0x4015, 0x00);
+        cpu.bus.write(0x4015, 0x0F);
+        cpu.bus.write(0x4017, 0x40);
+        for (address, bank) in (0x5FF8..=0x5FFF).zip(self.file.bank_register_values().iter()) {
+            cpu.bus.write(address, *bank);
+        }
+
+        cpu.stack_pointer = 0xFD;
+        cpu.status.interrupt = true;
+        cpu.accumulator = song;
+        cpu.x_index = 0; // NTSC
+        cpu.call_subroutine(self.file.init_address, IDLE_LOOP_ADDRESS);
+        self.cycles_until_play = self.cycles_per_play;
+        self.play_pending = false;
+    }
+
+    /// Counts the play period and calls PLAY when it is due. Should be called before every CPU cycle.
+    pub fn step(&mut self, cpu: &mut Cpu) {
+        self.cycles_until_play -= 1;
+        if self.cycles_until_play == 0 {
+            self.cycles_until_play = self.cycles_per_play;
+            self.play_pending = true;
+        }
+        if self.play_pending && cpu.is_at_instruction_boundary() && cpu.program_counter == IDLE_LOOP_ADDRESS {
+            self.play_pending = false;
+            cpu.call_subroutine(self.file.play_address, IDLE_LOOP_ADDRESS);
+        }
+    }
+
+    /// Returns true if a play period has just started.
+    pub fn is_new_period(&self) -> bool {
+        self.cycles_until_play == self.cycles_per_play
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::result::Result;
+
+    fn nsf_header() -> Vec<u8> {
+        let mut header = b"NESM\x1a\x01\x03\x02\x00\x80\x00\x80\x03\x80".to_vec();
+        header.resize(NSF_HEADER_SIZE, 0);
+        header[0x0E..0x12].copy_from_slice(b"Song");
+        header[0x6E..0x70].copy_from_slice(&16639u16.to_le_bytes());
+        header
+    }
+
+    #[test]
+    fn test_parse_nsf() -> Result<(), std::io::Error> {
+        let mut data = nsf_header();
+        data.extend_from_slice(&[0x60, 0x60, 0x60, 0x60]);
+        let file = NsfFile::parse(&data).unwrap();
+        assert_eq!(file.title, "Song");
+        assert_eq!((file.song_count, file.starting_song), (3, 1));
+        assert_eq!((file.load_address, file.init_address, file.play_address), (0x8000, 0x8000, 0x8003));
+        assert!(!file.is_bankswitched());
+        assert_eq!(file.prg_rom().len(), 0x8000);
+
+        // Bankswitched data starts at the offset of the load address in its bank
+        data[0x08..0x0A].copy_from_slice(&0x8123u16.to_le_bytes());
+        data[0x71] = 1;
+        let file = NsfFile::parse(&data).unwrap();
+        assert_eq!(file.bank_register_values(), [0, 1, 0, 0, 0, 0, 0, 0]);
+        let prg_rom = file.prg_rom();
+        assert_eq!(prg_rom.len(), BANK_SIZE);
+        assert_eq!(prg_rom[0x123], 0x60);
+
+        assert!(matches!(NsfFile::parse(&data[..0x40]), Err(Error::InvalidNsf(_))));
+        match NsfFile::parse(&data[..NSF_HEADER_SIZE]) {
+            Err(Error::InvalidNsf(message)) => assert_eq!(message, "no program data"),
+            _ => panic!("NSF without program data should be invalid"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_nsfe() -> Result<(), std::io::Error> {
+        let mut data = NSFE_MAGIC.to_vec();
+        let mut chunk = |id: &[u8], contents: &[u8]| {
+            data.extend_from_slice(&(contents.len() as u32).to_le_bytes());
+            data.extend_from_slice(id);
+            data.extend_from_slice(contents);
+        };
+        chunk(b"INFO", &[0x00, 0x80, 0x00, 0x80, 0x03, 0x80, 0x00, 0x00, 0x02, 0x00]);
+        chunk(b"DATA", &[0x60; 4]);
+        chunk(b"auth", b"Title\0Artist\0Copyright\0Ripper\0");
+        chunk(b"tlbl", b"First\0Second\0");
+        chunk(b"time", &[0x10, 0x27, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF]);
+        chunk(b"text", b"Skipped");
+        chunk(b"NEND", &[]);
+        let file = NsfFile::parse(&data).unwrap();
+        assert_eq!((file.title.as_str(), file.artist.as_str()), ("Title", "Artist"));
+        assert_eq!(file.song_count, 2);
+        assert_eq!(file.track_names, ["First", "Second"]);
+        assert_eq!(file.track_lengths, [Some(Duration::from_secs(10)), None]);
+        assert_eq!(file.data, [0x60; 4]);
+
+        let mut data = NSFE_MAGIC.to_vec();
+        data.extend_from_slice(&[0, 0, 0, 0]);
+        data.extend_from_slice(b"VRC7");
+        assert!(matches!(NsfFile::parse(&data), Err(Error::InvalidNsf(_))));
+        Ok(())
+    }
+}
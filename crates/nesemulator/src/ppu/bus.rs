@@ -20,19 +20,37 @@ impl Bus {
     }
 
     pub fn read(&self, address: u16) -> u8 {
+        self.set_address(address);
+        self.peek(address)
+    }
+
+    /// Puts an address on the bus without reading or writing.
+    ///
+    /// Mappers watching the PPU address bus, such as MMC3, see changes of the VRAM address made through PPUADDR and PPUDATA.
+    pub fn set_address(&self, address: u16) {
+        self.cartridge.borrow_mut().notify_ppu_address(address & 0x3FFF); // The PPU address bus is 14 bits wide
+    }
+
+    /// Reads a value without the side effects of a real bus access.
+    ///
+    /// Useful for debug views that should not disturb mappers watching the PPU address bus.
+    pub fn peek(&self, address: u16) -> u8 {
+        let address = address & 0x3FFF; // The PPU address bus is 14 bits wide
         let cartridge = self.cartridge.borrow();
         match address {
             // TODO: move this (or at least 0x0000-0x2FFF) logic inside cartridge or mappers
             0x0000..=0x1FFF => cartridge.read_from_pattern_table(address), // Pattern table 0..1
             0x2000..=0x2FFF => cartridge.read_from_nametable(address, &self.vram), // Nametable 0..3
-            0x3000..=0x3EFF => unimplemented!(), // TODO Mirrors of $2000-$2EFF
+            0x3000..=0x3EFF => cartridge.read_from_nametable(address - 0x1000, &self.vram), // Mirrors of $2000-$2EFF
             0x3F00..=0x3F1F => self.read_from_palette_ram(address - 0x3F00), // Palette RAM
             0x3F20..=0x3FFF => self.read_from_palette_ram(address - 0x3F00), // Palette RAM mirror
-            _ => panic!("PPU bus: unknown address {:#x}", address),
+            _ => unreachable!(),
         }
     }
 
     pub fn write(&mut self, address: u16, value: u8) {
+        let address = address & 0x3FFF; // The PPU address bus is 14 bits wide
+        self.cartridge.borrow_mut().notify_ppu_address(address);
         match address {
             0x0000..=0x1FFF => {
                 let mut cartridge = self.cartridge.borrow_mut();
@@ -42,7 +60,7 @@ impl Bus {
             // TODO: check these addresses and mirroring from documentations related to PPUDATA
             0x3000..=0x3EFF => self.write_name_table(address - 0x1000, value),
             0x3F00..=0x3FFF => self.write_to_palette_ram(address - 0x3F00, value),
-            _ => panic!("PPU bus: unknown address {:#x}", address),
+            _ => unreachable!(),
         }
     }
 
@@ -75,12 +93,10 @@ impl Bus {
         }
     }
 
+    /// Writes to nametables 0..3 using an address in range 0x2000..=0x2FFF.
     fn write_name_table(&mut self, address: u16, value: u8) {
-        let cartridge = self.cartridge.borrow();
-        match address {
-            0x2000..=0x2FFF => cartridge.write_to_nametable(address, &mut self.vram, value), // Nametable 0..3
-            _ => panic!("PPU bus: should be called with address of range 0x2000..=0x2FFF. Was called with {:#x}", address),
-        }
+        debug_assert!((0x2000..=0x2FFF).contains(&address));
+        self.cartridge.borrow_mut().write_to_nametable(address, &mut self.vram, value);
     }
 
     pub fn write_to_vram(&mut self, address: u16, value: u8) {
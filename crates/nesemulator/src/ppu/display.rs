@@ -37,7 +37,7 @@ impl Default for Display {
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Default)]
 pub struct Color {
     pub r: u8,
     pub g: u8,
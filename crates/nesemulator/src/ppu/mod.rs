@@ -13,8 +13,18 @@ use crate::ppu::shift_register::ShiftRegister;
 const MASK_STATUS_OVERFLOW: u8 = 0b0010_0000;
 const MASK_CONTROLLER_BACKGROUND_PATTERN_TABLE_ADDRESS: u8 = 0b0001_0000;
 const MASK_CONTROLLER_SPRITE_PATTERN_TABLE_ADDRESS: u8 = 0b0000_1000;
+const MASK_CONTROLLER_SPRITE_SIZE: u8 = 0b0010_0000;
 const MASK_FLIP_SPRITE_HORIZONTALLY: u8 = 0b0100_0000;
 const MASK_FLIP_SPRITE_VERTICALLY: u8 = 0b1000_0000;
+const MASK_SPRITE_BEHIND_BACKGROUND: u8 = 0b0010_0000;
+const MASK_GREYSCALE: u8 = 0b0000_0001;
+const MASK_SHOW_BACKGROUND_LEFT: u8 = 0b0000_0010;
+const MASK_SHOW_SPRITES_LEFT: u8 = 0b0000_0100;
+const MASK_SHOW_BACKGROUND: u8 = 0b0000_1000;
+const MASK_SHOW_SPRITES: u8 = 0b0001_0000;
+const MASK_EMPHASIS: u8 = 0b1110_0000;
+/// Frames after which a bit of the I/O latch that has not been refreshed decays to 0. About 600 ms.
+const IO_LATCH_DECAY_FRAMES: u8 = 36;
 
 
 pub struct Ppu {
@@ -28,7 +38,6 @@ pub struct Ppu {
     pub ppustatus: u8,
     pub ppuscroll: u8,
     pub oamaddr: u8,
-    pub oamdata: u8,
     _oamdma: u8,
     pub x: u16,
     pub y: u16,
@@ -37,6 +46,12 @@ pub struct Ppu {
     palette: Palette,
     pub nmi_occurred: bool,
     pub nmi_output: bool,
+    /// State of the NMI line when the CPU last looked at it. The CPU reacts to its rising edge.
+    nmi_line_previous: bool,
+    /// Set when PPUSTATUS is read one clock before vertical blank starts, which keeps the flag from being set.
+    vblank_suppressed: bool,
+    /// Every other frame is one clock shorter when rendering is enabled.
+    odd_frame: bool,
     ppudata_buffer: u8,
     shift_attribute_l: ShiftRegister,
     shift_attribute_h: ShiftRegister,
@@ -63,6 +78,17 @@ pub struct Ppu {
     oam_counters: [u8; 8],
     oam_sprite_fetched_y: u8,
     oam_sprite_fetched_tile_index: u8,
+    /// True if sprite evaluation copied OAM entry 0 to the first slot of secondary OAM.
+    sprite_0_on_next_scanline: bool,
+    /// True if the first sprite slot of the scanline being drawn holds OAM entry 0.
+    sprite_0_on_scanline: bool,
+    /// The I/O data latch between the CPU and the PPU. Reads of write-only registers return it.
+    io_latch: u8,
+    /// Frames left until each bit of the I/O latch decays.
+    io_latch_frames_left: [u8; 8],
+    /// Set by the Reset button. Writes to PPUCTRL, PPUMASK, PPUSCROLL and PPUADDR are ignored
+    /// until the pre-render scanline.
+    reset_write_lock: bool,
 }
 
 impl Ppu {
@@ -78,7 +104,6 @@ impl Ppu {
             ppustatus: 0xA0,
             ppuscroll: 0,
             oamaddr: 0,
-            oamdata: 0,
             _oamdma: 0,
             x: 0,
             y: 0,
@@ -86,7 +111,10 @@ impl Ppu {
             display: Default::default(),
             palette: Palette::new(),
             nmi_occurred: false,
-            nmi_output: true,
+            nmi_output: false,
+            nmi_line_previous: false,
+            vblank_suppressed: false,
+            odd_frame: false,
             ppudata_buffer: 0,
             shift_attribute_l: ShiftRegister::new(1),
             shift_attribute_h: ShiftRegister::new(1),
@@ -113,9 +141,33 @@ impl Ppu {
             oam_counters: [0; 8],
             oam_sprite_fetched_y: 0,
             oam_sprite_fetched_tile_index: 0,
+            sprite_0_on_next_scanline: false,
+            sprite_0_on_scanline: false,
+            io_latch: 0,
+            io_latch_frames_left: [0; 8],
+            reset_write_lock: false,
         }
     }
 
+    /// Handles the Reset button.
+    ///
+    /// PPUCTRL, PPUMASK, the scroll position, the write toggle and the PPUDATA read buffer are cleared.
+    /// VRAM, OAM, palette RAM and the VRAM address keep their values.
+    ///
+    /// Useful links:
+    /// [Nesdev wiki - PPU power up state]
+    ///
+    /// [Nesdev wiki - PPU power up state]: https://www.nesdev.org/wiki/PPU_power_up_state
+    pub fn reset(&mut self) {
+        self.write_ppuctrl(0);
+        self.ppumask = 0;
+        self.t = 0;
+        self.fine_x_scroll = 0;
+        self.w = false;
+        self.ppudata_buffer = 0;
+        self.reset_write_lock = true;
+    }
+
     fn get_coarse_x_scroll(&self) -> u8 {
         (self.v & 0b0001_1111) as u8
     }
@@ -179,7 +231,7 @@ impl Ppu {
     }
 
     fn rendering_enabled(&self) -> bool {
-        (self.ppumask & 0x18) > 0
+        self.ppumask & (MASK_SHOW_BACKGROUND | MASK_SHOW_SPRITES) != 0
     }
 
     pub fn write_ppuscroll(&mut self, value: u8) {
@@ -203,11 +255,104 @@ impl Ppu {
             self.t &= 0xff00;
             self.t |= value as u16;
             self.v = self.t;
+            self.bus.set_address(self.v);
         }
         self.w = !self.w;
     }
 
+    /// Reads a PPU register using a CPU bus address in range 0x2000..=0x2007.
+    ///
+    /// The value goes through the I/O latch. Registers that drive only some bits of the data bus
+    /// refresh those bits of the latch, and the other bits come from the latch.
+    /// Write-only registers return the latch as it is.
+    ///
+    /// Useful links:
+    /// [Nesdev wiki - PPU open bus]
+    ///
+    /// [Nesdev wiki - PPU open bus]: https://www.nesdev.org/wiki/Open_bus_behavior#PPU_open_bus
+    pub fn read_register(&mut self, address: u16) -> u8 {
+        match address {
+            0x2002 => {
+                let status = self.read_ppustatus();
+                self.refresh_io_latch(status, 0xE0);
+            },
+            0x2004 => {
+                let value = self.read_oamdata();
+                self.refresh_io_latch(value, 0xFF);
+            },
+            0x2007 => {
+                // Palette entries are 6 bits
+                let palette = (0x3F00..=0x3FFF).contains(&(self.v & 0x3FFF));
+                let value = self.read_ppudata();
+                self.refresh_io_latch(value, if palette { 0x3F } else { 0xFF });
+            },
+            0x2000 | 0x2001 | 0x2003 | 0x2005 | 0x2006 => (), // Write-only
+            _ => unreachable!("PPU: unknown register {:#06X}", address),
+        }
+        self.io_latch
+    }
+
+    /// Writes a PPU register using a CPU bus address in range 0x2000..=0x2007. Every write refreshes the I/O latch.
+    pub fn write_register(&mut self, address: u16, value: u8) {
+        self.refresh_io_latch(value, 0xFF);
+        if self.reset_write_lock && matches!(address, 0x2000 | 0x2001 | 0x2005 | 0x2006) {
+            return;
+        }
+        match address {
+            0x2000 => self.write_ppuctrl(value),
+            0x2001 => self.ppumask = value,
+            0x2002 => (), // Read-only
+            0x2003 => self.oamaddr = value,
+            0x2004 => self.write_oamdata(value),
+            0x2005 => self.write_ppuscroll(value),
+            0x2006 => self.write_ppuaddr(value),
+            0x2007 => self.write_ppudata(value),
+            _ => unreachable!("PPU: unknown register {:#06X}", address),
+        }
+    }
+
+    /// Returns the I/O latch, the value that reads of write-only registers return.
+    pub fn io_latch(&self) -> u8 {
+        self.io_latch
+    }
+
+    /// Sets the bits of the I/O latch given by the mask and restarts their decay.
+    fn refresh_io_latch(&mut self, value: u8, mask: u8) {
+        self.io_latch = (self.io_latch & !mask) | (value & mask);
+        for bit in 0..8 {
+            if mask & (1 << bit) != 0 {
+                self.io_latch_frames_left[bit] = IO_LATCH_DECAY_FRAMES;
+            }
+        }
+    }
+
+    /// Decays the bits of the I/O latch that have not been refreshed for a while. Called once per frame.
+    fn decay_io_latch(&mut self) {
+        for bit in 0..8 {
+            match self.io_latch_frames_left[bit] {
+                0 => (),
+                1 => {
+                    self.io_latch_frames_left[bit] = 0;
+                    self.io_latch &= !(1 << bit);
+                },
+                _ => self.io_latch_frames_left[bit] -= 1,
+            }
+        }
+    }
+
+    /// Reads PPUSTATUS, clearing the vblank flag and the write toggle.
+    ///
+    /// Reading it one clock before vertical blank starts returns the flag clear and keeps it from being set,
+    /// so no NMI occurs that frame. See [`Ppu::is_nmi`] for reads right after the flag is set.
+    ///
+    /// Useful links:
+    /// [Nesdev wiki - PPU frame timing]
+    ///
+    /// [Nesdev wiki - PPU frame timing]: https://www.nesdev.org/wiki/PPU_frame_timing#VBL_Flag_Timing
     pub fn read_ppustatus(&mut self) -> u8 {
+        if self.y == 241 && self.x == 1 {
+            self.vblank_suppressed = true;
+        }
         let status = self.ppustatus;
         self.clear_vblank();
         self.nmi_occurred = false; // and this variable are kinda the same.
@@ -244,13 +389,14 @@ impl Ppu {
     pub fn read_ppudata(&mut self) -> u8 {
         let mut result = self.ppudata_buffer;
         self.ppudata_buffer = self.bus.read(self.v);
-        if (0x3F00..=0x3FFF).contains(&self.v) {
+        if (0x3F00..=0x3FFF).contains(&(self.v & 0x3FFF)) {
             // Update the result with the newly updated buffer value that contains value from palette.
             result = self.ppudata_buffer;
             // Update the buffer with a value from VRAM address-0x1000
             self.ppudata_buffer = self.bus.read(self.v - 0x1000);
         }
         self.v += self.get_vram_address_increment();
+        self.bus.set_address(self.v);
         result
     }
 
@@ -258,6 +404,7 @@ impl Ppu {
     pub fn write_ppudata(&mut self, value: u8) {
         self.bus.write(self.v, value);
         self.v += self.get_vram_address_increment();
+        self.bus.set_address(self.v);
     }
 
     pub fn write_ppuctrl(&mut self, value: u8) {
@@ -275,31 +422,95 @@ impl Ppu {
         }
     }
 
+    /// Returns the state of the NMI output: the vblank flag combined with PPUCTRL bit 7.
+    ///
+    /// The CPU notices the NMI a few clocks after the flag is set. Reading PPUSTATUS or clearing PPUCTRL bit 7
+    /// in that window clears the line before the CPU sees it, which suppresses the NMI of that frame.
+    fn nmi_line(&self) -> bool {
+        let nmi_delay = self.y == 241 && self.x <= 3;
+        self.nmi_occurred && self.nmi_output && !nmi_delay
+    }
+
+    /// Returns true if the NMI line has risen since the last call. Called by the CPU every cycle.
+    ///
+    /// The line rises when vertical blank starts with NMI enabled,
+    /// and also when NMI is enabled in PPUCTRL while the vblank flag is still set.
     pub fn is_nmi(&mut self) -> bool {
-        // TODO: set some flag off? Is it needed?
-        let nmi_occurred = self.nmi_occurred;
-        self.nmi_occurred = false;
-        nmi_occurred && self.nmi_output
+        let nmi_line = self.nmi_line();
+        let rising_edge = nmi_line && !self.nmi_line_previous;
+        self.nmi_line_previous = nmi_line;
+        rising_edge
     }
 
     pub fn write_oamdma(&mut self, value: u8) {
+        self.write_oam(value);
+    }
+
+    /// Writes a byte to OAM at OAMADDR and increments OAMADDR.
+    ///
+    /// Bits 2-4 of the attribute byte do not exist in OAM, so they are always 0.
+    fn write_oam(&mut self, value: u8) {
+        let value = if self.oamaddr & 0x03 == 2 { value & 0xE3 } else { value };
         self.oam_primary[self.oamaddr as usize] = value;
         self.oamaddr = self.oamaddr.wrapping_add(1);
     }
 
+    /// Returns true while the PPU is reading OAM for rendering.
+    fn is_rendering_sprites(&self) -> bool {
+        self.rendering_enabled() && (self.y <= 239 || self.y == 261)
+    }
+
+    /// Reads OAMDATA.
+    ///
+    /// Outside rendering this is the OAM byte at OAMADDR. During rendering OAM is busy with sprite evaluation,
+    /// and the read returns the value on the internal OAM bus instead.
+    ///
+    /// Useful links:
+    /// [Nesdev wiki - OAMDATA]
+    /// [Nesdev wiki - PPU sprite evaluation]
+    ///
+    /// [Nesdev wiki - OAMDATA]: https://www.nesdev.org/wiki/PPU_registers#OAMDATA
+    /// [Nesdev wiki - PPU sprite evaluation]: https://www.nesdev.org/wiki/PPU_sprite_evaluation
+    pub fn read_oamdata(&self) -> u8 {
+        if !self.is_rendering_sprites() {
+            return self.oam_primary[self.oamaddr as usize];
+        }
+        match self.x {
+            1..=64 => 0xFF, // Secondary OAM clear reads 0xFF
+            65..=256 => self.oam_temp_value,
+            257..=320 => {
+                // Y, tile index, attributes and then X until the next sprite
+                let sprite_i = ((self.x - 257) / 8) as usize;
+                let byte = ((self.x - 257) % 8).min(3) as usize;
+                self.oam_secondary[sprite_i * 4 + byte]
+            },
+            _ => self.oam_secondary[0],
+        }
+    }
+
+    /// Writes OAMDATA.
+    ///
+    /// During rendering the write does not reach OAM, but it bumps the upper 6 bits of OAMADDR.
+    pub fn write_oamdata(&mut self, value: u8) {
+        if self.is_rendering_sprites() {
+            self.oamaddr = self.oamaddr.wrapping_add(4);
+        } else {
+            self.write_oam(value);
+        }
+    }
+
     pub fn step(&mut self) {
         self.cycle();
     }
 
     pub fn cycle(&mut self) {
         if self.y == 241 && self.x == 1 {
-            self.set_vblank();
+            self.decay_io_latch();
         }
 
         if self.y == 261 && self.x == 1 {
-            self.clear_vblank();
-            self.ppustatus &= 0b1110_1111;
-            // TODO: clear sprite 0 according to the timing chart.
+            self.ppustatus &= !MASK_STATUS_OVERFLOW;
+            self.reset_write_lock = false;
         }
 
         match self.y {
@@ -322,6 +533,25 @@ impl Ppu {
         self.increase_x();
         if self.x == 0 {
             self.increase_y();
+            if self.y == 0 {
+                self.start_frame();
+            }
+        }
+    }
+
+    /// Called when the PPU moves to the first dot of a frame.
+    ///
+    /// On odd frames the idle first dot is skipped if rendering is enabled,
+    /// which makes the frame one PPU clock shorter.
+    ///
+    /// Useful links:
+    /// [Nesdev wiki - PPU frame timing]
+    ///
+    /// [Nesdev wiki - PPU frame timing]: https://www.nesdev.org/wiki/PPU_frame_timing#Even.2FOdd_Frames
+    fn start_frame(&mut self) {
+        self.odd_frame = !self.odd_frame;
+        if self.odd_frame && self.rendering_enabled() {
+            self.x = 1;
         }
     }
 
@@ -339,8 +569,11 @@ impl Ppu {
         let vblank_end = self.y == 261 && self.x == 1;
 
         if vblank_start {
-            self.nmi_occurred = true;
-            self.set_vblank();
+            if !self.vblank_suppressed {
+                self.nmi_occurred = true;
+                self.set_vblank();
+            }
+            self.vblank_suppressed = false;
         }
         else if vblank_end {
             self.nmi_occurred = false;
@@ -405,7 +638,7 @@ impl Ppu {
 
         // Fetch background stuff
         match self.x {
-            0 => (), // TODO: is this ok? x: 0 y: 0 should be skipped every odd cycle?
+            0 => (), // Idle. Skipped on the first scanline of odd frames when rendering is enabled
             1..=256 => {
                 self.shift_registers();
                 self.fetch_match();
@@ -482,6 +715,7 @@ impl Ppu {
             self.oam_secondary_write_lock = false;
             self.oam_copying_sprite = false;
             self.oam_overflow_reads_left = 0;
+            self.sprite_0_on_next_scanline = false;
         }
 
         if self.oam_primary_n == 64 {
@@ -520,7 +754,7 @@ impl Ppu {
                 // Secondary OAM full
                 self.oam_secondary_write_lock = true;
                 let sprite_y = self.oam_temp_value as u16;
-                let y_in_range = (sprite_y..sprite_y+8).contains(&self.y);
+                let y_in_range = (sprite_y..sprite_y + self.sprite_height()).contains(&self.y);
                 if y_in_range {
                     self.ppustatus |= MASK_STATUS_OVERFLOW;
                 }
@@ -538,8 +772,11 @@ impl Ppu {
             }
 
             let y = self.oam_temp_value as u16;
-            let y_in_range = (y + 1..y+9).contains(&self.next_y());
+            let y_in_range = (y + 1..y + 1 + self.sprite_height()).contains(&self.next_y());
             if y_in_range {
+                if self.oam_primary_n == 0 {
+                    self.sprite_0_on_next_scanline = true;
+                }
                 self.oam_copying_sprite = true;
                 self.oam_primary_m += 1;
             }
@@ -554,6 +791,9 @@ impl Ppu {
         debug_assert!((0..=239).contains(&self.y) || self.y == 261);
         let step = ((self.x - 257) % 8) + 1;
         let sprite_i = ((self.x - 257)/8) as usize;
+        if self.x == 257 {
+            self.sprite_0_on_scanline = self.sprite_0_on_next_scanline;
+        }
         match step {
             1 => { // Read y-coordinate
                 self.oam_sprite_fetched_y = self.oam_secondary[sprite_i * 4]
@@ -567,16 +807,15 @@ impl Ppu {
             4 => { // Read x-coordinate
                 self.oam_counters[sprite_i] = self.oam_secondary[sprite_i * 4 + 3];
             },
-            5 => {
+            5 if self.rendering_enabled() => {
                 self.oam_pattern_low[sprite_i] = self.fetch_sprite_tile_byte(
                     sprite_i, false);
             },
-            6 => (),
-            7 => {
+            7 if self.rendering_enabled() => {
                 self.oam_pattern_high[sprite_i] = self.fetch_sprite_tile_byte(
                     sprite_i, true);
             },
-            8 => (),
+            5..=8 => (),
             _ => unreachable!(),
         }
     }
@@ -585,28 +824,26 @@ impl Ppu {
         debug_assert!((257..=320).contains(&self.x) && (((self.x - 257) % 8) + 1 == 5 || ((self.x - 257) % 8) + 1 == 7));
         debug_assert!((0..=239).contains(&self.y) || self.y == 261);
         let sprite_y = self.oam_sprite_fetched_y;
-        if (0xef..=0xff).contains(&sprite_y) {
-            return 0; // Hide the sprite
-        }
+        let sprite_y_fixed = sprite_y as u16 + 1;
+        let sprite_height = self.sprite_height();
         let next_y = self.next_y();
-        if !(sprite_y as u16 + 1..sprite_y as u16 + 9).contains(&next_y) {
+        if (0xef..=0xff).contains(&sprite_y) || !(sprite_y_fixed..sprite_y_fixed + sprite_height).contains(&next_y) {
+            // Hide the sprite. Empty sprite slots still fetch the pattern of tile 0xFF,
+            // which is visible to mappers watching the PPU address bus.
+            let address = self.get_sprite_pattern_address(0xFF, 0);
+            self.bus.read(address | ((high_plane as u16) << 3));
             return 0;
         }
-        let mut scanline_y = next_y;
 
         let flip_h = self.oam_latches[sprite_i] & MASK_FLIP_SPRITE_HORIZONTALLY > 0;
         let flip_v = self.oam_latches[sprite_i] & MASK_FLIP_SPRITE_VERTICALLY > 0;
-        let sprite_y_fixed = sprite_y as u16 + 1;
+        let mut row = next_y - sprite_y_fixed;
         if flip_v {
-            scanline_y = 7 - (scanline_y - sprite_y_fixed) + sprite_y_fixed; // Flip vertically
+            row = sprite_height - 1 - row; // Flip vertically
         }
 
-        let mut tile_byte = self.get_sprite_tile_byte(
-            self.oam_sprite_fetched_tile_index,
-            sprite_y_fixed,
-            scanline_y,
-            high_plane
-        );
+        let address = self.get_sprite_pattern_address(self.oam_sprite_fetched_tile_index, row);
+        let mut tile_byte = self.bus.read(address | ((high_plane as u16) << 3)).reverse_bits();
 
         if flip_h {
             tile_byte = tile_byte.reverse_bits(); // Flip horizontally
@@ -614,10 +851,35 @@ impl Ppu {
         tile_byte
     }
 
+    /// Draws a pixel of a visible scanline.
+    ///
+    /// The opaque pixel of the sprite in the lowest slot wins over the other sprites,
+    /// even if it is behind the background. This lets games mask sprites behind a "behind background" sprite.
+    /// The winning sprite pixel is then drawn in front of the background
+    /// unless it has background priority and the background pixel is opaque.
+    ///
+    /// Sprite 0 hit is set when an opaque pixel of OAM entry 0 overlaps an opaque background pixel, except at x=255.
+    /// Pixels clipped from the left 8 pixels by PPUMASK are transparent, so they never hit.
+    ///
+    /// PPUMASK greyscale keeps only the brightness bits of the color number,
+    /// and the emphasis bits select one of the emphasized variations of the palette.
+    ///
+    /// Useful links:
+    /// [Nesdev wiki - PPU sprite priority]
+    /// [Nesdev wiki - Sprite 0 hit]
+    /// [Nesdev wiki - PPUMASK]
+    ///
+    /// [Nesdev wiki - PPU sprite priority]: https://www.nesdev.org/wiki/PPU_sprite_priority
+    /// [Nesdev wiki - Sprite 0 hit]: https://www.nesdev.org/wiki/PPU_OAM#Sprite_zero_hits
+    /// [Nesdev wiki - PPUMASK]: https://www.nesdev.org/wiki/PPU_registers#PPUMASK
     pub fn visible_scanline(&mut self) {
-        let show_sprites = (self.ppumask >> 4) & 1 == 1;
+        let left_column = self.x <= 8;
+        let show_sprites = self.ppumask & MASK_SHOW_SPRITES != 0
+            && (!left_column || self.ppumask & MASK_SHOW_SPRITES_LEFT != 0);
 
         let mut sprite_color_index = 0;
+        let mut sprite_behind_background = false;
+        let mut sprite_0_pixel = false;
         if show_sprites && 1 <= self.x && self.x <= 256 {
             for (i, counter) in self.oam_counters.iter().enumerate() {
                 if *counter != 0 {
@@ -633,12 +895,15 @@ impl Ppu {
                 let attribute = self.oam_latches[i];
                 let palette_number = (attribute & 0x3) + 4;
                 sprite_color_index = (palette_number << 2) + pattern;
+                sprite_behind_background = attribute & MASK_SPRITE_BEHIND_BACKGROUND != 0;
+                sprite_0_pixel = i == 0 && self.sprite_0_on_scanline;
                 break;
             }
         }
 
         if 1 <= self.x && self.x <= 256 {
-            let show_background = (self.ppumask >> 3) & 1 == 1;
+            let show_background = self.ppumask & MASK_SHOW_BACKGROUND != 0
+                && (!left_column || self.ppumask & MASK_SHOW_BACKGROUND_LEFT != 0);
 
             let background_color_index = if show_background {
                 self.get_background_color_index()
@@ -646,21 +911,24 @@ impl Ppu {
                 0
             };
 
-            // Check for sprite 0 hit
-            if show_background && sprite_color_index != 0 && background_color_index != 0 {
+            let background_opaque = background_color_index & 0x03 != 0;
+
+            if sprite_0_pixel && background_opaque && self.x != 256 {
                 self.set_sprite_0_hit();
             }
 
-            // Prioritize sprite over background
-            // TODO implement proper priority
-            let color_index = if sprite_color_index != 0 {
+            let color_index = if sprite_color_index != 0 && !(sprite_behind_background && background_opaque) {
                 sprite_color_index
             } else {
                 background_color_index
             };
 
-            let color_number_in_big_palette = self.bus.read(0x3F00 + color_index as u16);
-            let color =  self.palette.get_color(color_number_in_big_palette as usize);
+            let mut color_number_in_big_palette = self.bus.peek(0x3F00 + color_index as u16) & 0x3F;
+            if self.ppumask & MASK_GREYSCALE != 0 {
+                color_number_in_big_palette &= 0x30;
+            }
+            let emphasis = ((self.ppumask & MASK_EMPHASIS) as usize) << 1;
+            let color = self.palette.get_color(emphasis | color_number_in_big_palette as usize);
 
             self.display.set_pixel(
                 (self.x - 1) as usize,
@@ -737,14 +1005,50 @@ impl Ppu {
         ((self.ppuctrl & MASK_CONTROLLER_BACKGROUND_PATTERN_TABLE_ADDRESS) as u16) << 8
     }
 
-    /// Returns the address of the current [pattern table] for sprites.
+    /// Returns the height of sprites, 8 or 16 pixels, as selected in [PPUCTRL].
     ///
-    /// Current [pattern table] addresses are defined in the register [PPUCTRL].
+    /// [PPUCTRL]: https://wiki.nesdev.com/w/index.php/PPU_registers#PPUCTRL
+    fn sprite_height(&self) -> u16 {
+        if self.ppuctrl & MASK_CONTROLLER_SPRITE_SIZE != 0 {
+            16
+        } else {
+            8
+        }
+    }
+
+    /// Returns the address of the [pattern table] of a sprite with the given tile index.
+    ///
+    /// 8x8 sprites use the [pattern table] selected in [PPUCTRL].
+    /// 8x16 sprites ignore it and take the [pattern table] from bit 0 of the tile index.
     ///
     /// [PPUCTRL]: https://wiki.nesdev.com/w/index.php/PPU_registers#PPUCTRL
     /// [pattern table]: https://wiki.nesdev.com/w/index.php/PPU_pattern_tables
-    fn get_current_sprite_pattern_table_address(&self) -> u16 {
-        ((self.ppuctrl & MASK_CONTROLLER_SPRITE_PATTERN_TABLE_ADDRESS) as u16) << 8
+    fn get_sprite_pattern_table_address(&self, tile_index: u8) -> u16 {
+        if self.sprite_height() == 16 {
+            ((tile_index & 1) as u16) << 12
+        } else {
+            ((self.ppuctrl & MASK_CONTROLLER_SPRITE_PATTERN_TABLE_ADDRESS) as u16) << 8
+        }
+    }
+
+    /// Returns the address of the low plane byte of a sprite's pattern row.
+    ///
+    /// Row counts from the top of the sprite as it is stored in the pattern table, so vertical flipping
+    /// has to be applied before. An 8x16 sprite is made of two tiles: the even tile of the tile index pair
+    /// on top and the odd tile below it.
+    ///
+    /// Useful links:
+    /// [Nesdev wiki - PPU OAM]
+    ///
+    /// [Nesdev wiki - PPU OAM]: https://www.nesdev.org/wiki/PPU_OAM#Byte_1
+    fn get_sprite_pattern_address(&self, tile_index: u8, row: u16) -> u16 {
+        let pattern_table_address = self.get_sprite_pattern_table_address(tile_index);
+        let tile_index = if self.sprite_height() == 16 {
+            (tile_index & 0xFE) | (row >= 8) as u8
+        } else {
+            tile_index
+        };
+        self.get_pattern_table_tile_address(pattern_table_address, tile_index) | (row & 0x07)
     }
 
     /// Returns the next background tile's pattern byte's address.
@@ -769,22 +1073,6 @@ impl Ppu {
         self.latch_background_pattern_high = self.bus.read(address).reverse_bits();
     }
 
-    /// Returns sprite tile byte from current pattern_table when given the pattern index, sprite y, scanline y
-    fn get_sprite_tile_byte(&self, pattern_index: u8, sprite_y: u16, scanline_y: u16, high_plane: bool) -> u8 {
-        let sprite_pattern_table_address = self.get_current_sprite_pattern_table_address();
-        let sprite_address = self.get_pattern_table_tile_address(
-            sprite_pattern_table_address, pattern_index);
-        if (sprite_y..sprite_y+8).contains(&scanline_y) {
-            let pattern_fine_y_offset = scanline_y - sprite_y;
-            let mut sprite_tile_byte_address = sprite_address | pattern_fine_y_offset as u16;
-            if high_plane {
-                sprite_tile_byte_address |= 0x8;
-            }
-            return self.bus.read(sprite_tile_byte_address).reverse_bits()
-        }
-        0
-    }
-
     /// Loads each tile from given pattern table into given display.
     ///
     /// A tile is 8x8 matrix where each cell has value 0..=3.
@@ -797,8 +1085,8 @@ impl Ppu {
             for fine_y_offset in 0x0..=0x7 {
                 for tile_col in 0x0..=0xf {
                     let pattern_address = pattern_table_address | (tile_row << 8) | (tile_col << 4) | fine_y_offset;
-                    let pattern_low = self.bus.read(pattern_address);
-                    let pattern_high = self.bus.read(pattern_address | 0b0000_1000);
+                    let pattern_low = self.bus.peek(pattern_address);
+                    let pattern_high = self.bus.peek(pattern_address | 0b0000_1000);
                     for x in 0..=7 {
                         let shift = 7 - x;
                         let low_bit = (pattern_low >> shift) & 1;
@@ -812,6 +1100,53 @@ impl Ppu {
         }
     }
 
+    /// Loads the 64 sprites in OAM into given display, in 8 rows of 8 sprites.
+    ///
+    /// Each sprite gets an 8x16 cell. 8x8 sprites fill the upper half of the cell and leave the rest black.
+    /// Sprites are drawn with their palettes and flipping. Transparent pixels use the backdrop color.
+    pub fn load_sprites_to_display(&self, display: &mut Display) {
+        debug_assert!(display.height == 128 && display.width == 64);
+        let sprite_height = self.sprite_height();
+        let backdrop = self.palette.get_color((self.bus.peek(0x3F00) & 0x3F) as usize);
+        for (sprite_i, sprite) in self.oam_primary.chunks_exact(4).enumerate() {
+            let tile_index = sprite[1];
+            let attribute = sprite[2];
+            let flip_h = attribute & MASK_FLIP_SPRITE_HORIZONTALLY > 0;
+            let flip_v = attribute & MASK_FLIP_SPRITE_VERTICALLY > 0;
+            let cell_x = (sprite_i % 8) * 8;
+            let cell_y = (sprite_i / 8) * 16;
+            for y in 0..16u16 {
+                let pattern_row = if y >= sprite_height {
+                    None
+                } else if flip_v {
+                    Some(sprite_height - 1 - y)
+                } else {
+                    Some(y)
+                };
+                let (pattern_low, pattern_high) = match pattern_row {
+                    Some(row) => {
+                        let address = self.get_sprite_pattern_address(tile_index, row);
+                        (self.bus.peek(address), self.bus.peek(address | 0b0000_1000))
+                    },
+                    None => (0, 0),
+                };
+                for x in 0..8 {
+                    let shift = if flip_h { x } else { 7 - x };
+                    let pattern = ((pattern_low >> shift) & 1) | (((pattern_high >> shift) & 1) << 1);
+                    let color = match (pattern_row, pattern) {
+                        (None, _) => Color::default(),
+                        (Some(_), 0) => backdrop,
+                        (Some(_), _) => {
+                            let color_address = 0x3F10 | (((attribute & 0x03) as u16) << 2) | pattern as u16;
+                            self.palette.get_color((self.bus.peek(color_address) & 0x3F) as usize)
+                        },
+                    };
+                    display.set_pixel(cell_x + x, cell_y + y as usize, color);
+                }
+            }
+        }
+    }
+
     fn get_current_palette(&mut self) -> Vec<Color> {
         const PALETTE_COUNT: usize = 8;
         const COLORS_IN_PALETTE: usize = 4;
@@ -822,7 +1157,7 @@ impl Ppu {
             let palette_number = color_index / COLORS_IN_PALETTE;
             let color_number = color_index % COLORS_IN_PALETTE;
             let color_address: u16 = ((palette_number as u16) << 2) | color_number as u16;
-            let color_number_in_big_palette = self.bus.read(0x3F00 + color_address as u16);
+            let color_number_in_big_palette = self.bus.peek(0x3F00 + color_address as u16) & 0x3F;
             let color = self.palette.get_color(color_number_in_big_palette as usize);
             colors.push(color);
         }
@@ -853,15 +1188,15 @@ impl Ppu {
 
             for tile_index in 0..(32*30) {
                 let nametable_address  = nametable_address_base + tile_index;
-                let pattern_index = self.bus.read(nametable_address) as u16;
+                let pattern_index = self.bus.peek(nametable_address) as u16;
 
                 let nametable_tile_coarse_y = (nametable_address >> 5) & 0x001f;
                 let nametable_tile_coarse_x = nametable_address & 0x001f;
 
                 for fine_y_offset in 0..=7u16 {
                     let pattern_address = pattern_table_address | (pattern_index << 4) | fine_y_offset as u16;
-                    let pattern_low = self.bus.read(pattern_address);
-                    let pattern_high = self.bus.read(pattern_address | 0b0000_1000);
+                    let pattern_low = self.bus.peek(pattern_address);
+                    let pattern_high = self.bus.peek(pattern_address | 0b0000_1000);
                     for fine_x_offset in 0..=7u16 {
                         let shift = 7 - fine_x_offset;
                         let low_bit = (pattern_low >> shift) & 1;
@@ -875,3 +1210,331 @@ impl Ppu {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::{Cartridge, NametableMirroring};
+    use crate::mapper::{Memory, Mmc3};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    fn step_frames(ppu: &mut Ppu, frames: u32) {
+        for _ in 0..frames * 341 * 262 {
+            ppu.step();
+        }
+    }
+
+    #[test]
+    fn test_vram_address_changes_reach_mapper() -> Result<(), std::io::Error> {
+        let mmc3 = Mmc3::new(Memory::new(vec![0; 0x8000], vec![0; 0x2000]), NametableMirroring::Vertical);
+        let cartridge = Rc::new(RefCell::new(Cartridge::new_with_mapper(Box::new(mmc3))));
+        let mut ppu = Ppu::new(Bus::new(cartridge.clone()));
+        let clock_irq_counter = |ppu: &mut Ppu, registers: &[(u16, u8)]| {
+            {
+                let mut cartridge = cartridge.borrow_mut();
+                cartridge.write_using_cpu_bus_address(0xE000, 0); // Acknowledge IRQ
+                cartridge.write_using_cpu_bus_address(0xE001, 0); // Enable IRQ with latch 0
+                for _ in 0..3 {
+                    cartridge.clock_cpu();
+                }
+            }
+            for &(address, value) in registers {
+                ppu.write_register(address, value);
+            }
+            cartridge.borrow().irq()
+        };
+
+        // Setting v to 0x1000 through PPUADDR raises A12
+        assert!(clock_irq_counter(&mut ppu, &[(0x2006, 0x10), (0x2006, 0x00)]));
+        assert!(!clock_irq_counter(&mut ppu, &[(0x2006, 0x0F), (0x2006, 0xFF)]));
+        // Incrementing v from 0x0FFF to 0x1000 after a PPUDATA access raises A12
+        assert!(clock_irq_counter(&mut ppu, &[(0x2007, 0x00)]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_io_latch() -> Result<(), std::io::Error> {
+        let mut ppu = Ppu::new(Bus::new(Rc::new(RefCell::new(Cartridge::new()))));
+
+        ppu.write_register(0x2003, 0x5A);
+        assert_eq!(ppu.read_register(0x2000), 0x5A);
+        assert_eq!(ppu.read_register(0x2006), 0x5A);
+
+        // PPUSTATUS drives only the upper 3 bits
+        ppu.write_register(0x2001, 0x1F);
+        assert_eq!(ppu.read_register(0x2002) & 0x1F, 0x1F);
+
+        // Palette reads drive only the lower 6 bits
+        ppu.write_register(0x2006, 0x3F);
+        ppu.write_register(0x2006, 0x00);
+        ppu.write_register(0x2007, 0x15);
+        ppu.write_register(0x2006, 0x3F);
+        ppu.write_register(0x2006, 0x00);
+        ppu.write_register(0x2000, 0xC0);
+        assert_eq!(ppu.read_register(0x2007), 0xD5);
+
+        // Bit 14 of v is not on the 14-bit PPU address bus
+        ppu.v = 0x7F00;
+        assert_eq!(ppu.read_register(0x2007), 0xD5);
+
+        // Bits that are not refreshed decay in about 600 ms
+        step_frames(&mut ppu, 30);
+        assert_eq!(ppu.io_latch(), 0xD5);
+        step_frames(&mut ppu, 8);
+        assert_eq!(ppu.io_latch(), 0x00);
+        Ok(())
+    }
+
+    #[test]
+    fn test_8x16_sprites() -> Result<(), std::io::Error> {
+        let mut ppu = Ppu::new(Bus::new(Rc::new(RefCell::new(Cartridge::new()))));
+        // Tile index 0x03 selects tiles 0x02 (top) and 0x03 (bottom) of pattern table 0x1000
+        for row in 0..8 {
+            ppu.bus.write(0x1020 + row, 0xFF);
+            ppu.bus.write(0x1030 + row, 0x0F);
+        }
+        ppu.bus.write(0x3F00, 0x0F);
+        ppu.bus.write(0x3F11, 0x30);
+        ppu.oam_primary[0..4].copy_from_slice(&[9, 0x03, 0x00, 20]);
+        ppu.write_register(0x2000, 0x20);
+        ppu.write_register(0x2001, 0x10);
+        let white = ppu.palette.get_color(0x30);
+        let is_white = |ppu: &Ppu, x: usize, y: usize| ppu.display.get_pixel(x, y)[..2] == [white.r, white.g];
+
+        step_frames(&mut ppu, 2);
+        assert!(is_white(&ppu, 20, 10) && is_white(&ppu, 20, 17));
+        assert!(!is_white(&ppu, 20, 18) && is_white(&ppu, 27, 18) && is_white(&ppu, 27, 25));
+        assert!(!is_white(&ppu, 27, 26));
+
+        // Flipping vertically swaps the tiles
+        ppu.oam_primary[2] = MASK_FLIP_SPRITE_VERTICALLY;
+        step_frames(&mut ppu, 1);
+        assert!(!is_white(&ppu, 20, 10) && is_white(&ppu, 27, 10) && is_white(&ppu, 20, 18));
+
+        let mut sprites = Display::new(64, 128);
+        ppu.load_sprites_to_display(&mut sprites);
+        assert_eq!(sprites.get_pixel(7, 0)[..2], [white.r, white.g]);
+        assert_eq!(sprites.get_pixel(0, 8)[..2], [white.r, white.g]);
+        assert_ne!(sprites.get_pixel(0, 0)[..2], [white.r, white.g]);
+
+        // Palette entries are 6 bits
+        ppu.bus.write(0x3F11, 0x70);
+        ppu.load_sprites_to_display(&mut sprites);
+        assert_eq!(sprites.get_pixel(7, 0)[..2], [white.r, white.g]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sprite_priority_and_sprite_0_hit() -> Result<(), std::io::Error> {
+        let mut ppu = Ppu::new(Bus::new(Rc::new(RefCell::new(Cartridge::new()))));
+        // Tile 0 is opaque and fills the whole background
+        for row in 0..8 {
+            ppu.bus.write(row, 0xFF);
+        }
+        ppu.bus.write(0x3F01, 0x16);
+        ppu.bus.write(0x3F11, 0x30);
+        ppu.bus.write(0x3F15, 0x2A);
+        ppu.write_register(0x2001, 0x1E);
+        let shows = |ppu: &Ppu, x: usize, y: usize, color_number: usize| {
+            let color = ppu.palette.get_color(color_number);
+            ppu.display.get_pixel(x, y)[..2] == [color.r, color.g]
+        };
+        let render_frame = |ppu: &mut Ppu| -> bool {
+            step_frames(ppu, 1);
+            while ppu.y != 240 {
+                ppu.step();
+            }
+            ppu.ppustatus & 0x40 != 0
+        };
+
+        // A behind-background sprite 0 hides the front-priority sprite 1 and still hits
+        ppu.oam_primary[0..8].copy_from_slice(&[9, 0, MASK_SPRITE_BEHIND_BACKGROUND, 20, 9, 0, 0x01, 20]);
+        ppu.oam_primary[8..].fill(0xFF);
+        assert!(render_frame(&mut ppu));
+        assert!(shows(&ppu, 20, 10, 0x16));
+
+        // Only OAM entry 0 hits
+        ppu.oam_primary[0] = 0xFF;
+        assert!(!render_frame(&mut ppu));
+        assert!(shows(&ppu, 20, 10, 0x2A));
+
+        // Never at x=255
+        ppu.oam_primary[0..4].copy_from_slice(&[9, 0, 0, 255]);
+        assert!(!render_frame(&mut ppu));
+        assert!(shows(&ppu, 255, 10, 0x30));
+
+        // Not in the left 8 pixels when they are clipped
+        ppu.oam_primary[0..4].copy_from_slice(&[9, 0, 0, 0]);
+        assert!(render_frame(&mut ppu));
+        ppu.write_register(0x2001, 0x1C);
+        assert!(!render_frame(&mut ppu));
+        ppu.write_register(0x2001, 0x1A);
+        assert!(!render_frame(&mut ppu));
+        Ok(())
+    }
+
+    #[test]
+    fn test_ppumask_clipping_greyscale_and_emphasis() -> Result<(), std::io::Error> {
+        let mut ppu = Ppu::new(Bus::new(Rc::new(RefCell::new(Cartridge::new()))));
+        for row in 0..8 {
+            ppu.bus.write(row, 0xFF);
+        }
+        ppu.bus.write(0x3F00, 0x0F);
+        ppu.bus.write(0x3F01, 0x16);
+        ppu.bus.write(0x3F11, 0x30);
+        ppu.oam_primary[0..4].copy_from_slice(&[9, 0, 0, 0]);
+        ppu.oam_primary[4..].fill(0xFF);
+        let shows = |ppu: &Ppu, x: usize, y: usize, color_index: usize| {
+            let color = ppu.palette.get_color(color_index);
+            ppu.display.get_pixel(x, y)[..2] == [color.r, color.g]
+        };
+
+        // Both clipped in the left 8 pixels
+        ppu.write_register(0x2001, 0x18);
+        step_frames(&mut ppu, 1);
+        assert!(shows(&ppu, 0, 10, 0x0F) && shows(&ppu, 7, 5, 0x0F) && shows(&ppu, 8, 5, 0x16));
+
+        ppu.write_register(0x2001, 0x1C);
+        step_frames(&mut ppu, 1);
+        assert!(shows(&ppu, 0, 10, 0x30) && shows(&ppu, 0, 5, 0x0F));
+
+        ppu.write_register(0x2001, 0x1A);
+        step_frames(&mut ppu, 1);
+        assert!(shows(&ppu, 0, 10, 0x16) && shows(&ppu, 0, 5, 0x16));
+
+        // Sprites disabled
+        ppu.write_register(0x2001, 0x0E);
+        step_frames(&mut ppu, 1);
+        assert!(shows(&ppu, 0, 10, 0x16));
+
+        ppu.write_register(0x2001, 0x0F);
+        step_frames(&mut ppu, 1);
+        assert!(shows(&ppu, 20, 5, 0x10));
+
+        // Red emphasis darkens green and blue
+        ppu.write_register(0x2001, 0x2E);
+        step_frames(&mut ppu, 1);
+        assert!(shows(&ppu, 20, 5, 0x56));
+        let (white, red_emphasis) = (ppu.palette.get_color(0x30), ppu.palette.get_color(0x70));
+        assert!(red_emphasis.r == white.r && red_emphasis.g < white.g && red_emphasis.b < white.b);
+        let all_emphasis = ppu.palette.get_color(0x1F0);
+        assert!(all_emphasis.r < white.r && all_emphasis.g < white.g && all_emphasis.b < white.b);
+
+        // The palette viewer ignores emphasis and the unused upper bits of palette RAM
+        ppu.bus.write(0x3F00, 0x70);
+        assert_eq!(ppu.get_current_palettes_raw()[..3], [white.r, white.g, white.b]);
+        Ok(())
+    }
+
+    /// Runs the PPU until the given dot is the next one to run, checking for NMI after every dot.
+    /// Returns the number of NMIs.
+    fn run_to_dot(ppu: &mut Ppu, y: u16, x: u16) -> u32 {
+        let mut nmi_count = 0;
+        while !(ppu.y == y && ppu.x == x) {
+            ppu.step();
+            nmi_count += ppu.is_nmi() as u32;
+        }
+        nmi_count
+    }
+
+    #[test]
+    fn test_vblank_flag_and_nmi_timing() -> Result<(), std::io::Error> {
+        let mut ppu = Ppu::new(Bus::new(Rc::new(RefCell::new(Cartridge::new()))));
+        ppu.write_register(0x2000, 0x80);
+        assert_eq!(run_to_dot(&mut ppu, 241, 1), 0);
+        assert_eq!(run_to_dot(&mut ppu, 241, 2), 0);
+        assert_eq!(ppu.ppustatus & 0x80, 0x80);
+        assert_eq!(run_to_dot(&mut ppu, 260, 0), 1);
+        assert_eq!(run_to_dot(&mut ppu, 0, 0), 0);
+        assert_eq!(ppu.ppustatus & 0x80, 0x00);
+
+        // One clock before the flag is set: reads clear, the flag is never set and there is no NMI
+        run_to_dot(&mut ppu, 241, 1);
+        assert_eq!(ppu.read_register(0x2002) & 0x80, 0x00);
+        assert_eq!(run_to_dot(&mut ppu, 260, 0), 0);
+        assert_eq!(ppu.ppustatus & 0x80, 0x00);
+
+        // Right after the flag is set: reads set and suppresses the NMI
+        for x in [2, 3] {
+            run_to_dot(&mut ppu, 241, x);
+            assert_eq!(ppu.read_register(0x2002) & 0x80, 0x80);
+            assert_eq!(run_to_dot(&mut ppu, 260, 0), 0);
+        }
+
+        // Later reads come too late to suppress the NMI
+        assert_eq!(run_to_dot(&mut ppu, 241, 4), 1);
+        assert_eq!(ppu.read_register(0x2002) & 0x80, 0x80);
+
+        // Enabling NMI while the flag is set causes an NMI, every time
+        ppu.write_register(0x2000, 0x00);
+        run_to_dot(&mut ppu, 0, 0);
+        assert_eq!(run_to_dot(&mut ppu, 241, 10), 0);
+        ppu.write_register(0x2000, 0x80);
+        assert_eq!(run_to_dot(&mut ppu, 250, 0), 1);
+        ppu.write_register(0x2000, 0x00);
+        assert!(!ppu.is_nmi());
+        ppu.write_register(0x2000, 0x80);
+        assert_eq!(run_to_dot(&mut ppu, 261, 0), 1);
+        // Not after the flag is cleared at the end of vertical blank
+        assert_eq!(run_to_dot(&mut ppu, 261, 2), 0);
+        ppu.write_register(0x2000, 0x00);
+        assert!(!ppu.is_nmi());
+        ppu.write_register(0x2000, 0x80);
+        assert_eq!(run_to_dot(&mut ppu, 0, 0), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_odd_frame_skip() -> Result<(), std::io::Error> {
+        let mut ppu = Ppu::new(Bus::new(Rc::new(RefCell::new(Cartridge::new()))));
+        let frame_length = |ppu: &mut Ppu| {
+            let odd_frame = ppu.odd_frame;
+            let mut dots = 0;
+            while ppu.odd_frame == odd_frame {
+                ppu.step();
+                dots += 1;
+            }
+            dots
+        };
+        assert_eq!(frame_length(&mut ppu), 341 * 262);
+        assert_eq!(frame_length(&mut ppu), 341 * 262);
+
+        ppu.write_register(0x2001, 0x08);
+        let lengths = [frame_length(&mut ppu), frame_length(&mut ppu)];
+        assert!(lengths.contains(&(341 * 262)) && lengths.contains(&(341 * 262 - 1)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_oamdata() -> Result<(), std::io::Error> {
+        let mut ppu = Ppu::new(Bus::new(Rc::new(RefCell::new(Cartridge::new()))));
+        ppu.oam_primary.fill(0xFF);
+        ppu.write_register(0x2003, 0x00);
+        for value in [0x05, 0x20, 0xFF, 0x30] {
+            ppu.write_register(0x2004, value);
+        }
+        assert_eq!(ppu.oam_primary[0..4], [0x05, 0x20, 0xE3, 0x30]);
+        assert_eq!(ppu.oamaddr, 4);
+
+        // Reads do not increment OAMADDR
+        ppu.write_register(0x2003, 0x02);
+        assert_eq!(ppu.read_register(0x2004), 0xE3);
+        assert_eq!(ppu.read_register(0x2004), 0xE3);
+
+        // During rendering reads return the value on the OAM bus
+        ppu.write_register(0x2001, 0x18);
+        run_to_dot(&mut ppu, 10, 30);
+        assert_eq!(ppu.read_register(0x2004), 0xFF);
+        ppu.write_register(0x2003, 0x01);
+        ppu.write_register(0x2004, 0x99);
+        assert_eq!((ppu.oamaddr, ppu.oam_primary[1]), (0x05, 0x20));
+        run_to_dot(&mut ppu, 10, 258);
+        assert_eq!(ppu.read_register(0x2004), 0x20);
+        run_to_dot(&mut ppu, 10, 260);
+        assert_eq!(ppu.read_register(0x2004), 0x30);
+        run_to_dot(&mut ppu, 10, 262);
+        assert_eq!(ppu.read_register(0x2004), 0x30);
+        Ok(())
+    }
+}
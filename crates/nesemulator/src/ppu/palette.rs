@@ -6,6 +6,24 @@ pub static PALETTE_GREYSCALE: [Color; 4] = [
     Color::new_rgb(170, 170, 170),
     Color::new_rgb(255, 255, 255)];
 
+/// Brightness left of a color channel that is darkened by color emphasis.
+const EMPHASIS_ATTENUATION: f32 = 0.816328;
+const EMPHASIS_RED: usize = 0b001;
+const EMPHASIS_GREEN: usize = 0b010;
+const EMPHASIS_BLUE: usize = 0b100;
+
+/// The 64 colors of the NES PPU, and the 8 variations of them produced by the color emphasis bits of PPUMASK.
+///
+/// A color is indexed with a 9-bit number `EEE_CCCCCC`, where `E` are the emphasis bits 5-7 of PPUMASK
+/// and `C` is the 6-bit color number found in palette RAM.
+///
+/// Emphasizing a color darkens the other two channels. The NTSC PPU darkens all channels when
+/// all three bits are set. This is an approximation of the analog signal the PPU produces.
+///
+/// Useful links:
+/// [Nesdev wiki - Color emphasis]
+///
+/// [Nesdev wiki - Color emphasis]: https://www.nesdev.org/wiki/NTSC_video#Color_Tint_Bits
 pub struct Palette {
     colors: Vec<Color>,
 }
@@ -19,11 +37,25 @@ impl Palette {
             (236, 238, 236), ( 76, 154, 236), (120, 124, 236), (176,  98, 236), (228,  84, 236), (236,  88, 180), (236, 106, 100), (212, 136,  32), (160, 170,   0), (116, 196,   0), ( 76, 208,  32), ( 56, 204, 108), ( 56, 180, 204), ( 60,  60,  60), (0, 0, 0), (0, 0, 0),
             (236, 238, 236), (168, 204, 236), (188, 188, 236), (212, 178, 236), (236, 174, 236), (236, 174, 212), (236, 180, 176), (228, 196, 144), (204, 210, 120), (180, 222, 120), (168, 226, 144), (152, 226, 180), (160, 214, 228), (160, 162, 160), (0, 0, 0), (0, 0, 0),
         ].into_iter().map(|x| Color {r: x.0, g:x.1, b:x.2}).collect();
+        let colors = (0..8).flat_map(|emphasis| colors.iter().map(move |&color| Palette::emphasize(color, emphasis))).collect();
         Palette { colors}
     }
 
+    fn emphasize(color: Color, emphasis: usize) -> Color {
+        let channel = |value: u8, own_bit: usize| {
+            let darkened = emphasis != 0 && (emphasis & own_bit == 0 || emphasis == 0b111);
+            if darkened {
+                (value as f32 * EMPHASIS_ATTENUATION).round() as u8
+            } else {
+                value
+            }
+        };
+        Color::new_rgb(channel(color.r, EMPHASIS_RED), channel(color.g, EMPHASIS_GREEN), channel(color.b, EMPHASIS_BLUE))
+    }
+
+    /// Returns the color with the given 9-bit index, the emphasis bits followed by the 6-bit color number.
     pub fn get_color(&self, index: usize) -> Color {
-        debug_assert!(index < 0x40);
+        debug_assert!(index < 0x200);
         self.colors[index]
     }
 }
@@ -0,0 +1,205 @@
+use crate::cartridge::NametableMirroring;
+use crate::error::{Error, Result};
+use crate::rom_header::{ExpansionDevice, RomHeader, TimingRegion};
+
+use log::info;
+use std::sync::OnceLock;
+
+const EMBEDDED_DATABASE: &str = include_str!("rom_database.txt");
+
+/// Header corrections for one game.
+///
+/// The game is recognized by a checksum of its PRG ROM and CHR ROM, so the header does not affect the match.
+/// Fields that are None are taken from the header.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct RomDatabaseEntry {
+    pub name: String,
+    pub crc32: Option<u32>,
+    /// Lowercase hex digits.
+    pub sha1: Option<String>,
+    pub mapper_number: Option<u16>,
+    pub submapper_number: Option<u8>,
+    pub mirroring: Option<NametableMirroring>,
+    pub battery: Option<bool>,
+    pub timing: Option<TimingRegion>,
+    pub default_expansion_device: Option<ExpansionDevice>,
+}
+
+impl RomDatabaseEntry {
+    /// Overrides the header fields given by the entry.
+    pub fn apply(&self, header: &mut RomHeader) {
+        if let Some(mapper_number) = self.mapper_number {
+            header.mapper_number = mapper_number;
+        }
+        if let Some(submapper_number) = self.submapper_number {
+            header.submapper_number = submapper_number;
+        }
+        if let Some(mirroring) = self.mirroring {
+            header.mirroring = mirroring;
+        }
+        if let Some(battery) = self.battery {
+            // Moves PRG RAM between the volatile and battery-backed sizes the way iNES headers would describe it
+            let prg_ram_size = header.total_prg_ram_size();
+            header.battery = battery;
+            if battery && header.prg_nvram_size == 0 {
+                header.prg_nvram_size = prg_ram_size;
+                header.prg_ram_size = 0;
+            } else if !battery {
+                header.prg_ram_size = prg_ram_size;
+                header.prg_nvram_size = 0;
+            }
+        }
+        if let Some(timing) = self.timing {
+            header.timing = timing;
+        }
+        if let Some(device) = self.default_expansion_device {
+            header.default_expansion_device = device;
+        }
+    }
+
+    fn parse(line: &str) -> std::result::Result<Option<RomDatabaseEntry>, String> {
+        let (fields, name) = match line.find('#') {
+            Some(index) => (&line[..index], line[index + 1..].trim()),
+            None => (line, ""),
+        };
+        if fields.trim().is_empty() {
+            return Ok(None);
+        }
+
+        let mut entry = RomDatabaseEntry {
+            name: name.to_owned(),
+            ..RomDatabaseEntry::default()
+        };
+        for field in fields.split_whitespace() {
+            let (key, value) = field.split_once('=').ok_or_else(|| format!("expected key=value, found {:?}", field))?;
+            let invalid = || format!("invalid value for {}: {:?}", key, value);
+            match key {
+                "crc32" => entry.crc32 = Some(u32::from_str_radix(value, 16).map_err(|_| invalid())?),
+                "sha1" => {
+                    if value.len() != 40 || !value.chars().all(|c| c.is_ascii_hexdigit()) {
+                        return Err(invalid());
+                    }
+                    entry.sha1 = Some(value.to_ascii_lowercase());
+                },
+                "mapper" => entry.mapper_number = Some(value.parse().map_err(|_| invalid())?),
+                "submapper" => entry.submapper_number = Some(value.parse().map_err(|_| invalid())?),
+                "mirroring" => entry.mirroring = Some(match value {
+                    "h" => NametableMirroring::Horizontal,
+                    "v" => NametableMirroring::Vertical,
+                    "4" => NametableMirroring::FourScreen,
+                    _ => return Err(invalid()),
+                }),
+                "battery" => entry.battery = Some(match value {
+                    "0" => false,
+                    "1" => true,
+                    _ => return Err(invalid()),
+                }),
+                "region" => entry.timing = Some(match value {
+                    "ntsc" => TimingRegion::Ntsc,
+                    "pal" => TimingRegion::Pal,
+                    "multi" => TimingRegion::MultipleRegion,
+                    "dendy" => TimingRegion::Dendy,
+                    _ => return Err(invalid()),
+                }),
+                "device" => entry.default_expansion_device = Some(ExpansionDevice::from_number(value.parse().map_err(|_| invalid())?)),
+                _ => return Err(format!("unknown key {:?}", key)),
+            }
+        }
+        if entry.crc32.is_none() && entry.sha1.is_none() {
+            return Err("entry needs crc32 or sha1".to_owned());
+        }
+        Ok(Some(entry))
+    }
+}
+
+/// Database of games whose iNES headers are commonly wrong in dumps.
+///
+/// The database embedded in the emulator is in `rom_database.txt`, which also describes the format.
+#[derive(Debug)]
+pub struct RomDatabase {
+    entries: Vec<RomDatabaseEntry>,
+}
+
+impl RomDatabase {
+    /// Returns the database embedded in the emulator. It is parsed on first use.
+    pub fn embedded() -> &'static RomDatabase {
+        static EMBEDDED: OnceLock<RomDatabase> = OnceLock::new();
+        EMBEDDED.get_or_init(|| RomDatabase::parse(EMBEDDED_DATABASE).expect("Embedded ROM database should be valid"))
+    }
+
+    /// Parses a database in the format of `rom_database.txt`.
+    pub fn parse(text: &str) -> Result<RomDatabase> {
+        let mut entries = Vec::new();
+        for (line_number, line) in (1..).zip(text.lines()) {
+            match RomDatabaseEntry::parse(line) {
+                Ok(Some(entry)) => entries.push(entry),
+                Ok(None) => (),
+                Err(message) => return Err(Error::InvalidRomDatabase(format!("line {}: {}", line_number, message))),
+            }
+        }
+        Ok(RomDatabase { entries })
+    }
+
+    /// Returns the entry matching the PRG ROM and CHR ROM of a game.
+    pub fn find(&self, prg_rom: &[u8], chr_rom: &[u8]) -> Option<&RomDatabaseEntry> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        let mut crc32 = crc32fast::Hasher::new();
+        crc32.update(prg_rom);
+        crc32.update(chr_rom);
+        let crc32 = crc32.finalize();
+        let mut sha1 = sha1_smol::Sha1::new();
+        sha1.update(prg_rom);
+        sha1.update(chr_rom);
+        let sha1 = sha1.digest().to_string();
+
+        let entry = self.entries.iter().find(|entry| {
+            entry.crc32 == Some(crc32) || entry.sha1.as_deref() == Some(sha1.as_str())
+        });
+        if let Some(entry) = entry {
+            info!("ROM database match: {}", entry.name);
+        }
+        entry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::result::Result;
+
+    #[test]
+    fn test_rom_database() -> Result<(), std::io::Error> {
+        RomDatabase::embedded(); // Panics if the embedded database is invalid
+
+        let prg_rom = vec![0xEA; 0x4000];
+        let chr_rom = vec![0x00; 0x2000];
+        let crc32 = crc32fast::hash(&[&prg_rom[..], &chr_rom[..]].concat());
+        let database = RomDatabase::parse(&format!(
+            "# Comment\n\ncrc32={:08X} mapper=2 mirroring=v battery=1 region=pal device=8 # Test Game\n\
+             sha1=0000000000000000000000000000000000000000 mapper=1\n",
+            crc32
+        )).unwrap();
+
+        let entry = database.find(&prg_rom, &chr_rom).unwrap();
+        assert_eq!(entry.name, "Test Game");
+        assert!(database.find(&prg_rom, &[]).is_none());
+
+        let mut header = RomHeader {
+            prg_ram_size: 0x2000,
+            ..RomHeader::default()
+        };
+        entry.apply(&mut header);
+        assert_eq!(header.mapper_number, 2);
+        assert_eq!(header.mirroring, NametableMirroring::Vertical);
+        assert!(header.battery);
+        assert_eq!((header.prg_ram_size, header.prg_nvram_size), (0, 0x2000));
+        assert_eq!(header.timing, TimingRegion::Pal);
+        assert_eq!(header.default_expansion_device, ExpansionDevice::Zapper);
+
+        assert!(matches!(RomDatabase::parse("crc32=XYZ"), Err(Error::InvalidRomDatabase(_))));
+        assert!(matches!(RomDatabase::parse("mapper=1"), Err(Error::InvalidRomDatabase(_))));
+        Ok(())
+    }
+}
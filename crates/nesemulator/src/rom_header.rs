@@ -0,0 +1,393 @@
+use crate::cartridge::NametableMirroring;
+
+pub const HEADER_SIZE: usize = 16;
+pub const TRAINER_SIZE: usize = 512;
+const PRG_ROM_UNIT_SIZE: usize = 0x4000; // 16384
+const CHR_ROM_UNIT_SIZE: usize = 0x2000; //  8192
+const PRG_RAM_UNIT_SIZE: usize = 0x2000; //  8192
+
+/// Header format of a ROM file.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RomFormat {
+    /// iNES file whose bytes 7..=15 contain garbage, e.g. "DiskDude!". Only the lower nibble of the mapper number is used.
+    ArchaicINes,
+    INes,
+    Nes20,
+    /// Famicom Disk System disk image. The header describes the RAM adapter, see [`RomHeader::fds`].
+    Fds,
+    /// UNIF file. The header is filled from its chunks, with the mapper number matching the board name.
+    Unif,
+    /// NSF or NSFe music file. The header describes the NSF player hardware, see [`RomHeader::nsf`].
+    Nsf,
+}
+
+/// CPU/PPU timing the ROM was made for.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TimingRegion {
+    Ntsc,
+    Pal,
+    /// Works on both NTSC and PAL consoles.
+    MultipleRegion,
+    Dendy,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ConsoleType {
+    /// Nintendo Entertainment System or Family Computer.
+    Nes,
+    VsSystem { ppu_type: u8, hardware_type: u8 },
+    Playchoice10,
+    /// Extended console type from byte 13, e.g. Famiclone with decimal mode or VT01.
+    Extended(u8),
+}
+
+/// Device that should be connected to the expansion or controller ports by default.
+///
+/// Useful links:
+/// [Nesdev wiki - NES 2.0 Default Expansion Device]
+///
+/// [Nesdev wiki - NES 2.0 Default Expansion Device]: https://www.nesdev.org/wiki/NES_2.0#Default_Expansion_Device
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ExpansionDevice {
+    Unspecified,
+    StandardControllers,
+    FourScore,
+    FamicomFourPlayersAdapter,
+    VsSystem,
+    VsSystemReversed,
+    VsPinballJapan,
+    VsZapper,
+    Zapper,
+    TwoZappers,
+    BandaiHyperShot,
+    PowerPadSideA,
+    PowerPadSideB,
+    FamilyTrainerSideA,
+    FamilyTrainerSideB,
+    /// Any other device by its number.
+    Other(u8),
+}
+
+impl ExpansionDevice {
+    /// Returns the device by its NES 2.0 number.
+    pub fn from_number(number: u8) -> ExpansionDevice {
+        use ExpansionDevice::*;
+        match number {
+            0x00 => Unspecified,
+            0x01 => StandardControllers,
+            0x02 => FourScore,
+            0x03 => FamicomFourPlayersAdapter,
+            0x04 => VsSystem,
+            0x05 => VsSystemReversed,
+            0x06 => VsPinballJapan,
+            0x07 => VsZapper,
+            0x08 => Zapper,
+            0x09 => TwoZappers,
+            0x0A => BandaiHyperShot,
+            0x0B => PowerPadSideA,
+            0x0C => PowerPadSideB,
+            0x0D => FamilyTrainerSideA,
+            0x0E => FamilyTrainerSideB,
+            _ => Other(number),
+        }
+    }
+}
+
+/// Decoded iNES or NES 2.0 header of a ROM file.
+///
+/// Memory sizes are in bytes. iNES headers only describe PRG RAM size, so it is reported
+/// as PRG NVRAM if the battery flag is set, and CHR RAM is assumed to be 8 KiB if there is no CHR ROM.
+///
+/// Useful links:
+/// [Nesdev wiki - iNES]
+/// [Nesdev wiki - NES 2.0]
+///
+/// [Nesdev wiki - iNES]: https://www.nesdev.org/wiki/INES
+/// [Nesdev wiki - NES 2.0]: https://www.nesdev.org/wiki/NES_2.0
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct RomHeader {
+    pub format: RomFormat,
+    pub mapper_number: u16,
+    pub submapper_number: u8,
+    pub prg_rom_size: usize,
+    pub chr_rom_size: usize,
+    pub prg_ram_size: usize,
+    pub prg_nvram_size: usize,
+    pub chr_ram_size: usize,
+    pub chr_nvram_size: usize,
+    /// Hardwired nametable mirroring. Ignored by mappers that control mirroring,
+    /// except four-screen mirroring, which means the board provides its own VRAM for two more nametables.
+    pub mirroring: NametableMirroring,
+    /// The board has battery-backed memory or other non-volatile memory.
+    pub battery: bool,
+    /// A 512-byte trainer is present between the header and PRG ROM.
+    pub trainer: bool,
+    pub timing: TimingRegion,
+    pub console_type: ConsoleType,
+    pub misc_rom_count: u8,
+    pub default_expansion_device: ExpansionDevice,
+}
+
+impl RomHeader {
+    /// Parses the header of an iNES or NES 2.0 ROM file.
+    ///
+    /// Returns None if the data does not start with the identification string "NES<EOF>".
+    pub fn parse(data: &[u8]) -> Option<RomHeader> {
+        if data.len() < HEADER_SIZE || !data[0..4].eq(b"NES\x1a") {
+            return None;
+        }
+        let header = &data[0..HEADER_SIZE];
+
+        let format = if header[7] & 0x0C == 0x08 && nes20_rom_size(header) <= data.len() - HEADER_SIZE {
+            RomFormat::Nes20
+        } else if header[7] & 0x0C == 0x00 && header[12..16].iter().all(|&byte| byte == 0) {
+            RomFormat::INes
+        } else {
+            RomFormat::ArchaicINes
+        };
+
+        let mut rom_header = RomHeader {
+            format,
+            mirroring: match header[6] & 0x09 {
+                0x08 | 0x09 => NametableMirroring::FourScreen,
+                0x01 => NametableMirroring::Vertical,
+                _ => NametableMirroring::Horizontal,
+            },
+            battery: header[6] & 0x02 == 0x02,
+            trainer: header[6] & 0x04 == 0x04,
+            mapper_number: (header[6] >> 4) as u16,
+            ..RomHeader::default()
+        };
+
+        match format {
+            RomFormat::Nes20 => rom_header.parse_nes20(header),
+            RomFormat::INes => rom_header.parse_ines(header),
+            RomFormat::Fds | RomFormat::Unif | RomFormat::Nsf => unreachable!("Only iNES formats are detected from the header"),
+            RomFormat::ArchaicINes => {
+                rom_header.prg_rom_size = header[4] as usize * PRG_ROM_UNIT_SIZE;
+                rom_header.chr_rom_size = header[5] as usize * CHR_ROM_UNIT_SIZE;
+                rom_header.set_ines_ram_sizes(1);
+            },
+        }
+        Some(rom_header)
+    }
+
+    /// Returns the header describing the Famicom Disk System RAM adapter:
+    /// the BIOS as PRG ROM, 32 KiB of PRG RAM and 8 KiB of CHR RAM. Mapper number 20 is reserved for it in iNES.
+    pub fn fds() -> RomHeader {
+        RomHeader {
+            format: RomFormat::Fds,
+            mapper_number: 20,
+            prg_rom_size: crate::mapper::fds::BIOS_SIZE,
+            prg_ram_size: crate::mapper::fds::PRG_RAM_SIZE,
+            chr_ram_size: CHR_ROM_UNIT_SIZE,
+            ..RomHeader::default()
+        }
+    }
+
+    /// Returns the header describing the NSF player hardware: PRG ROM of the given size, 8 KiB of PRG RAM and no CHR.
+    pub fn nsf(prg_rom_size: usize) -> RomHeader {
+        RomHeader {
+            format: RomFormat::Nsf,
+            prg_rom_size,
+            prg_ram_size: PRG_RAM_UNIT_SIZE,
+            ..RomHeader::default()
+        }
+    }
+
+    fn parse_ines(&mut self, header: &[u8]) {
+        self.mapper_number |= (header[7] & 0xF0) as u16;
+        self.prg_rom_size = header[4] as usize * PRG_ROM_UNIT_SIZE;
+        self.chr_rom_size = header[5] as usize * CHR_ROM_UNIT_SIZE;
+        self.set_ines_ram_sizes(header[8]);
+        self.console_type = match header[7] & 0x03 {
+            0x01 => ConsoleType::VsSystem { ppu_type: 0, hardware_type: 0 },
+            0x02 => ConsoleType::Playchoice10,
+            _ => ConsoleType::Nes,
+        };
+        self.timing = if header[9] & 0x01 == 0x01 { TimingRegion::Pal } else { TimingRegion::Ntsc };
+    }
+
+    /// Sets the memory sizes an iNES header implies. PRG RAM is given in 8 KiB units, 0 meaning 8 KiB for compatibility.
+    fn set_ines_ram_sizes(&mut self, prg_ram_units: u8) {
+        let prg_ram_size = (prg_ram_units as usize).max(1) * PRG_RAM_UNIT_SIZE;
+        if self.battery {
+            self.prg_nvram_size = prg_ram_size;
+        } else {
+            self.prg_ram_size = prg_ram_size;
+        }
+        if self.chr_rom_size == 0 {
+            self.chr_ram_size = CHR_ROM_UNIT_SIZE;
+        }
+    }
+
+    fn parse_nes20(&mut self, header: &[u8]) {
+        self.mapper_number |= (header[7] & 0xF0) as u16 | ((header[8] & 0x0F) as u16) << 8;
+        self.submapper_number = header[8] >> 4;
+        self.prg_rom_size = nes20_prg_rom_size(header);
+        self.chr_rom_size = nes20_chr_rom_size(header);
+        self.prg_ram_size = shift_count_to_size(header[10] & 0x0F);
+        self.prg_nvram_size = shift_count_to_size(header[10] >> 4);
+        self.chr_ram_size = shift_count_to_size(header[11] & 0x0F);
+        self.chr_nvram_size = shift_count_to_size(header[11] >> 4);
+        self.timing = match header[12] & 0x03 {
+            0 => TimingRegion::Ntsc,
+            1 => TimingRegion::Pal,
+            2 => TimingRegion::MultipleRegion,
+            _ => TimingRegion::Dendy,
+        };
+        self.console_type = match header[7] & 0x03 {
+            0 => ConsoleType::Nes,
+            1 => ConsoleType::VsSystem {
+                ppu_type: header[13] & 0x0F,
+                hardware_type: header[13] >> 4,
+            },
+            2 => ConsoleType::Playchoice10,
+            _ => ConsoleType::Extended(header[13] & 0x0F),
+        };
+        self.misc_rom_count = header[14] & 0x03;
+        self.default_expansion_device = ExpansionDevice::from_number(header[15] & 0x3F);
+    }
+
+    /// Returns the total size of PRG RAM and PRG NVRAM.
+    pub fn total_prg_ram_size(&self) -> usize {
+        self.prg_ram_size + self.prg_nvram_size
+    }
+
+    /// Returns the total size of CHR RAM and CHR NVRAM.
+    pub fn total_chr_ram_size(&self) -> usize {
+        self.chr_ram_size + self.chr_nvram_size
+    }
+}
+
+impl Default for RomHeader {
+    fn default() -> Self {
+        RomHeader {
+            format: RomFormat::INes,
+            mapper_number: 0,
+            submapper_number: 0,
+            prg_rom_size: 0,
+            chr_rom_size: 0,
+            prg_ram_size: 0,
+            prg_nvram_size: 0,
+            chr_ram_size: 0,
+            chr_nvram_size: 0,
+            mirroring: NametableMirroring::Horizontal,
+            battery: false,
+            trainer: false,
+            timing: TimingRegion::Ntsc,
+            console_type: ConsoleType::Nes,
+            misc_rom_count: 0,
+            default_expansion_device: ExpansionDevice::Unspecified,
+        }
+    }
+}
+
+/// RAM sizes are given as shift counts: 64 << n bytes, 0 meaning none.
+fn shift_count_to_size(shift_count: u8) -> usize {
+    if shift_count == 0 {
+        0
+    } else {
+        64 << shift_count
+    }
+}
+
+/// ROM sizes are given as a 12-bit number of units, the upper nibble coming from byte 9.
+/// If the upper nibble is 0xF, the lower byte is in exponent-multiplier form instead: 2^E * (MM * 2 + 1) bytes.
+fn nes20_rom_size(header: &[u8]) -> usize {
+    nes20_prg_rom_size(header).saturating_add(nes20_chr_rom_size(header))
+}
+
+fn nes20_prg_rom_size(header: &[u8]) -> usize {
+    rom_size_from_units(header[4], header[9] & 0x0F, PRG_ROM_UNIT_SIZE)
+}
+
+fn nes20_chr_rom_size(header: &[u8]) -> usize {
+    rom_size_from_units(header[5], header[9] >> 4, CHR_ROM_UNIT_SIZE)
+}
+
+fn rom_size_from_units(lsb: u8, msb: u8, unit_size: usize) -> usize {
+    if msb == 0x0F {
+        let exponent = (lsb >> 2) as u32;
+        let multiplier = (lsb & 0x03) as usize * 2 + 1;
+        1usize.checked_shl(exponent)
+            .and_then(|size| size.checked_mul(multiplier))
+            .unwrap_or(usize::MAX)
+    } else {
+        ((msb as usize) << 8 | lsb as usize) * unit_size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rom(header: [u8; HEADER_SIZE], data_size: usize) -> Vec<u8> {
+        let mut rom = header.to_vec();
+        rom.resize(HEADER_SIZE + data_size, 0);
+        rom
+    }
+
+    #[test]
+    fn test_ines_header() -> Result<(), std::io::Error> {
+        let data = rom(*b"NES\x1a\x02\x00\x13\x40\x00\x00\x00\x00\x00\x00\x00\x00", 2 * PRG_ROM_UNIT_SIZE);
+        let header = RomHeader::parse(&data).unwrap();
+        assert_eq!(header.format, RomFormat::INes);
+        assert_eq!(header.mapper_number, 0x41);
+        assert_eq!(header.prg_rom_size, 0x8000);
+        assert_eq!(header.chr_rom_size, 0);
+        assert_eq!(header.prg_ram_size, 0);
+        assert_eq!(header.prg_nvram_size, 0x2000);
+        assert_eq!(header.chr_ram_size, 0x2000);
+        assert_eq!(header.mirroring, NametableMirroring::Vertical);
+        assert!(header.battery);
+
+        // Garbage in bytes 12..=15 leaves only the lower nibble of the mapper number
+        let data = rom(*b"NES\x1a\x01\x01\x10\x40DiskDude", PRG_ROM_UNIT_SIZE);
+        assert_eq!(RomHeader::parse(&data).unwrap().format, RomFormat::ArchaicINes);
+        assert_eq!(RomHeader::parse(&data).unwrap().mapper_number, 1);
+
+        assert_eq!(RomHeader::parse(b"NES\x1a"), None);
+        assert_eq!(RomHeader::parse(&[0; HEADER_SIZE]), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_nes20_header() -> Result<(), std::io::Error> {
+        let header = [
+            b'N', b'E', b'S', 0x1A,
+            0x02, // PRG ROM 2 * 16 KiB
+            0x07, // CHR ROM exponent 1, multiplier 3: 2^1 * 7 = 14 bytes
+            0x42, // Mapper bits 0..=3, battery
+            0x59, // Mapper bits 4..=7, NES 2.0, Vs. System
+            0x31, // Submapper 3, mapper bits 8..=11
+            0xF0, // CHR ROM size in exponent-multiplier form
+            0x70, // PRG NVRAM 64 << 7 = 8 KiB
+            0x07, // CHR RAM 64 << 7 = 8 KiB
+            0x03, // Dendy
+            0x21, // Vs. PPU 1, Vs. hardware 2
+            0x01, // 1 misc ROM
+            0x08, // Zapper
+        ];
+        let data = rom(header, 2 * PRG_ROM_UNIT_SIZE + 14);
+        let header = RomHeader::parse(&data).unwrap();
+        assert_eq!(header.format, RomFormat::Nes20);
+        assert_eq!(header.mapper_number, 0x154);
+        assert_eq!(header.submapper_number, 3);
+        assert_eq!(header.prg_rom_size, 0x8000);
+        assert_eq!(header.chr_rom_size, 14);
+        assert_eq!(header.prg_ram_size, 0);
+        assert_eq!(header.prg_nvram_size, 0x2000);
+        assert_eq!(header.chr_ram_size, 0x2000);
+        assert_eq!(header.chr_nvram_size, 0);
+        assert_eq!(header.timing, TimingRegion::Dendy);
+        assert_eq!(header.console_type, ConsoleType::VsSystem { ppu_type: 1, hardware_type: 2 });
+        assert_eq!(header.misc_rom_count, 1);
+        assert_eq!(header.default_expansion_device, ExpansionDevice::Zapper);
+
+        // The NES 2.0 identifier is ignored if the sizes do not fit the file
+        let data = rom(*b"NES\x1a\x02\x00\x00\x08\x00\x01\x00\x00\x00\x00\x00\x00", 2 * PRG_ROM_UNIT_SIZE);
+        assert_eq!(RomHeader::parse(&data).unwrap().format, RomFormat::ArchaicINes);
+        Ok(())
+    }
+}
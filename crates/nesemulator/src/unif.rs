@@ -0,0 +1,206 @@
+use crate::cartridge::NametableMirroring;
+use crate::error::{Error, Result};
+use crate::rom_header::{RomFormat, RomHeader, TimingRegion};
+
+use log::info;
+
+const UNIF_MAGIC: &[u8] = b"UNIF";
+const UNIF_HEADER_SIZE: usize = 32;
+const PRG_RAM_SIZE: usize = 0x2000;
+const CHR_RAM_SIZE: usize = 0x2000;
+
+/// Returns true if the data is a UNIF file.
+pub fn is_unif(data: &[u8]) -> bool {
+    data.starts_with(UNIF_MAGIC)
+}
+
+/// Contents of a UNIF file.
+pub struct UnifRom {
+    /// Header describing the board the same way an iNES header would.
+    pub header: RomHeader,
+    pub board_name: String,
+    pub prg_rom: Vec<u8>,
+    pub chr_rom: Vec<u8>,
+}
+
+/// Parses a UNIF file.
+///
+/// A UNIF file is a 32 byte header followed by chunks with a 4 character ID and a length.
+/// The board is given by name in the MAPR chunk instead of a mapper number.
+/// PRG ROM and CHR ROM may be split into chunks PRG0..PRGF and CHR0..CHRF, which are joined in order.
+///
+/// Useful links:
+/// [Nesdev wiki - UNIF]
+///
+/// [Nesdev wiki - UNIF]: https://www.nesdev.org/wiki/UNIF
+pub fn parse(data: &[u8]) -> Result<UnifRom> {
+    if !is_unif(data) || data.len() < UNIF_HEADER_SIZE {
+        return Err(invalid_unif("missing header"));
+    }
+
+    let mut board_name = None;
+    let mut prg_chunks: [Option<&[u8]>; 16] = [None; 16];
+    let mut chr_chunks: [Option<&[u8]>; 16] = [None; 16];
+    let mut mirroring = NametableMirroring::Horizontal;
+    let mut battery = false;
+    let mut timing = TimingRegion::Ntsc;
+    let mut position = UNIF_HEADER_SIZE;
+    while position < data.len() {
+        let header = data.get(position..position + 8).ok_or_else(|| invalid_unif("truncated chunk header"))?;
+        let id = &header[0..4];
+        let length = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        let chunk = data.get(position + 8..position.saturating_add(8 + length)).ok_or_else(|| invalid_unif("truncated chunk"))?;
+        position += 8 + length;
+        match id {
+            b"MAPR" => board_name = Some(string(chunk)),
+            [b'P', b'R', b'G', digit] => prg_chunks[chunk_number(*digit)?] = Some(chunk),
+            [b'C', b'H', b'R', digit] => chr_chunks[chunk_number(*digit)?] = Some(chunk),
+            b"MIRR" => {
+                mirroring = match chunk.first() {
+                    Some(0) => NametableMirroring::Horizontal,
+                    Some(1) => NametableMirroring::Vertical,
+                    Some(2) => NametableMirroring::SingleScreenA,
+                    Some(3) => NametableMirroring::SingleScreenB,
+                    Some(4) => NametableMirroring::FourScreen,
+                    _ => NametableMirroring::Horizontal, // Controlled by the mapper
+                }
+            },
+            b"BATR" => battery = chunk.first() != Some(&0),
+            b"TVCI" => {
+                timing = match chunk.first() {
+                    Some(1) => TimingRegion::Pal,
+                    Some(2) => TimingRegion::MultipleRegion,
+                    _ => TimingRegion::Ntsc,
+                }
+            },
+            b"NAME" => info!("unif name: {}", string(chunk)),
+            _ => (),
+        }
+    }
+
+    let board_name = board_name.ok_or_else(|| invalid_unif("missing MAPR chunk"))?;
+    let prg_rom: Vec<u8> = prg_chunks.iter().flatten().flat_map(|chunk| chunk.iter().copied()).collect();
+    let chr_rom: Vec<u8> = chr_chunks.iter().flatten().flat_map(|chunk| chunk.iter().copied()).collect();
+    if prg_rom.is_empty() {
+        return Err(invalid_unif("missing PRG chunk"));
+    }
+    let (mapper_number, submapper_number) = board_mapper(&board_name).ok_or_else(|| Error::UnsupportedBoard(board_name.clone()))?;
+
+    let mut header = RomHeader {
+        format: RomFormat::Unif,
+        mapper_number,
+        submapper_number,
+        prg_rom_size: prg_rom.len(),
+        chr_rom_size: chr_rom.len(),
+        mirroring,
+        battery,
+        timing,
+        ..RomHeader::default()
+    };
+    if battery {
+        header.prg_nvram_size = PRG_RAM_SIZE;
+    } else {
+        header.prg_ram_size = PRG_RAM_SIZE;
+    }
+    if chr_rom.is_empty() {
+        header.chr_ram_size = CHR_RAM_SIZE;
+    }
+    Ok(UnifRom {
+        header,
+        board_name,
+        prg_rom,
+        chr_rom,
+    })
+}
+
+/// Returns the mapper number and submapper number of a board name.
+///
+/// The prefix telling who made the board, such as "NES-" or "HVC-", is ignored.
+/// Only licensed boards are known. Unlicensed, bootleg and multicart boards ("UNL-", "BTL-" and "BMC-")
+/// use their own mapper hardware, so they are not supported.
+fn board_mapper(board_name: &str) -> Option<(u16, u8)> {
+    let name = match board_name.split_once('-') {
+        Some((prefix, name)) if ["NES", "HVC", "IREM", "KONAMI"].contains(&prefix) => name,
+        _ => board_name,
+    };
+    Some(match name {
+        "NROM" | "NROM-128" | "NROM-256" | "RROM" | "RROM-128" => (0, 0),
+        "SAROM" | "SBROM" | "SCROM" | "SEROM" | "SFROM" | "SGROM" | "SHROM" | "SJROM" | "SKROM" | "SLROM"
+            | "SL1ROM" | "SNROM" | "SOROM" | "SUROM" | "SXROM" => (1, 0),
+        "UNROM" | "UOROM" => (2, 0),
+        "CNROM" => (3, 0),
+        "TBROM" | "TEROM" | "TFROM" | "TGROM" | "TKROM" | "TLROM" | "TL1ROM" | "TNROM" | "TR1ROM" | "TSROM"
+            | "TVROM" => (4, 0),
+        // AMROM has bus conflicts, which is submapper 2 of AxROM
+        "AMROM" => (7, 2),
+        "ANROM" | "AN1ROM" | "AOROM" => (7, 0),
+        "GNROM" | "MHROM" => (66, 0),
+        _ => return None,
+    })
+}
+
+/// Returns the number of a PRGn or CHRn chunk from its hexadecimal digit.
+fn chunk_number(digit: u8) -> Result<usize> {
+    (digit as char).to_digit(16).map(|number| number as usize).ok_or_else(|| invalid_unif("invalid chunk number"))
+}
+
+/// Reads a null-terminated string.
+fn string(data: &[u8]) -> String {
+    let end = data.iter().position(|&byte| byte == 0).unwrap_or(data.len());
+    String::from_utf8_lossy(&data[..end]).into_owned()
+}
+
+fn invalid_unif(message: &str) -> Error {
+    Error::InvalidUnif(message.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::result::Result;
+
+    fn unif(chunks: &[(&[u8], &[u8])]) -> Vec<u8> {
+        let mut data = b"UNIF\x07\x00\x00\x00".to_vec();
+        data.resize(UNIF_HEADER_SIZE, 0);
+        for (id, contents) in chunks {
+            data.extend_from_slice(id);
+            data.extend_from_slice(&(contents.len() as u32).to_le_bytes());
+            data.extend_from_slice(contents);
+        }
+        data
+    }
+
+    #[test]
+    fn test_parse_unif() -> Result<(), std::io::Error> {
+        let data = unif(&[
+            (b"MAPR", b"NES-AMROM\0"),
+            (b"PRG1", &[0x02; 0x4000]),
+            (b"PRG0", &[0x01; 0x4000]),
+            (b"MIRR", &[1]),
+            (b"BATR", &[1]),
+            (b"TVCI", &[1]),
+        ]);
+        let rom = parse(&data).unwrap();
+        assert_eq!(rom.board_name, "NES-AMROM");
+        assert_eq!((rom.header.mapper_number, rom.header.submapper_number), (7, 2));
+        assert_eq!(rom.prg_rom.len(), 0x8000);
+        assert_eq!((rom.prg_rom[0], rom.prg_rom[0x4000]), (0x01, 0x02));
+        assert!(rom.chr_rom.is_empty());
+        assert_eq!(rom.header.chr_ram_size, CHR_RAM_SIZE);
+        assert_eq!(rom.header.mirroring, NametableMirroring::Vertical);
+        assert!(rom.header.battery);
+        assert_eq!((rom.header.prg_ram_size, rom.header.prg_nvram_size), (0, PRG_RAM_SIZE));
+        assert_eq!(rom.header.timing, TimingRegion::Pal);
+
+        let data = unif(&[(b"MAPR", b"UNL-SOMETHING\0"), (b"PRG0", &[0; 0x4000])]);
+        assert!(matches!(parse(&data), Err(Error::UnsupportedBoard(name)) if name == "UNL-SOMETHING"));
+        let data = unif(&[(b"MAPR", b"BMC-NROM\0"), (b"PRG0", &[0; 0x4000])]);
+        assert!(matches!(parse(&data), Err(Error::UnsupportedBoard(name)) if name == "BMC-NROM"));
+        let data = unif(&[(b"PRG0", &[0; 0x4000])]);
+        assert!(matches!(parse(&data), Err(Error::InvalidUnif(_))));
+        let mut data = unif(&[(b"MAPR", b"NES-NROM-256\0"), (b"PRG0", &[0; 0x8000])]);
+        data.truncate(data.len() - 1);
+        assert!(matches!(parse(&data), Err(Error::InvalidUnif(_))));
+        Ok(())
+    }
+}
@@ -3,21 +3,24 @@ extern crate sdl2;
 use egui::{Pos2, RawInput, Rect, Vec2};
 use emulator::ppu::display::Display;
 use emulator::{Button, Emulator};
-use log::{debug, info};
+use log::{debug, error, info};
+use sdl2::audio::{AudioQueue, AudioSpecDesired};
 use sdl2::controller::GameController;
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
 use sdl2::video::GLProfile;
 use sdl2::video::{GLContext, Window};
-use sdl2::{Sdl, VideoSubsystem};
+use sdl2::{AudioSubsystem, Sdl, VideoSubsystem};
 use sdl2_egui::{CallbackFn, Painter, Texture};
 use std::sync::Arc;
-use std::{cell::RefCell, collections::HashMap, env, time::Duration};
+use std::{cell::RefCell, collections::HashMap, time::Duration};
 
 type TextureIdContainer = std::thread::LocalKey<RefCell<Option<egui::TextureId>>>;
 
 const DEFAULT_SCREEN_WIDTH: u32 = 1200;
 const DEFAULT_SCREEN_HEIGHT: u32 = 800;
+const AUDIO_SAMPLE_RATE: i32 = 44100;
+const AUDIO_MAX_QUEUED_FRAMES: usize = 4;
 
 trait CustomTexture {
     fn init(&'static self, painter: &mut Painter, width: usize, height: usize);
@@ -56,18 +59,21 @@ impl CustomTexture for TextureIdContainer {
 
 pub struct Gui {
     emulator: Emulator,
+    /// Error that stopped the emulation. Shown to the user instead of the game running further.
+    emulation_error: Option<emulator::Error>,
     sdl_context: Sdl,
     window: Window,
     _video_subsystem: VideoSubsystem,
+    _audio_subsystem: AudioSubsystem,
+    audio_queue: AudioQueue<f32>,
     gamepads: HashMap<u32, GameController>,
     painter: Painter,
     _gl_context: GLContext,
 }
 
 impl Gui {
-    pub fn new() -> Self {
-        let args: Vec<String> = env::args().collect();
-        let emulator = Emulator::new(&args[1]);
+    pub fn new(rom_path: &str) -> Result<Self, emulator::Error> {
+        let mut emulator = Emulator::new(rom_path)?;
         let sdl_context = sdl2::init().unwrap();
         let gamepads = HashMap::new();
 
@@ -89,16 +95,29 @@ impl Gui {
         gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void);
 
         let painter = Painter::new(DEFAULT_SCREEN_WIDTH, DEFAULT_SCREEN_HEIGHT, 1.0);
-        
-        Self {
+
+        let audio_subsystem = sdl_context.audio().unwrap();
+        let audio_spec = AudioSpecDesired {
+            freq: Some(AUDIO_SAMPLE_RATE),
+            channels: Some(1),
+            samples: None,
+        };
+        let audio_queue: AudioQueue<f32> = audio_subsystem.open_queue(None, &audio_spec).unwrap();
+        emulator.set_audio_sample_rate(audio_queue.spec().freq as u32);
+        audio_queue.resume();
+
+        Ok(Self {
             emulator,
+            emulation_error: None,
             sdl_context,
             window,
             _video_subsystem: video_subsystem,
+            _audio_subsystem: audio_subsystem,
+            audio_queue,
             gamepads,
             painter,
             _gl_context,
-        }
+        })
     }
 
     pub fn run(&mut self) {
@@ -113,16 +132,19 @@ impl Gui {
             pub static TEXTURE_PATTERN_TABLE_1: RefCell<Option<egui::TextureId>>  = RefCell::new(None);
             pub static TEXTURE_PALETTES: RefCell<Option<egui::TextureId>>  = RefCell::new(None);
             pub static TEXTURE_NAMETABLES: RefCell<Option<egui::TextureId>>  = RefCell::new(None);
+            pub static TEXTURE_SPRITES: RefCell<Option<egui::TextureId>>  = RefCell::new(None);
         }
         TEXTURE_GAME.init(&mut self.painter, 256, 240);
         TEXTURE_PATTERN_TABLE_0.init(&mut self.painter, 128, 128);
         TEXTURE_PATTERN_TABLE_1.init(&mut self.painter, 128, 128);
         TEXTURE_PALETTES.init(&mut self.painter, 4, 8);
         TEXTURE_NAMETABLES.init(&mut self.painter, 512, 480);
+        TEXTURE_SPRITES.init(&mut self.painter, 64, 128);
 
         let mut pixels_pattern_table_0 = Display::new(128, 128);
         let mut pixels_pattern_table_1 = Display::new(128, 128);
         let mut pixels_nametables = Display::new(512, 480);
+        let mut pixels_sprites = Display::new(64, 128);
 
         let mut event_pump = self.sdl_context.event_pump().unwrap();
 
@@ -130,6 +152,7 @@ impl Gui {
         let mut show_pattern_table_1: bool = true;
         let mut show_palettes: bool = true;
         let mut show_nametables: bool = true;
+        let mut show_sprites: bool = true;
 
         'running: loop {
             let time_frame_start = std::time::Instant::now();
@@ -161,6 +184,23 @@ impl Gui {
                         debug!("Controller button up index={} button={:?}", which, button);
                         handle_emulator_input(event, &mut self.emulator);
                     }
+                    Event::KeyDown {
+                        keycode: Some(Keycode::F5),
+                        repeat: false,
+                        ..
+                    } => {
+                        info!("Reset");
+                        self.emulation_error = self.emulator.reset().err();
+                    }
+                    Event::KeyDown {
+                        keycode: Some(Keycode::F6),
+                        repeat: false,
+                        ..
+                    } => {
+                        info!("Power cycle");
+                        self.emulator.power_cycle();
+                        self.emulation_error = None;
+                    }
                     Event::KeyDown { .. } | Event::KeyUp { .. } => {
                         handle_emulator_input(event, &mut self.emulator)
                     }
@@ -236,21 +276,40 @@ impl Gui {
                 has_focus: true, //TODO: add real focus from events
             };
 
-            // Run emulator until a frame is ready.
-            self.emulator.step_frame();
-
-            // Update game screen
-            let ppu = self.emulator.cpu.bus.ppu.as_mut().unwrap();
-            let display = &ppu.display;
-            TEXTURE_GAME.update(&mut self.painter, display.get_pixels());
-            // Update game screen texture
-            ppu.load_pattern_table_tiles_to_display(0x0000, &mut pixels_pattern_table_0);
-            ppu.load_pattern_table_tiles_to_display(0x1000, &mut pixels_pattern_table_1);
-            ppu.load_nametable_tiles_to_display(&mut pixels_nametables);
-            TEXTURE_PATTERN_TABLE_0.update(&mut self.painter, pixels_pattern_table_0.get_pixels());
-            TEXTURE_PATTERN_TABLE_1.update(&mut self.painter, pixels_pattern_table_1.get_pixels());
-            TEXTURE_PALETTES.update(&mut self.painter, &ppu.get_current_palettes_raw());
-            TEXTURE_NAMETABLES.update(&mut self.painter, pixels_nametables.get_pixels());
+            // Run emulator until a frame is ready. Emulation stops on the first error.
+            if self.emulation_error.is_none() {
+                if let Err(error) = self.emulator.step_frame() {
+                    error!("Emulation stopped: {}", error);
+                    self.emulation_error = Some(error);
+                }
+            }
+
+            // Queue audio. Samples are dropped if the queue grows too long so that latency stays low.
+            let samples = self.emulator.take_audio_samples();
+            let max_queued_bytes = AUDIO_MAX_QUEUED_FRAMES * samples.len() * std::mem::size_of::<f32>();
+            if (self.audio_queue.size() as usize) < max_queued_bytes {
+                self.audio_queue.queue_audio(&samples).unwrap();
+            }
+
+            // Update game screen. NSF files run without the PPU.
+            if let Some(ppu) = self.emulator.cpu.bus.ppu.as_mut() {
+                let display = &ppu.display;
+                TEXTURE_GAME.update(&mut self.painter, display.get_pixels());
+                // Update game screen texture
+                ppu.load_pattern_table_tiles_to_display(0x0000, &mut pixels_pattern_table_0);
+                ppu.load_pattern_table_tiles_to_display(0x1000, &mut pixels_pattern_table_1);
+                ppu.load_nametable_tiles_to_display(&mut pixels_nametables);
+                ppu.load_sprites_to_display(&mut pixels_sprites);
+                TEXTURE_PATTERN_TABLE_0.update(&mut self.painter, pixels_pattern_table_0.get_pixels());
+                TEXTURE_PATTERN_TABLE_1.update(&mut self.painter, pixels_pattern_table_1.get_pixels());
+                TEXTURE_PALETTES.update(&mut self.painter, &ppu.get_current_palettes_raw());
+                TEXTURE_NAMETABLES.update(&mut self.painter, pixels_nametables.get_pixels());
+                TEXTURE_SPRITES.update(&mut self.painter, pixels_sprites.get_pixels());
+            }
+
+            let nsf_file = self.emulator.nsf_file();
+            let song = self.emulator.song();
+            let mut selected_song = None;
 
             // Render
             let egui::FullOutput {
@@ -259,11 +318,31 @@ impl Gui {
                 textures_delta,
                 shapes,
             } = egui_context.run(inputs_to_egui, |ctx| {
+                if let Some(error) = self.emulation_error.as_ref() {
+                    egui::Window::new("Emulation stopped").show(ctx, |ui| {
+                        ui.label(error.to_string());
+                    });
+                }
                 egui::SidePanel::left("Settings").show(ctx, |ui| {
                     ui.checkbox(&mut show_pattern_table_0, "Show pattern table 0");
                     ui.checkbox(&mut show_pattern_table_1, "Show pattern table 1");
                     ui.checkbox(&mut show_palettes, "Show palettes");
                     ui.checkbox(&mut show_nametables, "Show nametables");
+                    ui.checkbox(&mut show_sprites, "Show sprites");
+                    if let (Some(nsf_file), Some(song)) = (nsf_file.as_ref(), song) {
+                        ui.separator();
+                        ui.label(format!("{} - {}", nsf_file.title, nsf_file.artist));
+                        let track_name = nsf_file.track_names.get(song as usize).map_or("", String::as_str);
+                        ui.label(format!("Song {} / {} {}", song + 1, nsf_file.song_count, track_name));
+                        ui.horizontal(|ui| {
+                            if ui.button("Previous").clicked() && song > 0 {
+                                selected_song = Some(song - 1);
+                            }
+                            if ui.button("Next").clicked() {
+                                selected_song = Some(song + 1);
+                            }
+                        });
+                    }
                 });
                 egui::CentralPanel::default().show(ctx, |ui| {
                     ui.horizontal(|ui| {
@@ -282,10 +361,20 @@ impl Gui {
                         if show_palettes {
                             self.ui_custom_texture_panel(ui, 24.0, &TEXTURE_PALETTES);
                         }
+                        if show_sprites {
+                            self.ui_custom_texture_panel(ui, 2.0, &TEXTURE_SPRITES);
+                        }
                     });
                 });
             });
 
+            if let Some(song) = selected_song {
+                if let Err(error) = self.emulator.select_song(song) {
+                    error!("Emulation stopped: {}", error);
+                    self.emulation_error = Some(error);
+                }
+            }
+
             //TODO:handle platform output
             //handle_platform_output(full_output.platform_output);
 
@@ -3,8 +3,24 @@
 
 mod gui;
 
+use std::env;
+use std::process;
+
 fn main() {
-    let mut gui = gui::Gui::new();
+    let rom_path = match env::args().nth(1) {
+        Some(rom_path) => rom_path,
+        None => {
+            eprintln!("Usage: nesemulator <path_to_rom>");
+            process::exit(1);
+        }
+    };
+    let mut gui = match gui::Gui::new(&rom_path) {
+        Ok(gui) => gui,
+        Err(error) => {
+            eprintln!("{}", error);
+            process::exit(1);
+        }
+    };
     gui.run();
 }
 